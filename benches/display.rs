@@ -0,0 +1,83 @@
+use chip_8::display::{self, Display};
+use chip_8::processor::Processor;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const SPRITE: [u8; 15] = [
+    0x3C, 0x7E, 0xFF, 0xDB, 0xFF, 0x7E, 0x3C, 0x18,
+    0x3C, 0x7E, 0xFF, 0xDB, 0xFF, 0x7E, 0x3C,
+];
+
+// LD I, 0x220; DRW V0, V1, 15; ADD V0, 3; ADD V1, 1; JP 0x202
+// with the sprite at 0x220, every other instruction draws
+const SPRITE_LOOP: [u8; 10] = [0xA2, 0x20, 0xD0, 0x1F, 0x70, 0x03, 0x71, 0x01, 0x12, 0x02];
+
+// the per-pixel bool display the packed rows replaced, kept for comparison
+struct BoolDisplay {
+    buffer: [[bool; display::WIDTH]; display::HEIGHT],
+}
+
+impl BoolDisplay {
+    fn draw(&mut self, starting_x: u8, starting_y: u8, memory: &[u8]) -> bool {
+        let mut pixel_turned_off = false;
+
+        for (byte_number, block) in memory.iter().enumerate() {
+            let y = (starting_y as usize + byte_number) % display::HEIGHT;
+
+            for bit_number in 0..8 {
+                let x = (starting_x as usize + bit_number) % display::WIDTH;
+                let current_pixel = self.buffer[y][x] as u8;
+
+                let current_bit = (block >> (7 - bit_number)) & 1;
+                let new_pixel = current_bit ^ current_pixel;
+
+                self.buffer[y][x] = new_pixel != 0;
+
+                if current_pixel == 1 && new_pixel == 0 {
+                    pixel_turned_off = true;
+                }
+            }
+        }
+        pixel_turned_off
+    }
+}
+
+fn sprite_draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sprite_draw");
+
+    group.bench_function("packed_rows", |b| {
+        let mut display = Display::new();
+        b.iter(|| {
+            for position in 0..64u8 {
+                black_box(display.draw(position * 3, position, black_box(&SPRITE)));
+            }
+        })
+    });
+
+    group.bench_function("bool_per_pixel", |b| {
+        let mut display = BoolDisplay { buffer: [[false; display::WIDTH]; display::HEIGHT] };
+        b.iter(|| {
+            for position in 0..64u8 {
+                black_box(display.draw(position * 3, position, black_box(&SPRITE)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn sprite_heavy_rom(c: &mut Criterion) {
+    let mut program = [0; 0x20 + SPRITE.len()];
+    program[..SPRITE_LOOP.len()].copy_from_slice(&SPRITE_LOOP);
+    program[0x20..].copy_from_slice(&SPRITE);
+
+    let mut cpu = Processor::new();
+    cpu.reset();
+    cpu.load_program(&program).unwrap();
+
+    c.bench_function("sprite_heavy_rom_1000_instructions", |b| {
+        b.iter(|| cpu.run_frame(1000).unwrap())
+    });
+}
+
+criterion_group!(benches, sprite_draw, sprite_heavy_rom);
+criterion_main!(benches);
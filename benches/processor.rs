@@ -0,0 +1,52 @@
+use chip_8::processor::{Backend, Processor};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+// instructions executed per benchmark iteration
+const INSTRUCTIONS: u64 = 100_000;
+const INSTRUCTIONS_PER_FRAME: usize = 1_000;
+
+const BACKENDS: &[(&str, Backend)] = &[
+    ("interpreter", Backend::Interpreter),
+    ("threaded", Backend::Threaded),
+    #[cfg(feature = "jit")]
+    ("jit", Backend::Jit),
+];
+
+const ROMS: [(&str, &[u8]); 3] = [
+    ("pong", include_bytes!("../roms/pong")),
+    ("test_opcode", include_bytes!("../roms/test_opcode.ch8")),
+    ("c8_test", include_bytes!("../roms/c8_test.c8")),
+];
+
+fn roms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rom");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+
+    for ((name, rom), (backend_name, backend)) in ROMS.iter().flat_map(|rom| BACKENDS.iter().map(move |backend| (rom, backend))) {
+        group.bench_function(format!("{}/{}", name, backend_name), |b| {
+            b.iter_batched_ref(
+                || {
+                    let mut cpu = Processor::new();
+                    cpu.set_backend(*backend);
+                    cpu.reset();
+                    cpu.load_program(rom).unwrap();
+                    // warm up so decoded and compiled blocks are in place
+                    for _ in 0..INSTRUCTIONS as usize / INSTRUCTIONS_PER_FRAME {
+                        cpu.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+                    }
+                    cpu
+                },
+                |cpu| {
+                    for _ in 0..INSTRUCTIONS as usize / INSTRUCTIONS_PER_FRAME {
+                        cpu.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+                    }
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, roms);
+criterion_main!(benches);
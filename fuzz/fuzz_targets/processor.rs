@@ -0,0 +1,41 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use chip_8::processor::Processor;
+
+const CYCLES: usize = 10_000;
+const CYCLES_PER_KEY_EVENT: usize = 64;
+
+// input layout: [key event count] [key events ...] [rom ...]
+// each key event toggles the hex key in its low nibble, press if bit 4 is set
+fuzz_target!(|data: &[u8]| {
+    let (key_count, rest) = match data.split_first() {
+        Some((n, rest)) => ((*n as usize).min(rest.len()), rest),
+        None => return,
+    };
+    let (key_events, rom) = rest.split_at(key_count);
+
+    let mut cpu = Processor::new();
+    cpu.reset();
+    if cpu.load_program(rom).is_err() {
+        return;
+    }
+
+    let mut key_events = key_events.iter();
+    for cycle in 0..CYCLES {
+        if cycle % CYCLES_PER_KEY_EVENT == 0 {
+            if let Some(event) = key_events.next() {
+                if event & 0x10 != 0 {
+                    cpu.keyboard.key_press(event & 0xF);
+                } else {
+                    cpu.keyboard.key_release(event & 0xF);
+                }
+            }
+        }
+
+        // faults must surface as errors, never as panics
+        if cpu.execute_cycle().is_err() {
+            return;
+        }
+    }
+});
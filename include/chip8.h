@@ -0,0 +1,95 @@
+/* generated by cbindgen from src/ffi.rs, do not edit */
+
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Pixels in a display row.
+#define CHIP8_WIDTH 64
+
+// Rows in the display.
+#define CHIP8_HEIGHT 32
+
+// What a call did, every error leaves the machine as it was before the call
+// unless noted otherwise.
+typedef enum {
+  CHIP8_STATUS_OK = 0,
+  // A pointer argument was null.
+  CHIP8_STATUS_NULL_POINTER,
+  // The rom does not fit in memory above 0x200.
+  CHIP8_STATUS_ROM_TOO_LARGE,
+  // The rom ran an opcode with no known meaning, the machine stopped on it.
+  CHIP8_STATUS_UNKNOWN_OPCODE,
+  // The rom called a subroutine with all 16 stack slots in use.
+  CHIP8_STATUS_STACK_OVERFLOW,
+  // The rom returned with an empty stack.
+  CHIP8_STATUS_STACK_UNDERFLOW,
+  // The save state was cut short, damaged or written by another version.
+  CHIP8_STATUS_INVALID_STATE,
+  // The buffer is smaller than `chip8_state_size()`.
+  CHIP8_STATUS_BUFFER_TOO_SMALL,
+  // Keys go from 0x0 to 0xF.
+  CHIP8_STATUS_INVALID_KEY,
+} Chip8Status;
+
+// A machine made by `chip8_create`. Handles are used from one thread at a
+// time and never after `chip8_destroy`. Buffers passed in must hold at
+// least the length given with them.
+typedef struct Chip8 Chip8;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// A machine with no rom loaded, freed with `chip8_destroy`.
+Chip8 *chip8_create(void);
+
+// Frees a machine, null is ignored.
+void chip8_destroy(Chip8 *machine);
+
+// Starts `length` bytes of rom from scratch, with the quirks and speed the
+// bundled database has for it.
+Chip8Status chip8_load_rom(Chip8 *machine, const uint8_t *rom, size_t length);
+
+// Runs one 60Hz frame. On an error the machine stops at the opcode that
+// failed, with the frame's earlier opcodes run.
+Chip8Status chip8_run_frame(Chip8 *machine);
+
+// Overrides the speed the database gave the rom.
+Chip8Status chip8_set_instructions_per_frame(Chip8 *machine, size_t instructions);
+
+// Presses or releases hex key `key`.
+Chip8Status chip8_set_key(Chip8 *machine, uint8_t key, bool pressed);
+
+// Makes the random numbers of CXNN repeat for the same seed.
+Chip8Status chip8_seed(Chip8 *machine, uint64_t seed);
+
+// `CHIP8_WIDTH * CHIP8_HEIGHT` bytes, one a pixel row by row from the top
+// left, 1 when lit. The pointer stays valid until the machine is
+// destroyed, its pixels change with each call that runs or loads.
+const uint8_t *chip8_framebuffer(const Chip8 *machine);
+
+// Whether the sound timer is running, the buzzer sounds while it does.
+bool chip8_sound_active(const Chip8 *machine);
+
+// Bytes in a save state.
+size_t chip8_state_size(void);
+
+// Writes a save state to the first `chip8_state_size()` bytes of `buffer`.
+Chip8Status chip8_save_state(const Chip8 *machine, uint8_t *buffer, size_t length);
+
+// Restores a save state from `chip8_save_state` out of the first
+// `chip8_state_size()` bytes of `buffer`, the speed set for the rom stays
+// as it is.
+Chip8Status chip8_load_state(Chip8 *machine, const uint8_t *buffer, size_t length);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHIP8_H */
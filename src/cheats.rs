@@ -0,0 +1,306 @@
+// ram search to find where a rom keeps things like lives and scores, and
+// cheats that write to those addresses. a search starts from every address
+// and each filter keeps the ones whose value compares as asked with the last
+// filter's, so losing a life then filtering for decreased narrows it down.
+//
+// cheats are kept one file a rom, named by the rom's sha1, one cheat a line
+// with the address and value in hex:
+//
+//   freeze 2f4 03 lives     written back every frame so the rom can't change it
+//   patch 300 a2            written once when the rom starts or the cheat is turned on
+//   off freeze 2f6 09       kept in the file but not applied
+
+use std::path::{Path, PathBuf};
+
+use crate::database::sha1;
+use crate::error::Error;
+use crate::processor::{Processor, ADDRESS_MASK};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    // the same as at the last filter
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u8),
+}
+
+pub struct Search {
+    // addresses still in the running and their values at the last filter
+    candidates: Vec<(u16, u8)>,
+}
+
+impl Search {
+    // every address, with the values `cpu` has now
+    pub fn new(cpu: &Processor) -> Search {
+        Search { candidates: cpu.memory().iter().enumerate().map(|(address, value)| (address as u16, *value)).collect() }
+    }
+
+    pub fn filter(&mut self, cpu: &Processor, filter: Filter) {
+        let memory = cpu.memory();
+        self.candidates.retain_mut(|(address, last)| {
+            let value = memory[*address as usize];
+            let keep = match filter {
+                Filter::Equal => value == *last,
+                Filter::Changed => value != *last,
+                Filter::Increased => value > *last,
+                Filter::Decreased => value < *last,
+                Filter::Value(wanted) => value == wanted,
+            };
+            *last = value;
+            keep
+        });
+    }
+
+    pub fn candidates(&self) -> &[(u16, u8)] {
+        &self.candidates
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Freeze,
+    Patch,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cheat {
+    pub kind: Kind,
+    pub address: u16,
+    pub value: u8,
+    pub enabled: bool,
+    pub description: String,
+    // patches are written once until turned on again or the rom restarts
+    applied: bool,
+}
+
+impl Cheat {
+    pub fn new(kind: Kind, address: u16, value: u8, description: &str) -> Cheat {
+        Cheat { kind, address: address & ADDRESS_MASK, value, enabled: true, description: description.to_string(), applied: false }
+    }
+}
+
+#[derive(Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new() -> Cheats {
+        Cheats::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Cheats, Error> {
+        let mut cheats = Vec::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            let error = |message: &str| Error::Cheats { line: index + 1, message: message.to_string() };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // fields are split on any run of whitespace, and the description
+            // is the rest of the line as written
+            let mut rest = line;
+            let mut field = || {
+                let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                rest = tail.trim_start();
+                field
+            };
+            let mut kind = field();
+            let enabled = kind != "off";
+            if !enabled {
+                kind = field();
+            }
+            let kind = match kind {
+                "freeze" => Kind::Freeze,
+                "patch" => Kind::Patch,
+                _ => return Err(error("expected freeze or patch")),
+            };
+            let address = u16::from_str_radix(field(), 16)
+                .ok()
+                .filter(|address| *address <= ADDRESS_MASK)
+                .ok_or_else(|| error("address is not in memory"))?;
+            let value = u8::from_str_radix(field(), 16).map_err(|_| error("value is not a hex byte"))?;
+            let mut cheat = Cheat::new(kind, address, value, rest);
+            cheat.enabled = enabled;
+            cheats.push(cheat);
+        }
+        Ok(Cheats { cheats })
+    }
+
+    // the cheats saved for `rom` in `dir`, none when it has no file yet
+    pub fn load(dir: &Path, rom: &[u8]) -> Result<Cheats, Error> {
+        let path = path(dir, rom);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Cheats::parse(&text),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Cheats::new()),
+            Err(error) => Err(Error::File { path, error }),
+        }
+    }
+
+    pub fn save(&self, dir: &Path, rom: &[u8]) -> Result<(), Error> {
+        std::fs::create_dir_all(dir).map_err(|error| Error::File { path: dir.to_path_buf(), error })?;
+        let path = path(dir, rom);
+        std::fs::write(&path, self.to_text()).map_err(|error| Error::File { path, error })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for cheat in self.cheats.iter() {
+            let kind = match cheat.kind {
+                Kind::Freeze => "freeze",
+                Kind::Patch => "patch",
+            };
+            let line = format!("{}{} {:03x} {:02x} {}", if cheat.enabled { "" } else { "off " }, kind, cheat.address, cheat.value, cheat.description);
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    // the cheat at `index`, if there was one
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        (index < self.cheats.len()).then(|| self.cheats.remove(index))
+    }
+
+    // false when there is no cheat at `index`
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> bool {
+        match self.cheats.get_mut(index) {
+            Some(cheat) => {
+                cheat.enabled = enabled;
+                cheat.applied = false;
+                true
+            },
+            None => false,
+        }
+    }
+
+    // has the patches written again, for a rom started over
+    pub fn restart(&mut self) {
+        for cheat in self.cheats.iter_mut() {
+            cheat.applied = false;
+        }
+    }
+
+    // writes the cheats due, called before each frame. memory already
+    // holding the value is left alone so decoded code stays good
+    pub fn apply(&mut self, cpu: &mut Processor) {
+        for cheat in self.cheats.iter_mut().filter(|cheat| cheat.enabled) {
+            if cheat.kind == Kind::Patch && cheat.applied {
+                continue;
+            }
+            if cpu.memory()[cheat.address as usize] != cheat.value {
+                cpu.write_memory(cheat.address, &[cheat.value]);
+            }
+            cheat.applied = true;
+        }
+    }
+}
+
+// the file in `dir` holding the cheats of `rom`
+pub fn path(dir: &Path, rom: &[u8]) -> PathBuf {
+    dir.join(format!("{}.cheats", sha1(rom)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processor() -> Processor {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu
+    }
+
+    #[test]
+    fn searches_narrow_down_to_the_changing_byte() {
+        let mut cpu = processor();
+        cpu.write_memory(0x300, &[3, 100]);
+        let mut search = Search::new(&cpu);
+        assert_eq!(search.candidates().len(), 4096, "a search starts from all of memory");
+
+        search.filter(&cpu, Filter::Value(3));
+        assert!(search.candidates().contains(&(0x300, 3)));
+        cpu.write_memory(0x300, &[2, 101]);
+        search.filter(&cpu, Filter::Decreased);
+        assert_eq!(search.candidates(), [(0x300, 2)], "losing a life finds the lives");
+
+        search.filter(&cpu, Filter::Equal);
+        assert_eq!(search.candidates(), [(0x300, 2)]);
+        search.filter(&cpu, Filter::Changed);
+        assert!(search.candidates().is_empty());
+
+        let mut search = Search::new(&cpu);
+        cpu.write_memory(0x301, &[102]);
+        search.filter(&cpu, Filter::Increased);
+        assert_eq!(search.candidates(), [(0x301, 102)], "scores going up are found");
+    }
+
+    #[test]
+    fn freezes_hold_and_patches_write_once() {
+        let mut cpu = processor();
+        let mut cheats = Cheats::new();
+        cheats.add(Cheat::new(Kind::Freeze, 0x300, 9, "lives"));
+        cheats.add(Cheat::new(Kind::Patch, 0x400, 0xA2, ""));
+        cheats.apply(&mut cpu);
+        assert_eq!((cpu.memory()[0x300], cpu.memory()[0x400]), (9, 0xA2));
+
+        cpu.write_memory(0x300, &[1]);
+        cpu.write_memory(0x400, &[0]);
+        cheats.apply(&mut cpu);
+        assert_eq!((cpu.memory()[0x300], cpu.memory()[0x400]), (9, 0), "freezes are written back, patches aren't");
+
+        cheats.restart();
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x400], 0xA2, "patches are written again after a restart");
+
+        assert!(cheats.set_enabled(0, false));
+        cpu.write_memory(0x300, &[1]);
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x300], 1, "cheats turned off are left out");
+        assert!(!cheats.set_enabled(5, true));
+        assert_eq!(cheats.remove(1).map(|cheat| cheat.address), Some(0x400));
+        assert!(cheats.remove(1).is_none());
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let text = "# pong\nfreeze 2f4 03 lives left\noff patch 300 a2\n";
+        let cheats = Cheats::parse(text).unwrap();
+        assert_eq!(cheats.cheats()[0], Cheat::new(Kind::Freeze, 0x2F4, 3, "lives left"));
+        assert!(!cheats.cheats()[1].enabled);
+        assert_eq!(cheats.to_text(), "freeze 2f4 03 lives left\noff patch 300 a2\n");
+
+        assert!(matches!(Cheats::parse("melt 2f4 03"), Err(Error::Cheats { line: 1, .. })));
+        assert!(matches!(Cheats::parse("\nfreeze 1000 03"), Err(Error::Cheats { line: 2, .. })));
+        assert!(matches!(Cheats::parse("patch 200 100"), Err(Error::Cheats { .. })));
+
+        let spaced = Cheats::parse("off\tfreeze  2f4 \t03   lives  left").unwrap();
+        assert_eq!(spaced.cheats()[0].description, "lives  left", "fields may be spaced out and the description is kept as written");
+        assert_eq!((spaced.cheats()[0].address, spaced.cheats()[0].value, spaced.cheats()[0].enabled), (0x2F4, 3, false));
+
+        let dir = std::env::temp_dir().join(format!("chip_8_cheats_{}", std::process::id()));
+        let rom = include_bytes!("../roms/pong");
+        assert!(Cheats::load(&dir, rom).unwrap().cheats().is_empty(), "roms without a file have no cheats");
+        cheats.save(&dir, rom).unwrap();
+        assert!(path(&dir, rom).ends_with(format!("{}.cheats", sha1(rom))), "files are named by the rom's hash");
+        assert_eq!(Cheats::load(&dir, rom).unwrap().cheats(), cheats.cheats());
+
+        // a file where the directory should be
+        let blocked = dir.join("blocked");
+        std::fs::write(&blocked, "").unwrap();
+        assert!(matches!(cheats.save(&blocked, rom), Err(Error::File { path, .. }) if path == blocked), "failed saves name the directory");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
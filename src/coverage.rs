@@ -0,0 +1,265 @@
+// how a rom used each byte of memory: run as code, drawn as sprites, or read
+// and written by FX33/FX55/FX65. the marks are exported as a text report of
+// address ranges and as a png image with one colored cell per byte
+
+use crate::symbols::Symbols;
+use std::fmt::Write;
+
+pub const EXECUTED: u8 = 1;
+pub const SPRITE: u8 = 2;
+pub const READ: u8 = 4;
+pub const WRITTEN: u8 = 8;
+
+const KINDS: [(u8, &str); 4] = [(EXECUTED, "executed"), (SPRITE, "sprite"), (READ, "read"), (WRITTEN, "written")];
+
+// the image is a grid of `COLUMNS` bytes per row with each byte a square
+// of `CELL` pixels
+const COLUMNS: usize = 64;
+const CELL: usize = 8;
+
+pub struct Coverage {
+    marks: Vec<u8>,
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage { marks: vec![0; 0x1000] }
+    }
+
+    // the kinds of use seen at `address`, a set of the flags above
+    pub fn marks(&self, address: u16) -> u8 {
+        self.marks[(address & 0xFFF) as usize]
+    }
+
+    // marks what `opcode` at `pc` touched, with I at `index` before it ran
+    pub(crate) fn record(&mut self, pc: u16, opcode: u16, index: u16) {
+        self.mark(pc, 2, EXECUTED);
+        let x = (opcode & 0x0F00) >> 8;
+        match (opcode >> 12, opcode & 0x00FF) {
+            (0xD, _) => self.mark(index, opcode & 0x000F, SPRITE),
+            (0xF, 0x33) => self.mark(index, 3, WRITTEN),
+            (0xF, 0x55) => self.mark(index, x + 1, WRITTEN),
+            (0xF, 0x65) => self.mark(index, x + 1, READ),
+            _ => (),
+        }
+    }
+
+    fn mark(&mut self, start: u16, length: u16, kind: u8) {
+        for offset in 0..length {
+            self.marks[((start + offset) & 0xFFF) as usize] |= kind;
+        }
+    }
+
+    // byte counts for each kind, then every run of bytes used the same way
+    // with the label it starts at
+    pub fn report(&self, symbols: &Symbols) -> String {
+        let mut report = String::new();
+        for (kind, name) in KINDS.iter() {
+            let count = self.marks.iter().filter(|marks| *marks & kind != 0).count();
+            let _ = writeln!(report, "{:<10} {:>5} bytes", name, count);
+        }
+        let untouched = self.marks.iter().filter(|marks| **marks == 0).count();
+        let _ = writeln!(report, "{:<10} {:>5} bytes\n", "untouched", untouched);
+
+        let mut start = 0;
+        while start < self.marks.len() {
+            let marks = self.marks[start];
+            let length = self.marks[start..].iter().take_while(|other| **other == marks).count();
+            let end = start + length - 1;
+            let label = symbols.name(start as u16).unwrap_or_default();
+            let line = format!("{:03X}-{:03X}  {:<24} {}", start, end, describe(marks), label);
+            let _ = writeln!(report, "{}", line.trim_end());
+            start = end + 1;
+        }
+        report
+    }
+
+    // memory as a png of `COLUMNS` bytes a row, each kind its own color
+    pub fn image(&self) -> Vec<u8> {
+        let width = COLUMNS * CELL;
+        let height = self.marks.len() / COLUMNS * CELL;
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend_from_slice(&color(self.marks[y / CELL * COLUMNS + x / CELL]));
+            }
+        }
+        png(width, height, &pixels)
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Coverage {
+        Coverage::new()
+    }
+}
+
+fn describe(marks: u8) -> String {
+    let names: Vec<&str> = KINDS.iter().filter(|(kind, _)| marks & kind != 0).map(|(_, name)| *name).collect();
+    if names.is_empty() {
+        "untouched".to_string()
+    } else {
+        names.join("+")
+    }
+}
+
+// code that is also used as data stands out from both
+fn color(marks: u8) -> [u8; 3] {
+    match marks {
+        0 => [0x20, 0x20, 0x20],
+        _ if marks & EXECUTED != 0 && marks != EXECUTED => [0xD0, 0x40, 0xD0],
+        EXECUTED => [0x30, 0xC0, 0x40],
+        _ if marks & WRITTEN != 0 => [0xF0, 0x40, 0x40],
+        _ if marks & SPRITE != 0 => [0x40, 0x80, 0xF0],
+        _ => [0xE0, 0xD0, 0x40],
+    }
+}
+
+// an rgb png with the image data stored uncompressed, so no deflate encoder
+// is needed
+fn png(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut rows = Vec::with_capacity(height * (width * 3 + 1));
+    for row in pixels.chunks(width * 3) {
+        // no filter
+        rows.push(0);
+        rows.extend_from_slice(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = rows.chunks(0xFFFF).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        zlib.push((index == blocks.len() - 1) as u8);
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&rows).to_be_bytes());
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bit rgb, deflate, no filtering method or interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib);
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// the crc-32 png chunks and bps patches carry
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{Backend, Processor};
+
+    // draws a sprite, stores and loads registers, then loops
+    const PROGRAM: [u8; 16] = [0xA2, 0x0C, 0xD0, 0x02, 0xA3, 0x00, 0xF1, 0x55, 0xF1, 0x65, 0x12, 0x0A, 0xF0, 0x90, 0x00, 0x00];
+
+    fn covered() -> Processor {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.set_backend(Backend::Threaded);
+        cpu.load_program(&PROGRAM).unwrap();
+        cpu.set_coverage(true);
+        cpu.run_frame(10).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn memory_use_is_marked() {
+        let cpu = covered();
+        let coverage = cpu.coverage().unwrap();
+        assert_eq!(coverage.marks(0x200), EXECUTED, "opcodes are executed");
+        assert_eq!(coverage.marks(0x20B), EXECUTED, "both opcode bytes are marked");
+        assert_eq!((coverage.marks(0x20C), coverage.marks(0x20D)), (SPRITE, SPRITE), "the sprite rows are marked");
+        assert_eq!(coverage.marks(0x20E), 0, "bytes past the sprite are untouched");
+        assert_eq!((coverage.marks(0x300), coverage.marks(0x301)), (WRITTEN | READ, WRITTEN | READ), "V0 and V1 are stored and loaded");
+        assert_eq!(coverage.marks(0x302), 0, "only two registers are stored");
+    }
+
+    #[test]
+    fn coverage_is_optional() {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.load_program(&PROGRAM).unwrap();
+        cpu.run_frame(10).unwrap();
+        assert!(cpu.coverage().is_none(), "nothing is recorded by default");
+
+        let mut cpu = covered();
+        cpu.set_coverage(false);
+        assert!(cpu.coverage().is_none(), "coverage can be turned off");
+    }
+
+    #[test]
+    fn failed_opcodes_are_not_marked() {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.load_program(&[0x60, 0x01, 0xFF, 0xFF]).unwrap();
+        cpu.set_coverage(true);
+        assert!(cpu.run_frame(2).is_err());
+        let coverage = cpu.coverage().unwrap();
+        assert_eq!(coverage.marks(0x200), EXECUTED, "the opcode before it ran");
+        assert_eq!(coverage.marks(0x202), 0, "the unknown opcode never ran");
+    }
+
+    #[test]
+    fn report_lists_ranges() {
+        let symbols = Symbols::parse("20c ball\n", std::path::Path::new("")).unwrap();
+        let report = covered().coverage().unwrap().report(&symbols);
+        assert!(report.starts_with("executed      12 bytes\nsprite         2 bytes\n"), "bytes are counted by kind");
+        assert!(report.contains("200-20B  executed\n"), "the code is one range");
+        assert!(report.contains("20C-20D  sprite                   ball\n"), "ranges are named by label");
+        assert!(report.contains("300-301  read+written\n"), "combined kinds are listed together");
+        assert!(report.ends_with("302-FFF  untouched\n"), "the rest of memory is untouched");
+    }
+
+    #[test]
+    fn image_is_a_png() {
+        let image = covered().coverage().unwrap().image();
+        assert!(image.starts_with(b"\x89PNG\r\n\x1a\n"), "the png signature comes first");
+        assert_eq!(&image[12..24], b"IHDR\x00\x00\x02\x00\x00\x00\x02\x00", "the image is 512 pixels square");
+        assert_eq!(crc32(&image[12..29]), u32::from_be_bytes([image[29], image[30], image[31], image[32]]), "chunks carry their crc");
+        let rows: usize = 512 * (512 * 3 + 1);
+        let blocks = rows.div_ceil(0xFFFF);
+        assert_eq!(image.len(), 8 + 25 + 12 + 2 + blocks * 5 + rows + 4 + 12, "the rows are stored uncompressed");
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926, "crc32 matches the standard check value");
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082, "the empty IEND chunk has its well known crc");
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
@@ -0,0 +1,800 @@
+// debug adapter protocol server so editors can run a rom under their own
+// debugger ui. requests arrive on stdin and replies go out on stdout, with
+// the server polled from the frontend's loop like the gdb stub. breakpoints
+// go on assembler source lines and labels from a symbol file or on raw
+// addresses
+
+use crate::error::Error;
+use crate::processor::Processor;
+use crate::symbols::Symbols;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+// the processor is the only thread
+const THREAD: u64 = 1;
+
+// variable references of the scopes
+const REGISTERS: u64 = 1;
+const STACK: u64 = 2;
+
+// what the frontend has to do for the editor
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    // load the rom and report back with `launched`
+    Load(PathBuf),
+    // the editor ended the session
+    Quit,
+}
+
+// a step is over once the stack is no deeper than `depth` and execution has
+// left `line`
+#[derive(Debug)]
+struct Step {
+    depth: u8,
+    line: Option<(PathBuf, usize)>,
+}
+
+pub struct Server<W: Write> {
+    requests: Receiver<Value>,
+    output: W,
+    // number of the next message sent
+    sequence: u64,
+    symbols: Symbols,
+    // replaced as a whole for a source by every setBreakpoints
+    source_breakpoints: HashMap<PathBuf, Vec<u16>>,
+    function_breakpoints: Vec<u16>,
+    instruction_breakpoints: Vec<u16>,
+    // every address above
+    breakpoints: BTreeSet<u16>,
+    // launch request waiting for the frontend to load its rom
+    launching: Option<Value>,
+    stop_on_entry: bool,
+    // the processor only runs while this is false
+    halted: bool,
+    // set by a continue or step so the opcode under a breakpoint runs once
+    resuming: bool,
+    step: Option<Step>,
+    // instructions run since the timers last ticked
+    progress: usize,
+}
+
+impl Server<io::Stdout> {
+    pub fn stdio() -> Server<io::Stdout> {
+        Server::new(io::stdin(), io::stdout())
+    }
+}
+
+impl<W: Write> Server<W> {
+    // requests are read from `input` on a thread of their own so polling
+    // never blocks the frontend. the processor waits for the editor to launch
+    pub fn new<R: Read + Send + 'static>(input: R, output: W) -> Server<W> {
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            let mut input = BufReader::new(input);
+            loop {
+                match read_message(&mut input) {
+                    Ok(Some(message)) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    },
+                    Err(e) if e.kind() == ErrorKind::InvalidData => (),
+                    _ => break,
+                }
+            }
+        });
+        Server {
+            requests,
+            output,
+            sequence: 1,
+            symbols: Symbols::new(),
+            source_breakpoints: HashMap::new(),
+            function_breakpoints: Vec::new(),
+            instruction_breakpoints: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            launching: None,
+            stop_on_entry: false,
+            halted: true,
+            resuming: false,
+            step: None,
+            progress: 0,
+        }
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    // answers the requests that arrived since the last poll, stopping at one
+    // the frontend has to act on
+    pub fn poll(&mut self, cpu: &mut Processor) -> Option<Action> {
+        while self.launching.is_none() {
+            match self.requests.try_recv() {
+                Ok(request) => {
+                    if let Some(action) = self.handle(cpu, &request) {
+                        return Some(action);
+                    }
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Some(Action::Quit),
+            }
+        }
+        None
+    }
+
+    // finishes a launch once the frontend tried loading the rom. roms loaded
+    // any other way just keep running
+    pub fn launched(&mut self, result: &Result<(), Error>) {
+        if let Some(request) = self.launching.take() {
+            match result {
+                Ok(()) => {
+                    self.respond(&request, Ok(Value::Null));
+                    // breakpoints are set now and the processor waits for configurationDone
+                    self.halted = true;
+                    self.event("initialized", Value::Null);
+                },
+                Err(e) => self.respond(&request, Err(e.to_string())),
+            }
+        }
+    }
+
+    // tells the editor the session is over when the frontend closes
+    pub fn terminate(&mut self) {
+        self.event("terminated", Value::Null);
+    }
+
+    // runs what is left of a frame of `instructions` instructions while the
+    // editor lets the processor run, stopping early at breakpoints and at the
+    // end of steps. errors stop the processor as exceptions
+    pub fn run_frame(&mut self, cpu: &mut Processor, instructions: usize) {
+        if !self.halted && self.breakpoints.is_empty() && self.step.is_none() && self.progress == 0 {
+            self.resuming = false;
+            if let Err(e) = cpu.run_frame(instructions) {
+                self.stop("exception", Some(self.symbols.explain(&e)));
+            }
+            return;
+        }
+
+        while !self.halted && self.progress < instructions {
+            if !self.run_one(cpu) {
+                break;
+            }
+            self.progress += 1;
+        }
+        if self.progress >= instructions {
+            cpu.tick_timers();
+            self.progress = 0;
+        }
+    }
+
+    // runs a single opcode for the frontend's step key, unless the editor
+    // has the processor stopped. breakpoints and errors stop it as in a frame
+    pub fn step(&mut self, cpu: &mut Processor) {
+        if !self.halted {
+            self.run_one(cpu);
+        }
+    }
+
+    // runs the opcode at the program counter unless a breakpoint is there,
+    // false if the processor stopped before it ran
+    fn run_one(&mut self, cpu: &mut Processor) -> bool {
+        if self.breakpoints.contains(&cpu.program_counter()) && !self.resuming {
+            self.step = None;
+            self.stop("breakpoint", None);
+            return false;
+        }
+        self.resuming = false;
+        if let Err(e) = cpu.step() {
+            self.step = None;
+            self.stop("exception", Some(self.symbols.explain(&e)));
+            return false;
+        }
+        if self.step.as_ref().is_some_and(|step| self.stepped(cpu, step)) {
+            self.step = None;
+            self.stop("step", None);
+        }
+        true
+    }
+
+    fn handle(&mut self, cpu: &mut Processor, request: &Value) -> Option<Action> {
+        if request["type"] != "request" {
+            return None;
+        }
+        let arguments = &request["arguments"];
+        let result = match request["command"].as_str().unwrap_or("") {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsSetVariable": true,
+                "supportsFunctionBreakpoints": true,
+                "supportsInstructionBreakpoints": true,
+                "supportsSteppingGranularity": true,
+            })),
+            "launch" => {
+                let program = match arguments["program"].as_str() {
+                    Some(program) => PathBuf::from(program),
+                    None => {
+                        self.respond(request, Err("launch needs a program".to_string()));
+                        return None;
+                    },
+                };
+                if let Some(path) = arguments["symbols"].as_str() {
+                    match Symbols::load(path) {
+                        Ok(symbols) => self.symbols = symbols,
+                        Err(e) => {
+                            self.respond(request, Err(e.to_string()));
+                            return None;
+                        },
+                    }
+                }
+                self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+                self.launching = Some(request.clone());
+                return Some(Action::Load(program));
+            },
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(arguments),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments),
+            "configurationDone" => {
+                self.respond(request, Ok(Value::Null));
+                if self.stop_on_entry {
+                    self.stop("entry", None);
+                } else {
+                    self.halted = false;
+                }
+                return None;
+            },
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD, "name": "chip-8" }] })),
+            "stackTrace" => Ok(self.stack_trace(cpu, arguments)),
+            "scopes" => Ok(json!({ "scopes": [
+                { "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+                { "name": "Stack", "variablesReference": STACK, "expensive": false },
+            ] })),
+            "variables" => Ok(variables(cpu, arguments["variablesReference"].as_u64())),
+            "setVariable" => set_variable(cpu, arguments),
+            "continue" => {
+                self.resume(None);
+                Ok(json!({ "allThreadsContinued": true }))
+            },
+            command @ ("next" | "stepIn" | "stepOut") => {
+                let line = match arguments["granularity"].as_str() {
+                    Some("instruction") => None,
+                    _ => self.symbols.location(cpu.program_counter()).map(|(file, line)| (file.to_path_buf(), line)),
+                };
+                let depth = cpu.stack_pointer();
+                // stepping out of the outermost frame runs on like a continue
+                let step = match command {
+                    "next" => Some(Step { depth, line }),
+                    "stepIn" => Some(Step { depth: u8::MAX, line }),
+                    _ => depth.checked_sub(1).map(|depth| Step { depth, line: None }),
+                };
+                self.resume(step);
+                Ok(Value::Null)
+            },
+            "pause" => {
+                self.respond(request, Ok(Value::Null));
+                self.step = None;
+                self.stop("pause", None);
+                return None;
+            },
+            "disconnect" => {
+                self.respond(request, Ok(Value::Null));
+                return Some(Action::Quit);
+            },
+            _ => Err("unsupported request".to_string()),
+        };
+        self.respond(request, result);
+        None
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["source"]["path"].as_str().map(PathBuf::from).ok_or("breakpoints need a source path")?;
+        let requested = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+
+        let mut addresses = Vec::new();
+        let breakpoints: Vec<Value> = requested
+            .iter()
+            .map(|breakpoint| {
+                let line = breakpoint["line"].as_u64().unwrap_or(0) as usize;
+                match self.symbols.address(&path, line) {
+                    Some((address, line)) => {
+                        addresses.push(address);
+                        json!({ "id": address, "verified": true, "line": line, "instructionReference": reference(address) })
+                    },
+                    None => json!({ "verified": false, "line": line, "message": "no code at this line" }),
+                }
+            })
+            .collect();
+
+        self.source_breakpoints.insert(path, addresses);
+        self.update_breakpoints();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn set_function_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let requested = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+
+        self.function_breakpoints.clear();
+        let breakpoints: Vec<Value> = requested
+            .iter()
+            .map(|breakpoint| match breakpoint["name"].as_str().and_then(|name| self.symbols.label(name)) {
+                Some(address) => {
+                    self.function_breakpoints.push(address);
+                    json!({ "id": address, "verified": true, "instructionReference": reference(address) })
+                },
+                None => json!({ "verified": false, "message": "no such label" }),
+            })
+            .collect();
+
+        self.update_breakpoints();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let requested = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+
+        self.instruction_breakpoints.clear();
+        let breakpoints: Vec<Value> = requested
+            .iter()
+            .map(|breakpoint| {
+                let address = breakpoint["instructionReference"]
+                    .as_str()
+                    .and_then(parse_number)
+                    .map(|address| address as i64 + breakpoint["offset"].as_i64().unwrap_or(0))
+                    .filter(|address| (0..0x1000).contains(address));
+                match address {
+                    Some(address) => {
+                        self.instruction_breakpoints.push(address as u16);
+                        json!({ "id": address, "verified": true, "instructionReference": reference(address as u16) })
+                    },
+                    None => json!({ "verified": false, "message": "address is not in memory" }),
+                }
+            })
+            .collect();
+
+        self.update_breakpoints();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn update_breakpoints(&mut self) {
+        self.breakpoints = self
+            .source_breakpoints
+            .values()
+            .flatten()
+            .chain(&self.function_breakpoints)
+            .chain(&self.instruction_breakpoints)
+            .copied()
+            .collect();
+    }
+
+    // the innermost frame is at the program counter and each one out is at
+    // the call below a return address on the stack
+    fn stack_trace(&self, cpu: &Processor, arguments: &Value) -> Value {
+        let calls = cpu.call_stack();
+        let mut addresses = vec![cpu.program_counter()];
+        addresses.extend(calls.iter().rev().map(|address| address.wrapping_sub(2) & 0xFFF));
+
+        let frames: Vec<Value> = addresses
+            .iter()
+            .enumerate()
+            .map(|(index, address)| {
+                // a frame runs the subroutine called from the frame outside it
+                let name = match addresses.get(index + 1) {
+                    Some(call) => {
+                        let subroutine = cpu.opcode_at(*call) & 0xFFF;
+                        match self.symbols.name(subroutine) {
+                            Some(name) => name.to_string(),
+                            None => format!("sub_{:03x}", subroutine),
+                        }
+                    },
+                    None => "main".to_string(),
+                };
+                let mut frame = json!({
+                    "id": index,
+                    "name": name,
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": reference(*address),
+                });
+                if let Some((file, line)) = self.symbols.location(*address) {
+                    frame["source"] = json!({
+                        "name": file.file_name().map(|name| name.to_string_lossy()),
+                        "path": file.to_string_lossy(),
+                    });
+                    frame["line"] = json!(line);
+                    frame["column"] = json!(1);
+                }
+                frame
+            })
+            .collect();
+
+        let start = (arguments["startFrame"].as_u64().unwrap_or(0) as usize).min(frames.len());
+        let levels = match arguments["levels"].as_u64() {
+            Some(levels) if levels > 0 => levels as usize,
+            _ => frames.len(),
+        };
+        json!({
+            "stackFrames": frames.iter().skip(start).take(levels).collect::<Vec<_>>(),
+            "totalFrames": frames.len(),
+        })
+    }
+
+    fn stepped(&self, cpu: &Processor, step: &Step) -> bool {
+        cpu.stack_pointer() <= step.depth
+            && step.line.as_ref().is_none_or(|(file, line)| self.symbols.location(cpu.program_counter()) != Some((file.as_path(), *line)))
+    }
+
+    fn resume(&mut self, step: Option<Step>) {
+        self.halted = false;
+        self.resuming = true;
+        self.step = step;
+    }
+
+    // halts the processor and tells the editor why
+    fn stop(&mut self, reason: &str, text: Option<String>) {
+        self.halted = true;
+        let mut body = json!({ "reason": reason, "threadId": THREAD, "allThreadsStopped": true });
+        if let Some(text) = text {
+            body["description"] = json!(text);
+            body["text"] = json!(text);
+        }
+        self.event("stopped", body);
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(Value::Null) => (),
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response);
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        let mut message = json!({ "type": "event", "event": event });
+        if !body.is_null() {
+            message["body"] = body;
+        }
+        self.send(message);
+    }
+
+    fn send(&mut self, mut message: Value) {
+        message["seq"] = json!(self.sequence);
+        self.sequence += 1;
+        // an editor that went away shows up as the end of the requests
+        let _ = write_message(&mut self.output, &message);
+    }
+}
+
+fn variables(cpu: &Processor, reference: Option<u64>) -> Value {
+    let values: Vec<(String, String)> = match reference {
+        Some(REGISTERS) => {
+            let mut values: Vec<(String, String)> =
+                cpu.registers().iter().enumerate().map(|(index, value)| (format!("V{:X}", index), format!("{:#04x}", value))).collect();
+            values.push(("I".to_string(), format!("{:#05x}", cpu.index_register())));
+            values.push(("PC".to_string(), format!("{:#05x}", cpu.program_counter())));
+            values.push(("SP".to_string(), cpu.stack_pointer().to_string()));
+            values.push(("DT".to_string(), cpu.delay_timer().to_string()));
+            values.push(("ST".to_string(), cpu.sound_timer().to_string()));
+            values
+        },
+        Some(STACK) => cpu.call_stack().iter().enumerate().map(|(index, address)| (index.to_string(), format!("{:#05x}", address))).collect(),
+        _ => Vec::new(),
+    };
+    let variables: Vec<Value> = values.into_iter().map(|(name, value)| json!({ "name": name, "value": value, "variablesReference": 0 })).collect();
+    json!({ "variables": variables })
+}
+
+// registers take values in hex with a 0x prefix or in decimal
+fn set_variable(cpu: &mut Processor, arguments: &Value) -> Result<Value, String> {
+    if arguments["variablesReference"].as_u64() != Some(REGISTERS) {
+        return Err("only registers can be set".to_string());
+    }
+    let name = arguments["name"].as_str().unwrap_or("");
+    let value = arguments["value"].as_str().and_then(parse_number).ok_or("value is not a number")?;
+    let value = match name {
+        "I" | "PC" if value < 0x1000 => {
+            if name == "I" {
+                cpu.set_index_register(value as u16);
+            } else {
+                cpu.set_program_counter(value as u16);
+            }
+            format!("{:#05x}", value)
+        },
+        "SP" if value <= 16 => {
+            cpu.set_stack_pointer(value as u8);
+            value.to_string()
+        },
+        _ => match name.strip_prefix('V').and_then(|index| usize::from_str_radix(index, 16).ok()) {
+            Some(index) if index < 16 && value <= 0xFF => {
+                cpu.set_register(index, value as u8);
+                format!("{:#04x}", value)
+            },
+            _ => return Err(format!("{} cannot be set to {}", name, value)),
+        },
+    };
+    Ok(json!({ "value": value }))
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn reference(address: u16) -> String {
+    format!("{:#05x}", address)
+}
+
+// one message after its `Content-Length` header, none at the end of the input
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        match header.trim() {
+            "" if length.is_some() => break,
+            header => {
+                if let Some(value) = header.strip_prefix("Content-Length:") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            },
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // main calls a subroutine of two opcodes on one line in a loop
+    const PROGRAM: [u8; 14] = [0x60, 0x01, 0x22, 0x08, 0x70, 0x01, 0x12, 0x02, 0x61, 0x05, 0x71, 0x01, 0x00, 0xEE];
+    const SYMBOLS: &str = "200 main\n208 bump\n200 prog.8o:1\n202 prog.8o:2\n204 prog.8o:3\n206 prog.8o:4\n208 prog.8o:6\n20a prog.8o:6\n20c prog.8o:7\n";
+
+    // a server stopped on entry into `PROGRAM` with its symbols
+    fn launch() -> (Server<Vec<u8>>, Processor) {
+        let mut server = Server::new(io::empty(), Vec::new());
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.load_program(&PROGRAM).unwrap();
+        server.symbols = Symbols::parse(SYMBOLS, Path::new("/src")).unwrap();
+        server.stop_on_entry = true;
+        request(&mut server, &mut cpu, "configurationDone", Value::Null);
+        (server, cpu)
+    }
+
+    // messages the server sent since the last call
+    fn sent(server: &mut Server<Vec<u8>>) -> Vec<Value> {
+        let mut input = &server.output[..];
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut input).unwrap() {
+            messages.push(message);
+        }
+        server.output.clear();
+        messages
+    }
+
+    fn request(server: &mut Server<Vec<u8>>, cpu: &mut Processor, command: &str, arguments: Value) -> Vec<Value> {
+        server.handle(cpu, &json!({ "seq": 7, "type": "request", "command": command, "arguments": arguments }));
+        sent(server)
+    }
+
+    // runs frames until the processor stops and returns the stop reason
+    fn run_until_stopped(server: &mut Server<Vec<u8>>, cpu: &mut Processor) -> String {
+        for _ in 0..100 {
+            server.run_frame(cpu, 7);
+            if server.halted() {
+                let messages = sent(server);
+                return messages[0]["body"]["reason"].as_str().unwrap().to_string();
+            }
+        }
+        panic!("the processor did not stop");
+    }
+
+    #[test]
+    fn messages_are_framed() {
+        let mut output = Vec::new();
+        write_message(&mut output, &json!({ "seq": 1 })).unwrap();
+        write_message(&mut output, &json!({ "seq": 2 })).unwrap();
+        assert!(output.starts_with(b"Content-Length: 9\r\n\r\n{\"seq\":1}"), "the header gives the body length");
+
+        let mut input = &output[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "seq": 1 })), "the first message is read");
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "seq": 2 })), "the second message follows");
+        assert_eq!(read_message(&mut input).unwrap(), None, "the input ends");
+    }
+
+    #[test]
+    fn launch_waits_for_configuration() {
+        let mut server = Server::new(io::empty(), Vec::new());
+        let mut cpu = Processor::new();
+        let initialize = request(&mut server, &mut cpu, "initialize", json!({ "adapterID": "chip-8" }));
+        assert_eq!(initialize[0]["body"]["supportsConfigurationDoneRequest"], true, "capabilities are answered");
+
+        let action = server.handle(&mut cpu, &json!({ "seq": 2, "type": "request", "command": "launch", "arguments": { "program": "pong" } }));
+        assert_eq!(action, Some(Action::Load(PathBuf::from("pong"))), "the frontend loads the rom");
+        assert!(sent(&mut server).is_empty(), "the launch is answered once loaded");
+
+        cpu.reset();
+        cpu.load_program(&PROGRAM).unwrap();
+        server.launched(&Ok(()));
+        let messages = sent(&mut server);
+        assert_eq!((&messages[0]["request_seq"], &messages[0]["success"]), (&json!(2), &json!(true)), "the launch succeeds");
+        assert_eq!(messages[1]["event"], "initialized", "breakpoints can be set");
+        server.run_frame(&mut cpu, 7);
+        assert_eq!(cpu.program_counter(), 0x200, "nothing runs before configuration is done");
+
+        request(&mut server, &mut cpu, "configurationDone", Value::Null);
+        server.run_frame(&mut cpu, 7);
+        assert_ne!(cpu.program_counter(), 0x200, "the rom runs");
+    }
+
+    #[test]
+    fn end_of_requests_quits() {
+        let input = b"Content-Length: 9\r\n\r\n{\"seq\":1}".to_vec();
+        let mut server = Server::new(io::Cursor::new(input), Vec::new());
+        let mut cpu = Processor::new();
+        let mut action = None;
+        while action.is_none() {
+            action = server.poll(&mut cpu);
+            thread::yield_now();
+        }
+        assert_eq!(action, Some(Action::Quit), "the session ends with the input");
+        assert!(sent(&mut server).is_empty(), "messages that are not requests get no reply");
+    }
+
+    #[test]
+    fn source_breakpoints_stop_a_continue() {
+        let (mut server, mut cpu) = launch();
+        let arguments = json!({ "source": { "path": "/src/prog.8o" }, "breakpoints": [{ "line": 3 }, { "line": 5 }, { "line": 9 }] });
+        let breakpoints = &request(&mut server, &mut cpu, "setBreakpoints", arguments)[0]["body"]["breakpoints"];
+        assert_eq!((&breakpoints[0]["verified"], &breakpoints[0]["line"]), (&json!(true), &json!(3)), "line 3 has code");
+        assert_eq!(breakpoints[1]["line"], 6, "line 5 moves to the next line with code");
+        assert_eq!(breakpoints[2]["verified"], false, "line 9 has no code");
+
+        request(&mut server, &mut cpu, "setBreakpoints", json!({ "source": { "path": "/src/prog.8o" }, "breakpoints": [{ "line": 3 }] }));
+        request(&mut server, &mut cpu, "continue", Value::Null);
+        assert_eq!(run_until_stopped(&mut server, &mut cpu), "breakpoint", "the breakpoint stops the processor");
+        assert_eq!((cpu.program_counter(), cpu.registers()[1]), (0x204, 6), "the subroutine ran first");
+
+        request(&mut server, &mut cpu, "continue", Value::Null);
+        assert_eq!(run_until_stopped(&mut server, &mut cpu), "breakpoint", "the loop comes back to the breakpoint");
+        assert_eq!(cpu.registers()[0], 2, "continuing ran the loop once");
+    }
+
+    #[test]
+    fn steps_follow_source_lines() {
+        let (mut server, mut cpu) = launch();
+        request(&mut server, &mut cpu, "next", Value::Null);
+        assert_eq!(run_until_stopped(&mut server, &mut cpu), "step", "the step stops");
+        assert_eq!(cpu.program_counter(), 0x202, "next moves to line 2");
+
+        request(&mut server, &mut cpu, "next", Value::Null);
+        run_until_stopped(&mut server, &mut cpu);
+        assert_eq!(cpu.program_counter(), 0x204, "next runs the whole call");
+
+        cpu.set_program_counter(0x202);
+        request(&mut server, &mut cpu, "stepIn", Value::Null);
+        run_until_stopped(&mut server, &mut cpu);
+        assert_eq!(cpu.program_counter(), 0x208, "step in enters the call");
+        request(&mut server, &mut cpu, "stepIn", Value::Null);
+        run_until_stopped(&mut server, &mut cpu);
+        assert_eq!(cpu.program_counter(), 0x20C, "both opcodes of line 6 ran");
+
+        cpu.set_program_counter(0x208);
+        request(&mut server, &mut cpu, "stepIn", json!({ "granularity": "instruction" }));
+        run_until_stopped(&mut server, &mut cpu);
+        assert_eq!(cpu.program_counter(), 0x20A, "instruction steps run one opcode");
+
+        request(&mut server, &mut cpu, "stepOut", Value::Null);
+        run_until_stopped(&mut server, &mut cpu);
+        assert_eq!((cpu.program_counter(), cpu.stack_pointer()), (0x204, 0), "step out returns to the caller");
+    }
+
+    #[test]
+    fn stack_trace_names_subroutines() {
+        let (mut server, mut cpu) = launch();
+        server.breakpoints.insert(0x20A);
+        request(&mut server, &mut cpu, "continue", Value::Null);
+        run_until_stopped(&mut server, &mut cpu);
+
+        let trace = &request(&mut server, &mut cpu, "stackTrace", json!({ "threadId": THREAD }))[0]["body"];
+        assert_eq!(trace["totalFrames"], 2, "one frame for the call and one for main");
+        let frames = &trace["stackFrames"];
+        assert_eq!((&frames[0]["name"], &frames[0]["line"]), (&json!("bump"), &json!(6)), "the innermost frame is the subroutine");
+        assert_eq!(frames[0]["source"]["path"], "/src/prog.8o", "frames point to the source");
+        assert_eq!((&frames[1]["name"], &frames[1]["instructionPointerReference"]), (&json!("main"), &json!("0x202")), "the outer frame is at the call");
+
+        let limited = &request(&mut server, &mut cpu, "stackTrace", json!({ "startFrame": 1, "levels": 1 }))[0]["body"]["stackFrames"];
+        assert_eq!(limited.as_array().map(Vec::len), Some(1), "levels limit the frames");
+        assert_eq!(limited[0]["name"], "main", "frames start at startFrame");
+    }
+
+    #[test]
+    fn function_breakpoints_stop_at_labels() {
+        let (mut server, mut cpu) = launch();
+        let breakpoints = json!({ "breakpoints": [{ "name": "bump" }, { "name": "missing" }] });
+        let breakpoints = &request(&mut server, &mut cpu, "setFunctionBreakpoints", breakpoints)[0]["body"]["breakpoints"];
+        assert_eq!((&breakpoints[0]["instructionReference"], &breakpoints[1]["verified"]), (&json!("0x208"), &json!(false)), "labels resolve");
+
+        request(&mut server, &mut cpu, "continue", Value::Null);
+        assert_eq!(run_until_stopped(&mut server, &mut cpu), "breakpoint", "the label stops the processor");
+        assert_eq!(cpu.program_counter(), 0x208, "the processor stops at the label");
+    }
+
+    #[test]
+    fn frontend_steps_stop_at_breakpoints() {
+        let (mut server, mut cpu) = launch();
+        request(&mut server, &mut cpu, "setInstructionBreakpoints", json!({ "breakpoints": [{ "instructionReference": "0x202" }] }));
+        request(&mut server, &mut cpu, "continue", Value::Null);
+
+        server.step(&mut cpu);
+        assert_eq!(cpu.program_counter(), 0x202, "a step runs one opcode");
+        server.step(&mut cpu);
+        assert_eq!(sent(&mut server)[0]["body"]["reason"], "breakpoint", "the editor is told about the breakpoint");
+        assert_eq!(cpu.program_counter(), 0x202, "the step stops on the breakpoint");
+        server.step(&mut cpu);
+        assert_eq!(cpu.program_counter(), 0x202, "nothing runs while stopped");
+    }
+
+    #[test]
+    fn registers_are_variables() {
+        let (mut server, mut cpu) = launch();
+        cpu.set_register(0xA, 0x3C);
+        let registers = &request(&mut server, &mut cpu, "variables", json!({ "variablesReference": REGISTERS }))[0]["body"]["variables"];
+        assert_eq!((&registers[10]["name"], &registers[10]["value"]), (&json!("VA"), &json!("0x3c")), "VA is listed");
+        assert_eq!(registers[17]["value"], "0x200", "the program counter is listed");
+
+        let set = request(&mut server, &mut cpu, "setVariable", json!({ "variablesReference": REGISTERS, "name": "V3", "value": "0x1f" }));
+        assert_eq!((&set[0]["body"]["value"], cpu.registers()[3]), (&json!("0x1f"), 0x1F), "the register is set");
+        let set = request(&mut server, &mut cpu, "setVariable", json!({ "variablesReference": REGISTERS, "name": "I", "value": "800" }));
+        assert_eq!((&set[0]["success"], cpu.index_register()), (&json!(true), 800), "decimal values are taken");
+        let set = request(&mut server, &mut cpu, "setVariable", json!({ "variablesReference": REGISTERS, "name": "V3", "value": "300" }));
+        assert_eq!(set[0]["success"], false, "values too large are refused");
+    }
+
+    #[test]
+    fn errors_and_pauses_stop_the_processor() {
+        let (mut server, mut cpu) = launch();
+        request(&mut server, &mut cpu, "continue", Value::Null);
+        server.run_frame(&mut cpu, 7);
+        let pause = request(&mut server, &mut cpu, "pause", Value::Null);
+        assert_eq!(pause[1]["body"]["reason"], "pause", "a pause stops the processor");
+
+        let breakpoints = json!({ "breakpoints": [{ "instructionReference": "0x200", "offset": 4 }, { "instructionReference": "0x1000" }] });
+        let breakpoints = &request(&mut server, &mut cpu, "setInstructionBreakpoints", breakpoints)[0]["body"]["breakpoints"];
+        assert_eq!((&breakpoints[0]["verified"], &breakpoints[1]["verified"]), (&json!(true), &json!(false)), "addresses in memory are verified");
+
+        request(&mut server, &mut cpu, "setInstructionBreakpoints", json!({ "breakpoints": [] }));
+        cpu.write_memory(0x204, &[0x00, 0xEE]);
+        request(&mut server, &mut cpu, "continue", Value::Null);
+        server.run_frame(&mut cpu, 7);
+        let stopped = &sent(&mut server)[0]["body"];
+        assert_eq!(stopped["reason"], "exception", "an error stops with an exception");
+        assert_eq!(stopped["text"], "stack underflow: pc: 204 in main+4 at /src/prog.8o:3", "the error points to the source");
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::quirks::Quirks;
+
+static BUNDLED: &str = include_str!("database.txt");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::chip8(),
+            Platform::SuperChip => Quirks::schip(),
+            Platform::XoChip => Quirks::xo_chip(),
+        }
+    }
+}
+
+// how a rom should be run, unknown roms get the defaults
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub instructions_per_frame: usize,
+    // host key character for each hex key 0 - F
+    pub keymap: [char; 16],
+    // background and foreground rgb
+    pub colors: [[u8; 3]; 2],
+}
+
+impl Default for RomInfo {
+    fn default() -> RomInfo {
+        RomInfo {
+            title: None,
+            author: None,
+            platform: Platform::Chip8,
+            quirks: Quirks::default(),
+            instructions_per_frame: 10,
+            keymap: ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f'],
+            colors: [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF]],
+        }
+    }
+}
+
+pub struct Database {
+    // keyed by lowercase hex sha1
+    entries: HashMap<String, RomInfo>,
+}
+
+impl Database {
+    pub fn bundled() -> Database {
+        Database::parse(BUNDLED).expect("bundled rom database is valid")
+    }
+
+    pub fn parse(text: &str) -> Result<Database, Error> {
+        let mut entries = HashMap::new();
+        // section being read and whether it named its quirks explicitly
+        let mut current: Option<(String, RomInfo, bool)> = None;
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            let error = |message: &str| Error::Database { line: index + 1, message: message.to_string() };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                finish_entry(&mut entries, current.take());
+                let hash = line[1..line.len() - 1].trim().to_ascii_lowercase();
+                if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(error("section name is not a sha1"));
+                }
+                current = Some((hash, RomInfo::default(), false));
+                continue;
+            }
+
+            let (_, info, explicit_quirks) = current.as_mut().ok_or_else(|| error("setting outside of a rom section"))?;
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().ok_or_else(|| error("expected key = value"))?.trim();
+
+            match key {
+                "title" => info.title = Some(value.to_string()),
+                "author" => info.author = Some(value.to_string()),
+                "platform" => info.platform = parse_platform(value).ok_or_else(|| error("unknown platform"))?,
+                "quirks" => {
+                    info.quirks = match value {
+                        "default" => Quirks::default(),
+                        _ => parse_platform(value).ok_or_else(|| error("unknown quirk preset"))?.quirks(),
+                    };
+                    *explicit_quirks = true;
+                },
+                "instructions_per_frame" => {
+                    info.instructions_per_frame = value.parse().map_err(|_| error("instructions_per_frame is not a number"))?
+                },
+                "keymap" => {
+                    let keys: Vec<char> = value.chars().map(|c| c.to_ascii_lowercase()).collect();
+                    if keys.len() != 16 {
+                        return Err(error("keymap needs one key for each of the 16 hex keys"));
+                    }
+                    info.keymap.copy_from_slice(&keys);
+                },
+                "colors" => {
+                    let colors: Option<Vec<[u8; 3]>> = value.split_whitespace().map(parse_color).collect();
+                    match colors.as_deref() {
+                        Some([background, foreground]) => info.colors = [*background, *foreground],
+                        _ => return Err(error("colors needs a background and foreground rrggbb")),
+                    }
+                },
+                _ => return Err(error("unknown key")),
+            }
+        }
+
+        finish_entry(&mut entries, current);
+        Ok(Database { entries })
+    }
+
+    // adds the entries of a local database, replacing bundled ones for the same rom
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|error| Error::File { path: path.to_path_buf(), error })?;
+        self.entries.extend(Database::parse(&text)?.entries);
+        Ok(())
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> RomInfo {
+        self.entries.get(&sha1(rom)).cloned().unwrap_or_default()
+    }
+}
+
+pub fn sha1(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+fn finish_entry(entries: &mut HashMap<String, RomInfo>, entry: Option<(String, RomInfo, bool)>) {
+    if let Some((hash, mut info, explicit_quirks)) = entry {
+        if !explicit_quirks {
+            info.quirks = info.platform.quirks();
+        }
+        entries.insert(hash, info);
+    }
+}
+
+fn parse_platform(value: &str) -> Option<Platform> {
+    match value {
+        "chip-8" => Some(Platform::Chip8),
+        "schip" => Some(Platform::SuperChip),
+        "xo-chip" => Some(Platform::XoChip),
+        _ => None,
+    }
+}
+
+fn parse_color(value: &str) -> Option<[u8; 3]> {
+    if value.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(value, 16).ok()?;
+    Some([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: [u8; 2] = [0x12, 0x00];
+
+    #[test]
+    fn bundled_database_parses() {
+        let database = Database::bundled();
+        let pong = database.lookup(include_bytes!("../roms/pong"));
+        assert_eq!(pong.title.as_deref(), Some("Pong"), "pong is recognised");
+        assert_eq!(pong.quirks, Quirks::chip8(), "quirks follow the platform");
+    }
+
+    #[test]
+    fn unknown_rom_gets_defaults() {
+        let database = Database::bundled();
+        assert_eq!(database.lookup(&ROM), RomInfo::default(), "unknown roms use the defaults");
+    }
+
+    #[test]
+    fn parse_entry() {
+        let text = format!(
+            "# comment\n[{}]\ntitle = Jump\nplatform = schip\nquirks = xo-chip\ninstructions_per_frame = 30\nkeymap = X123QWEASDZC4RFV\ncolors = 102030 ffeedd\n",
+            sha1(&ROM)
+        );
+        let info = Database::parse(&text).unwrap().lookup(&ROM);
+        assert_eq!(info.title.as_deref(), Some("Jump"));
+        assert_eq!(info.platform, Platform::SuperChip);
+        assert_eq!(info.quirks, Quirks::xo_chip(), "explicit quirks win over the platform");
+        assert_eq!(info.instructions_per_frame, 30);
+        assert_eq!(info.keymap[0], 'x', "keys are lowercased");
+        assert_eq!(info.colors, [[0x10, 0x20, 0x30], [0xFF, 0xEE, 0xDD]]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let hash = sha1(&ROM);
+        let result = Database::parse("title = Orphan\n");
+        assert!(matches!(result, Err(Error::Database { line: 1, .. })), "settings need a section");
+
+        let result = Database::parse(&format!("[{}]\n\nkeymap = 0123\n", hash));
+        assert!(matches!(result, Err(Error::Database { line: 3, .. })), "short keymap is reported");
+
+        let result = Database::parse("[not a hash]\n");
+        assert!(matches!(result, Err(Error::Database { line: 1, .. })), "bad section name is reported");
+    }
+}
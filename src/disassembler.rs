@@ -0,0 +1,100 @@
+use crate::symbols::Symbols;
+
+// renders opcodes in the mnemonics of Cowgod's Chip-8 technical reference
+pub fn disassemble(opcode: u16) -> String {
+    // break up into nibbles
+    let op_1 = (opcode & 0xF000) >> 12;
+    let op_2 = (opcode & 0x0F00) >> 8;
+    let op_3 = (opcode & 0x00F0) >> 4;
+    let op_4 = opcode & 0x000F;
+
+    // helper addresses
+    let nnn = opcode & 0x0FFF;
+    let nn = opcode & 0x00FF;
+    let n = opcode & 0x000F;
+
+    // registers
+    let x = op_2;
+    let y = op_3;
+
+    match (op_1, op_2, op_3, op_4) {
+        (0, 0, 0xE, 0) => "CLS".to_string(),
+        (0, 0, 0xE, 0xE) => "RET".to_string(),
+        (0x1, _, _, _) => format!("JP {:#05X}", nnn),
+        (0x2, _, _, _) => format!("CALL {:#05X}", nnn),
+        (0x3, _, _, _) => format!("SE V{:X}, {:#04X}", x, nn),
+        (0x4, _, _, _) => format!("SNE V{:X}, {:#04X}", x, nn),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x6, _, _, _) => format!("LD V{:X}, {:#04X}", x, nn),
+        (0x7, _, _, _) => format!("ADD V{:X}, {:#04X}", x, nn),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}", x),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("SHL V{:X}", x),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, _, _, _) => format!("LD I, {:#05X}", nnn),
+        (0xB, _, _, _) => format!("JP V0, {:#05X}", nnn),
+        (0xC, _, _, _) => format!("RND V{:X}, {:#04X}", x, nn),
+        (0xD, _, _, _) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{:X}", x),
+        (0xF, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        // not an instruction, most likely sprite data
+        (_, _, _, _) => format!("DW {:#06X}", opcode),
+    }
+}
+
+// like `disassemble` with the addresses that have a label shown by name
+pub fn disassemble_with(opcode: u16, symbols: &Symbols) -> String {
+    match (opcode >> 12, symbols.name(opcode & 0x0FFF)) {
+        (0x1, Some(name)) => format!("JP {}", name),
+        (0x2, Some(name)) => format!("CALL {}", name),
+        (0xA, Some(name)) => format!("LD I, {}", name),
+        (0xB, Some(name)) => format!("JP V0, {}", name),
+        _ => disassemble(opcode),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_instructions() {
+        assert_eq!(disassemble(0x00E0), "CLS");
+        assert_eq!(disassemble(0x1A2A), "JP 0xA2A");
+        assert_eq!(disassemble(0x31FE), "SE V1, 0xFE");
+        assert_eq!(disassemble(0x8124), "ADD V1, V2");
+        assert_eq!(disassemble(0xD125), "DRW V1, V2, 5");
+        assert_eq!(disassemble(0xF355), "LD [I], V3");
+    }
+
+    #[test]
+    fn disassemble_labels() {
+        let symbols = Symbols::parse("2a0 draw\n300 sprite\n", std::path::Path::new("")).unwrap();
+        assert_eq!(disassemble_with(0x22A0, &symbols), "CALL draw");
+        assert_eq!(disassemble_with(0xA300, &symbols), "LD I, sprite");
+        assert_eq!(disassemble_with(0x12A2, &symbols), "JP 0x2A2", "addresses without a label stay in hex");
+        assert_eq!(disassemble_with(0x62A0, &symbols), "LD V2, 0xA0", "only addresses are named");
+    }
+
+    #[test]
+    fn disassemble_data() {
+        assert_eq!(disassemble(0xFFFF), "DW 0xFFFF");
+        assert_eq!(disassemble(0x5121), "DW 0x5121");
+    }
+}
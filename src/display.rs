@@ -4,43 +4,241 @@ pub const HEIGHT: usize = 32;
 
 pub type Buffer = [[bool; WIDTH]; HEIGHT];
 
+// one display row packed into a word, the leftmost pixel in the top bit.
+// a 128 pixel wide hires mode would pack its rows into u128 the same way
+pub type Row = u64;
+
+// rectangle of pixels in display coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    fn union(self, other: Region) -> Region {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Region {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+const FULL_SCREEN: Region = Region { x: 0, y: 0, width: WIDTH, height: HEIGHT };
+
 pub struct Display {
-    buffer: Buffer,
+    rows: [Row; HEIGHT],
+    // rows changed since the last `mark_clean`, bit n for row n
+    dirty_rows: u64,
+    // bounding box of the pixels changed since the last `mark_clean`
+    dirty_region: Option<Region>,
+}
+
+impl Default for Display {
+    fn default() -> Display {
+        Display::new()
+    }
 }
 
 impl Display {
     pub fn new() -> Display {
-        Display { buffer: [[false; WIDTH]; HEIGHT] }
+        // nothing has been shown yet so the whole screen starts dirty
+        Display { rows: [0; HEIGHT], dirty_rows: u64::MAX, dirty_region: Some(FULL_SCREEN) }
     }
 
     pub fn draw(&mut self, starting_x: u8, starting_y: u8, memory: &[u8]) -> bool {
+        self.blit(starting_x, starting_y, memory, false)
+    }
+
+    // like `draw` but pixels past the right and bottom edges are dropped
+    pub fn draw_clipped(&mut self, starting_x: u8, starting_y: u8, memory: &[u8]) -> bool {
+        self.blit(starting_x, starting_y, memory, true)
+    }
+
+    fn blit(&mut self, starting_x: u8, starting_y: u8, memory: &[u8], clip: bool) -> bool {
         let mut pixel_turned_off = false;
+        let starting_x = starting_x as u32 % WIDTH as u32;
+        let starting_y = starting_y as usize % HEIGHT;
+
+        // rows and columns changed by this sprite
+        let mut changed_rows: u64 = 0;
+        let mut changed_columns: Row = 0;
 
         for (byte_number, block) in memory.iter().enumerate() {
-            let y = (starting_y as usize + byte_number) % HEIGHT;
+            if clip && starting_y + byte_number >= HEIGHT {
+                break;
+            }
+            let y = (starting_y + byte_number) % HEIGHT;
 
-            for bit_number in 0..8 {
-                let x = (starting_x as usize + bit_number) % WIDTH;
-                let current_pixel = self.buffer[y][x] as u8;
+            // line the sprite byte up with the leftmost pixel then move it to
+            // x, rotating wraps it around the right edge and shifting clips it
+            let sprite = (*block as Row) << (WIDTH - 8);
+            let mask = if clip { sprite >> starting_x } else { sprite.rotate_right(starting_x) };
 
-                let current_bit = (block >> (7 - bit_number)) & 1;
-                let new_pixel = current_bit ^ current_pixel;
+            pixel_turned_off |= self.rows[y] & mask != 0;
+            self.rows[y] ^= mask;
 
-                self.buffer[y][x] = new_pixel != 0;
+            changed_columns |= mask;
+            changed_rows |= ((mask != 0) as u64) << y;
+        }
 
-                if current_pixel == 1 && new_pixel == 0 {
-                    pixel_turned_off = true;
-                }
-            }
+        if changed_rows != 0 {
+            let left = changed_columns.leading_zeros() as usize;
+            let right = WIDTH - changed_columns.trailing_zeros() as usize;
+            let top = changed_rows.trailing_zeros() as usize;
+            let bottom = 64 - changed_rows.leading_zeros() as usize;
+            self.dirty_rows |= changed_rows;
+            self.touch(Region { x: left, y: top, width: right - left, height: bottom - top });
         }
         pixel_turned_off
     }
 
     pub fn get_buffer(&self) -> Buffer {
-        self.buffer
+        let mut buffer = [[false; WIDTH]; HEIGHT];
+        for (y, row) in buffer.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.pixel(x, y);
+            }
+        }
+        buffer
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        (self.rows[y] >> (WIDTH - 1 - x)) & 1 == 1
+    }
+
+    pub fn rows(&self) -> &[Row; HEIGHT] {
+        &self.rows
+    }
+
+    // every pixel as (x, y, on), row by row from the top left
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, bool)> + '_ {
+        (0..HEIGHT).flat_map(move |y| (0..WIDTH).map(move |x| (x, y, self.pixel(x, y))))
     }
 
     pub fn clear(&mut self) {
-        self.buffer = [[false; WIDTH]; HEIGHT];
+        self.rows = [0; HEIGHT];
+        self.dirty_rows = u64::MAX;
+        self.touch(FULL_SCREEN);
+    }
+
+    // replaces every pixel, the whole screen needs redrawing
+    pub fn set_rows(&mut self, rows: &[Row; HEIGHT]) {
+        self.rows = *rows;
+        self.dirty_rows = u64::MAX;
+        self.touch(FULL_SCREEN);
+    }
+
+    // whether anything changed since the last `mark_clean`
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_region.is_some()
+    }
+
+    pub fn dirty_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..HEIGHT).filter(move |row| (self.dirty_rows >> row) & 1 == 1)
+    }
+
+    pub fn dirty_region(&self) -> Option<Region> {
+        self.dirty_region
+    }
+
+    // called by frontends once they have shown the current pixels
+    pub fn mark_clean(&mut self) {
+        self.dirty_rows = 0;
+        self.dirty_region = None;
+    }
+
+    // records a change to the bounding region, callers mark the rows
+    fn touch(&mut self, region: Region) {
+        self.dirty_region = Some(match self.dirty_region {
+            Some(dirty) => dirty.union(region),
+            None => region,
+        });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_display_is_dirty() {
+        let display = Display::new();
+        assert_eq!(display.dirty_region(), Some(FULL_SCREEN), "the first frame needs a full redraw");
+        assert_eq!(display.dirty_rows().count(), HEIGHT);
+    }
+
+    #[test]
+    fn draw_marks_changed_pixels() {
+        let mut display = Display::new();
+        display.mark_clean();
+
+        display.draw(10, 4, &[0b00011000, 0x00, 0b10000000]);
+        assert!(display.is_dirty());
+        assert_eq!(display.dirty_region(), Some(Region { x: 10, y: 4, width: 5, height: 3 }));
+        assert_eq!(display.dirty_rows().collect::<Vec<_>>(), vec![4, 6], "rows without set bits stay clean");
+
+        display.mark_clean();
+        display.draw(10, 4, &[0x00]);
+        assert!(!display.is_dirty(), "an empty sprite changes nothing");
+    }
+
+    #[test]
+    fn wrapped_draw_spans_both_edges() {
+        let mut display = Display::new();
+        display.mark_clean();
+
+        display.draw(62, 0, &[0xF0]);
+        assert_eq!(display.dirty_region(), Some(Region { x: 0, y: 0, width: WIDTH, height: 1 }));
+    }
+
+    #[test]
+    fn draw_xors_and_detects_collisions() {
+        let mut display = Display::new();
+
+        assert!(!display.draw(0, 0, &[0b11000000]), "drawing on a blank screen collides with nothing");
+        assert!(display.pixel(0, 0) && display.pixel(1, 0) && !display.pixel(2, 0));
+
+        assert!(display.draw(1, 0, &[0b11000000]), "overlapping pixel is reported");
+        assert!(display.pixel(0, 0) && !display.pixel(1, 0) && display.pixel(2, 0), "overlap is xored off");
+    }
+
+    #[test]
+    fn draw_wraps_and_clips() {
+        let mut display = Display::new();
+        display.draw(60, 31, &[0xFF, 0xFF]);
+        assert_eq!(display.rows()[31], 0xF000_0000_0000_000F, "the sprite wraps around the right edge");
+        assert_eq!(display.rows()[0], 0xF000_0000_0000_000F, "and around the bottom edge");
+
+        display.clear();
+        display.draw_clipped(60, 31, &[0xFF, 0xFF]);
+        assert_eq!(display.rows()[31], 0x0000_0000_0000_000F, "the sprite is cut off at the right edge");
+        assert_eq!(display.rows()[0], 0, "and at the bottom edge");
+    }
+
+    #[test]
+    fn pixels_iterate_row_by_row() {
+        let mut display = Display::new();
+        display.draw(1, 1, &[0x80]);
+
+        let lit: Vec<_> = display.pixels().filter(|(_, _, on)| *on).map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(lit, vec![(1, 1)]);
+        assert_eq!(display.pixels().count(), WIDTH * HEIGHT);
+    }
+
+    #[test]
+    fn clear_marks_everything() {
+        let mut display = Display::new();
+        display.mark_clean();
+
+        display.clear();
+        assert_eq!(display.dirty_region(), Some(FULL_SCREEN));
     }
 }
\ No newline at end of file
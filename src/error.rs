@@ -0,0 +1,70 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    // rom could not be read from disk
+    Io(std::io::Error),
+    // some other file could not be read or written
+    File { path: PathBuf, error: std::io::Error },
+    // rom does not fit in the memory above 0x200
+    RomTooLarge { size: usize },
+    // opcode has no known decoding
+    UnknownOpcode { opcode: u16, pc: u16 },
+    // call with all 16 stack slots in use
+    StackOverflow { pc: u16 },
+    // return with an empty stack
+    StackUnderflow { pc: u16 },
+    // rom database file could not be parsed
+    Database { line: usize, message: String },
+    // symbol file could not be parsed
+    Symbols { line: usize, message: String },
+    // cheat file could not be parsed
+    Cheats { line: usize, message: String },
+    // ips or bps patch could not be applied to the rom
+    Patch { message: String },
+    // save state was cut short, damaged or written by another version
+    State { message: String },
+    // netplay connection failed or the other side broke the protocol
+    Netplay { message: String },
+    // the two sides of a netplay game ran into different states
+    Desync { frame: u64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "could not read rom: {}", e),
+            Error::File { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::RomTooLarge { size } => write!(f, "rom is too large: {} bytes", size),
+            Error::UnknownOpcode { opcode, pc } => write!(f, "unknown opcode: op: {:x}, pc: {:x}", opcode, pc),
+            Error::StackOverflow { pc } => write!(f, "stack overflow: pc: {:x}", pc),
+            Error::StackUnderflow { pc } => write!(f, "stack underflow: pc: {:x}", pc),
+            Error::Database { line, message } => write!(f, "rom database line {}: {}", line, message),
+            Error::Symbols { line, message } => write!(f, "symbol file line {}: {}", line, message),
+            Error::Cheats { line, message } => write!(f, "cheat file line {}: {}", line, message),
+            Error::Patch { message } => write!(f, "rom patch: {}", message),
+            Error::State { message } => write!(f, "save state: {}", message),
+            Error::Netplay { message } => write!(f, "netplay: {}", message),
+            Error::Desync { frame } => write!(f, "netplay: the game desynced by frame {}", frame),
+        }
+    }
+}
+
+impl Error {
+    // address of the opcode that failed, for errors raised while running
+    pub fn pc(&self) -> Option<u16> {
+        match self {
+            Error::UnknownOpcode { pc, .. } | Error::StackOverflow { pc } | Error::StackUnderflow { pc } => Some(*pc),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
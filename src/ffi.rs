@@ -0,0 +1,311 @@
+// the c api declared in include/chip8.h, which is generated from this file
+// with `cbindgen --config cbindgen.toml --output include/chip8.h src/ffi.rs`.
+// doc comments here end up in the header, the safety rules for every
+// function are given once at its top
+#![allow(clippy::missing_safety_doc)]
+
+use std::ptr;
+use std::slice;
+
+use crate::database::Database;
+use crate::display::{HEIGHT, WIDTH};
+use crate::error::Error;
+use crate::processor::{Processor, Snapshot, SNAPSHOT_SIZE};
+
+/// Pixels in a display row.
+pub const CHIP8_WIDTH: usize = 64;
+/// Rows in the display.
+pub const CHIP8_HEIGHT: usize = 32;
+
+const _: () = assert!(CHIP8_WIDTH == WIDTH && CHIP8_HEIGHT == HEIGHT);
+
+/// What a call did, every error leaves the machine as it was before the call
+/// unless noted otherwise.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Chip8Status {
+    Ok = 0,
+    /// A pointer argument was null.
+    NullPointer,
+    /// The rom does not fit in memory above 0x200.
+    RomTooLarge,
+    /// The rom ran an opcode with no known meaning, the machine stopped on it.
+    UnknownOpcode,
+    /// The rom called a subroutine with all 16 stack slots in use.
+    StackOverflow,
+    /// The rom returned with an empty stack.
+    StackUnderflow,
+    /// The save state was cut short, damaged or written by another version.
+    InvalidState,
+    /// The buffer is smaller than `chip8_state_size()`.
+    BufferTooSmall,
+    /// Keys go from 0x0 to 0xF.
+    InvalidKey,
+}
+
+impl From<Error> for Chip8Status {
+    fn from(error: Error) -> Chip8Status {
+        match error {
+            Error::RomTooLarge { .. } => Chip8Status::RomTooLarge,
+            Error::UnknownOpcode { .. } => Chip8Status::UnknownOpcode,
+            Error::StackOverflow { .. } => Chip8Status::StackOverflow,
+            Error::StackUnderflow { .. } => Chip8Status::StackUnderflow,
+            // files are never read through this api
+            Error::State { .. } | Error::Io(_) | Error::File { .. } | Error::Database { .. } | Error::Symbols { .. } | Error::Cheats { .. } | Error::Patch { .. } | Error::Netplay { .. } | Error::Desync { .. } => Chip8Status::InvalidState,
+        }
+    }
+}
+
+fn status(result: Result<(), Error>) -> Chip8Status {
+    result.map_or_else(Chip8Status::from, |()| Chip8Status::Ok)
+}
+
+/// A machine made by `chip8_create`. Handles are used from one thread at a
+/// time and never after `chip8_destroy`. Buffers passed in must hold at
+/// least the length given with them.
+pub struct Chip8 {
+    cpu: Processor,
+    instructions_per_frame: usize,
+    // one byte a pixel, refreshed after every call that can draw
+    framebuffer: [u8; WIDTH * HEIGHT],
+}
+
+impl Chip8 {
+    fn refresh(&mut self) {
+        for (pixel, (_, _, on)) in self.framebuffer.iter_mut().zip(self.cpu.display.pixels()) {
+            *pixel = on as u8;
+        }
+    }
+}
+
+/// A machine with no rom loaded, freed with `chip8_destroy`.
+#[no_mangle]
+pub extern "C" fn chip8_create() -> *mut Chip8 {
+    let mut cpu = Processor::new();
+    cpu.reset();
+    Box::into_raw(Box::new(Chip8 { cpu, instructions_per_frame: 0, framebuffer: [0; WIDTH * HEIGHT] }))
+}
+
+/// Frees a machine, null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(machine: *mut Chip8) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// Starts `length` bytes of rom from scratch, with the quirks and speed the
+/// bundled database has for it.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(machine: *mut Chip8, rom: *const u8, length: usize) -> Chip8Status {
+    let machine = match machine.as_mut() {
+        Some(machine) if !rom.is_null() || length == 0 => machine,
+        _ => return Chip8Status::NullPointer,
+    };
+    let rom = if length == 0 { &[] } else { slice::from_raw_parts(rom, length) };
+    let info = Database::bundled().lookup(rom);
+    let mut cpu = Processor::new();
+    cpu.reset();
+    cpu.set_quirks(info.quirks);
+    if let Err(e) = cpu.load_program(rom) {
+        return e.into();
+    }
+    machine.cpu = cpu;
+    machine.instructions_per_frame = info.instructions_per_frame;
+    machine.refresh();
+    Chip8Status::Ok
+}
+
+/// Runs one 60Hz frame. On an error the machine stops at the opcode that
+/// failed, with the frame's earlier opcodes run.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(machine: *mut Chip8) -> Chip8Status {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return Chip8Status::NullPointer,
+    };
+    let result = machine.cpu.run_frame(machine.instructions_per_frame);
+    machine.refresh();
+    status(result)
+}
+
+/// Overrides the speed the database gave the rom.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_instructions_per_frame(machine: *mut Chip8, instructions: usize) -> Chip8Status {
+    match machine.as_mut() {
+        Some(machine) => {
+            machine.instructions_per_frame = instructions;
+            Chip8Status::Ok
+        },
+        None => Chip8Status::NullPointer,
+    }
+}
+
+/// Presses or releases hex key `key`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(machine: *mut Chip8, key: u8, pressed: bool) -> Chip8Status {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return Chip8Status::NullPointer,
+    };
+    match (key, pressed) {
+        (0x10..=0xFF, _) => Chip8Status::InvalidKey,
+        (_, true) => {
+            machine.cpu.keyboard.key_press(key);
+            Chip8Status::Ok
+        },
+        (_, false) => {
+            machine.cpu.keyboard.key_release(key);
+            Chip8Status::Ok
+        },
+    }
+}
+
+/// Makes the random numbers of CXNN repeat for the same seed.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_seed(machine: *mut Chip8, seed: u64) -> Chip8Status {
+    match machine.as_mut() {
+        Some(machine) => {
+            machine.cpu.set_seed(seed);
+            Chip8Status::Ok
+        },
+        None => Chip8Status::NullPointer,
+    }
+}
+
+/// `CHIP8_WIDTH * CHIP8_HEIGHT` bytes, one a pixel row by row from the top
+/// left, 1 when lit. The pointer stays valid until the machine is
+/// destroyed, its pixels change with each call that runs or loads.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(machine: *const Chip8) -> *const u8 {
+    match machine.as_ref() {
+        Some(machine) => machine.framebuffer.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Whether the sound timer is running, the buzzer sounds while it does.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(machine: *const Chip8) -> bool {
+    machine.as_ref().is_some_and(|machine| machine.cpu.sound_timer() > 0)
+}
+
+/// Bytes in a save state.
+#[no_mangle]
+pub extern "C" fn chip8_state_size() -> usize {
+    SNAPSHOT_SIZE
+}
+
+/// Writes a save state to the first `chip8_state_size()` bytes of `buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(machine: *const Chip8, buffer: *mut u8, length: usize) -> Chip8Status {
+    let machine = match machine.as_ref() {
+        Some(machine) if !buffer.is_null() => machine,
+        _ => return Chip8Status::NullPointer,
+    };
+    if length < SNAPSHOT_SIZE {
+        return Chip8Status::BufferTooSmall;
+    }
+    let state = machine.cpu.snapshot().to_bytes();
+    slice::from_raw_parts_mut(buffer, SNAPSHOT_SIZE).copy_from_slice(&state);
+    Chip8Status::Ok
+}
+
+/// Restores a save state from `chip8_save_state` out of the first
+/// `chip8_state_size()` bytes of `buffer`, the speed set for the rom stays
+/// as it is.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(machine: *mut Chip8, buffer: *const u8, length: usize) -> Chip8Status {
+    let machine = match machine.as_mut() {
+        Some(machine) if !buffer.is_null() => machine,
+        _ => return Chip8Status::NullPointer,
+    };
+    match Snapshot::from_bytes(slice::from_raw_parts(buffer, length.min(SNAPSHOT_SIZE))) {
+        Ok(snapshot) => {
+            machine.cpu.restore(&snapshot);
+            machine.refresh();
+            Chip8Status::Ok
+        },
+        Err(e) => e.into(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PONG: &[u8] = include_bytes!("../roms/pong");
+
+    unsafe fn pong() -> *mut Chip8 {
+        let machine = chip8_create();
+        assert_eq!(chip8_load_rom(machine, PONG.as_ptr(), PONG.len()), Chip8Status::Ok);
+        assert_eq!(chip8_seed(machine, 3), Chip8Status::Ok);
+        machine
+    }
+
+    unsafe fn screen(machine: *const Chip8) -> Vec<u8> {
+        slice::from_raw_parts(chip8_framebuffer(machine), CHIP8_WIDTH * CHIP8_HEIGHT).to_vec()
+    }
+
+    #[test]
+    fn frames_draw() {
+        unsafe {
+            let machine = pong();
+            assert!(screen(machine).iter().all(|pixel| *pixel == 0), "nothing is drawn before the first frame");
+            for _ in 0..10 {
+                assert_eq!(chip8_run_frame(machine), Chip8Status::Ok);
+            }
+            assert!(screen(machine).contains(&1), "pong draws paddles");
+            assert_eq!(chip8_set_key(machine, 0x1, true), Chip8Status::Ok);
+            assert_eq!(chip8_set_key(machine, 0x10, true), Chip8Status::InvalidKey, "keys past F are refused");
+            chip8_destroy(machine);
+        }
+    }
+
+    #[test]
+    fn states_round_trip() {
+        unsafe {
+            let machine = pong();
+            let mut state = vec![0; chip8_state_size() + 10];
+            assert_eq!(chip8_save_state(machine, state.as_mut_ptr(), 10), Chip8Status::BufferTooSmall, "short buffers are refused");
+            assert_eq!(chip8_save_state(machine, state.as_mut_ptr(), state.len()), Chip8Status::Ok);
+            for _ in 0..60 {
+                chip8_run_frame(machine);
+            }
+            let later = screen(machine);
+
+            let other = chip8_create();
+            assert_eq!(chip8_load_state(other, state.as_ptr(), state.len()), Chip8Status::Ok);
+            assert_eq!(chip8_set_instructions_per_frame(other, (*machine).instructions_per_frame), Chip8Status::Ok);
+            for _ in 0..60 {
+                chip8_run_frame(other);
+            }
+            assert_eq!(screen(other), later, "another machine carries on from the state");
+
+            state[0] = b'X';
+            assert_eq!(chip8_load_state(other, state.as_ptr(), state.len()), Chip8Status::InvalidState, "damaged states are refused");
+            chip8_destroy(machine);
+            chip8_destroy(other);
+        }
+    }
+
+    #[test]
+    fn errors_are_returned() {
+        unsafe {
+            let machine = chip8_create();
+            let rom = [0xFF, 0xFF];
+            assert_eq!(chip8_load_rom(machine, rom.as_ptr(), rom.len()), Chip8Status::Ok);
+            assert_eq!(chip8_run_frame(machine), Chip8Status::UnknownOpcode);
+            let large = vec![0; 4096];
+            assert_eq!(chip8_load_rom(machine, large.as_ptr(), large.len()), Chip8Status::RomTooLarge);
+
+            assert_eq!(chip8_run_frame(ptr::null_mut()), Chip8Status::NullPointer);
+            assert_eq!(chip8_load_rom(machine, ptr::null(), 1), Chip8Status::NullPointer);
+            assert!(chip8_framebuffer(ptr::null()).is_null(), "null machines have no pixels");
+            assert!(!chip8_sound_active(ptr::null()), "null machines are silent");
+            chip8_destroy(ptr::null_mut());
+            chip8_destroy(machine);
+        }
+    }
+}
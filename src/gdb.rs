@@ -0,0 +1,711 @@
+// gdb remote serial protocol stub. a debugger connecting over tcp halts the
+// processor and drives it with register and memory access, breakpoints,
+// single steps and continues. the stub is polled from the frontend's loop
+// so the window keeps drawing while the rom is stopped
+
+use crate::error::Error;
+use crate::processor::Processor;
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+// largest packet accepted and sent, advertised in qSupported
+const PACKET_SIZE: usize = 4096;
+
+// a poll keeps answering while the debugger follows up within this long, so
+// the bursts of packets sent on every stop don't wait a frame each
+const BURST: Duration = Duration::from_millis(10);
+
+// signals reported in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+// V0 to VF, I, PC and SP in the order of the target description
+const REGISTERS: usize = 19;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Breakpoint {
+    // Z0, gdb's own breakpoints
+    Software,
+    // Z1, hbreak. there is no opcode to patch in so both work the same way
+    Hardware,
+}
+
+// input from the debugger
+#[derive(Debug, PartialEq)]
+enum Input {
+    Packet(String),
+    // ^C sent while the processor runs
+    Interrupt,
+}
+
+struct Client {
+    stream: TcpStream,
+    // bytes received but not yet split into packets
+    input: Vec<u8>,
+    // packets are acknowledged until the debugger asks for QStartNoAckMode
+    acks: bool,
+}
+
+pub struct Stub {
+    listener: TcpListener,
+    client: Option<Client>,
+    breakpoints: BTreeMap<u16, Breakpoint>,
+    // the processor only runs while this is false
+    halted: bool,
+    // set by a continue so the opcode under a breakpoint runs once
+    resuming: bool,
+    // instructions run since the timers last ticked
+    progress: usize,
+}
+
+impl Stub {
+    // listens on `address`, the processor runs freely until a debugger connects
+    pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<Stub> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Stub { listener, client: None, breakpoints: BTreeMap::new(), halted: false, resuming: false, progress: 0 })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    // accepts a debugger and answers everything it sent since the last poll
+    pub fn poll(&mut self, cpu: &mut Processor) -> io::Result<()> {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.client = Some(Client { stream, input: Vec::new(), acks: true });
+                    // a debugger expects to find the target stopped
+                    self.halted = true;
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let deadline = Instant::now() + BURST;
+        while self.receive() && Instant::now() < deadline {
+            let mut answered = false;
+            while let Some(input) = self.next_input() {
+                match input {
+                    Input::Packet(packet) => self.answer(cpu, &packet),
+                    Input::Interrupt if !self.halted => self.stop(SIGINT, None),
+                    Input::Interrupt => (),
+                }
+                answered = true;
+            }
+            if !answered || !self.wait_for_input(deadline) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // runs what is left of a frame of `instructions` instructions while the
+    // debugger lets the processor run, stopping early at breakpoints. errors
+    // halt the processor for the debugger rather than ending the frontend
+    pub fn run_frame(&mut self, cpu: &mut Processor, instructions: usize) -> Result<(), Error> {
+        if self.client.is_none() {
+            return cpu.run_frame(instructions);
+        }
+        if !self.halted && self.breakpoints.is_empty() && self.progress == 0 {
+            self.resuming = false;
+            if let Err(e) = cpu.run_frame(instructions) {
+                self.stop(signal(&e), None);
+            }
+            return Ok(());
+        }
+
+        while !self.halted && self.progress < instructions {
+            if !self.run_one(cpu) {
+                break;
+            }
+            self.progress += 1;
+        }
+        if self.progress >= instructions {
+            cpu.tick_timers();
+            self.progress = 0;
+        }
+        Ok(())
+    }
+
+    // runs a single opcode for the frontend's step key, unless the debugger
+    // has the processor halted. breakpoints and errors stop it as in a frame
+    pub fn step(&mut self, cpu: &mut Processor) -> Result<(), Error> {
+        if self.client.is_none() {
+            return cpu.step();
+        }
+        if !self.halted {
+            self.run_one(cpu);
+        }
+        Ok(())
+    }
+
+    // runs the opcode at the program counter unless a breakpoint is there,
+    // false once the processor stopped
+    fn run_one(&mut self, cpu: &mut Processor) -> bool {
+        let breakpoint = self.breakpoints.get(&cpu.program_counter()).copied();
+        if breakpoint.is_some() && !self.resuming {
+            self.stop(SIGTRAP, breakpoint);
+            return false;
+        }
+        self.resuming = false;
+        if let Err(e) = cpu.step() {
+            self.stop(signal(&e), None);
+            return false;
+        }
+        true
+    }
+
+    // reads whatever the debugger sent, false once it is gone
+    fn receive(&mut self) -> bool {
+        let client = match &mut self.client {
+            Some(client) => client,
+            None => return false,
+        };
+        let mut buffer = [0; 1024];
+        loop {
+            match client.stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(length) => client.input.extend_from_slice(&buffer[..length]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => return true,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(_) => break,
+            }
+        }
+        self.disconnect();
+        false
+    }
+
+    // blocks until more input arrives or `deadline` passes
+    fn wait_for_input(&mut self, deadline: Instant) -> bool {
+        let client = match &mut self.client {
+            Some(client) => client,
+            None => return false,
+        };
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if timeout.is_zero() {
+            return false;
+        }
+        let mut byte = [0; 1];
+        let ready = client.stream.set_nonblocking(false).is_ok()
+            && client.stream.set_read_timeout(Some(timeout)).is_ok()
+            && matches!(client.stream.peek(&mut byte), Ok(length) if length > 0);
+        let restored = client.stream.set_nonblocking(true).is_ok();
+        ready && restored
+    }
+
+    // the next complete packet or interrupt in the input, acknowledging packets
+    fn next_input(&mut self) -> Option<Input> {
+        let client = self.client.as_mut()?;
+        loop {
+            match client.input.first()? {
+                0x03 => {
+                    client.input.remove(0);
+                    return Some(Input::Interrupt);
+                },
+                b'$' => break,
+                // acks of our replies and line noise
+                _ => {
+                    client.input.remove(0);
+                },
+            }
+        }
+
+        let end = client.input.iter().position(|byte| *byte == b'#')?;
+        if client.input.len() < end + 3 {
+            return None;
+        }
+        let frame: Vec<u8> = client.input.drain(..end + 3).collect();
+        let data = unescape(&frame[1..end]);
+        let checksum = std::str::from_utf8(&frame[end + 1..]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        let valid = checksum == Some(sum(&frame[1..end]));
+        if client.acks {
+            let ack: &[u8] = if valid { b"+" } else { b"-" };
+            if write_all(&mut client.stream, ack).is_err() {
+                self.disconnect();
+                return None;
+            }
+        }
+        if valid {
+            Some(Input::Packet(String::from_utf8_lossy(&data).into_owned()))
+        } else {
+            // the debugger sends it again
+            self.next_input()
+        }
+    }
+
+    fn answer(&mut self, cpu: &mut Processor, packet: &str) {
+        let (command, arguments) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+        let reply = match command {
+            "?" => Some(format!("S{:02x}", SIGTRAP)),
+            "q" => Some(self.query(arguments)),
+            "Q" if arguments == "StartNoAckMode" => {
+                self.send("OK");
+                if let Some(client) = &mut self.client {
+                    client.acks = false;
+                }
+                None
+            },
+            "H" => Some("OK".to_string()),
+            "g" => Some((0..REGISTERS).flat_map(|index| read_register(cpu, index)).map(|byte| format!("{:02x}", byte)).collect()),
+            "G" => Some(match decode_hex(arguments) {
+                Some(bytes) if bytes.len() == register_offset(REGISTERS) => {
+                    for index in 0..REGISTERS {
+                        write_register(cpu, index, &bytes[register_offset(index)..register_offset(index + 1)]);
+                    }
+                    "OK".to_string()
+                },
+                _ => "E01".to_string(),
+            }),
+            "p" => Some(match usize::from_str_radix(arguments, 16) {
+                Ok(index) if index < REGISTERS => read_register(cpu, index).iter().map(|byte| format!("{:02x}", byte)).collect(),
+                _ => "E01".to_string(),
+            }),
+            "P" => Some(match arguments.split_once('=').map(|(index, value)| (usize::from_str_radix(index, 16), decode_hex(value))) {
+                Some((Ok(index), Some(bytes))) if index < REGISTERS && bytes.len() == register_offset(index + 1) - register_offset(index) => {
+                    write_register(cpu, index, &bytes);
+                    "OK".to_string()
+                },
+                _ => "E01".to_string(),
+            }),
+            "m" => Some(match parse_range(arguments) {
+                Some((address, length)) if address < cpu.memory().len() => {
+                    let end = (address + length.min(PACKET_SIZE / 2 - 4)).min(cpu.memory().len());
+                    cpu.memory()[address..end].iter().map(|byte| format!("{:02x}", byte)).collect()
+                },
+                _ => "E01".to_string(),
+            }),
+            "M" => Some(match arguments.split_once(':').and_then(|(range, data)| Some((parse_range(range)?, decode_hex(data)?))) {
+                Some(((address, length), bytes)) if bytes.len() == length && address.checked_add(length).is_some_and(|end| end <= cpu.memory().len()) => {
+                    cpu.write_memory(address as u16, &bytes);
+                    "OK".to_string()
+                },
+                _ => "E01".to_string(),
+            }),
+            "Z" | "z" => {
+                let mut fields = arguments.split(',');
+                let kind = match fields.next() {
+                    Some("0") => Some(Breakpoint::Software),
+                    Some("1") => Some(Breakpoint::Hardware),
+                    // watchpoints are not supported
+                    _ => None,
+                };
+                let address = fields.next().and_then(|address| u16::from_str_radix(address, 16).ok());
+                Some(match (kind, address) {
+                    (Some(kind), Some(address)) if command == "Z" => {
+                        self.breakpoints.insert(address, kind);
+                        "OK".to_string()
+                    },
+                    (Some(_), Some(address)) => {
+                        self.breakpoints.remove(&address);
+                        "OK".to_string()
+                    },
+                    (None, _) => String::new(),
+                    (_, None) => "E01".to_string(),
+                })
+            },
+            "s" => {
+                if let Ok(address) = u16::from_str_radix(arguments, 16) {
+                    cpu.set_program_counter(address);
+                }
+                let signal = match cpu.step() {
+                    Ok(()) => SIGTRAP,
+                    Err(e) => signal(&e),
+                };
+                self.progress += 1;
+                self.halted = true;
+                Some(format!("S{:02x}", signal))
+            },
+            "c" => {
+                if let Ok(address) = u16::from_str_radix(arguments, 16) {
+                    cpu.set_program_counter(address);
+                }
+                self.halted = false;
+                self.resuming = true;
+                // the stop reply is sent when the processor stops
+                None
+            },
+            "D" => {
+                self.send("OK");
+                self.disconnect();
+                None
+            },
+            "k" => {
+                self.disconnect();
+                None
+            },
+            // vCont and everything else unsupported gets an empty reply
+            _ => Some(String::new()),
+        };
+        if let Some(reply) = reply {
+            self.send(&reply);
+        }
+    }
+
+    fn query(&self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            return format!("PacketSize={:x};qXfer:features:read+;swbreak+;hwbreak+;QStartNoAckMode+", PACKET_SIZE);
+        }
+        if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            let description = target_description();
+            return match parse_range(range) {
+                Some((offset, length)) if offset <= description.len() => {
+                    let end = (offset + length.min(PACKET_SIZE - 4)).min(description.len());
+                    let more = if end < description.len() { "m" } else { "l" };
+                    format!("{}{}", more, &description[offset..end])
+                },
+                _ => "E01".to_string(),
+            };
+        }
+        match query {
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    // halts the processor and tells the debugger why
+    fn stop(&mut self, signal: u8, breakpoint: Option<Breakpoint>) {
+        self.halted = true;
+        let reason = match breakpoint {
+            Some(Breakpoint::Software) => "swbreak:;",
+            Some(Breakpoint::Hardware) => "hwbreak:;",
+            None => "",
+        };
+        self.send(&format!("T{:02x}{}", signal, reason));
+    }
+
+    fn send(&mut self, packet: &str) {
+        let sent = match &mut self.client {
+            Some(client) => write_all(&mut client.stream, frame(packet).as_bytes()).is_ok(),
+            None => return,
+        };
+        if !sent {
+            self.disconnect();
+        }
+    }
+
+    // the processor runs freely again without a debugger
+    fn disconnect(&mut self) {
+        self.client = None;
+        self.breakpoints.clear();
+        self.halted = false;
+        self.resuming = false;
+    }
+}
+
+fn signal(error: &Error) -> u8 {
+    match error {
+        Error::UnknownOpcode { .. } => SIGILL,
+        Error::StackOverflow { .. } | Error::StackUnderflow { .. } => SIGSEGV,
+        _ => SIGTRAP,
+    }
+}
+
+// registers go out in little endian like most stubs, gdb has no chip-8
+// architecture to take the byte order from
+fn read_register(cpu: &Processor, index: usize) -> Vec<u8> {
+    match index {
+        0..=15 => vec![cpu.registers()[index]],
+        16 => cpu.index_register().to_le_bytes().to_vec(),
+        17 => cpu.program_counter().to_le_bytes().to_vec(),
+        _ => vec![cpu.stack_pointer()],
+    }
+}
+
+fn write_register(cpu: &mut Processor, index: usize, bytes: &[u8]) {
+    match index {
+        0..=15 => cpu.set_register(index, bytes[0]),
+        16 => cpu.set_index_register(u16::from_le_bytes([bytes[0], bytes[1]])),
+        17 => cpu.set_program_counter(u16::from_le_bytes([bytes[0], bytes[1]])),
+        _ => cpu.set_stack_pointer(bytes[0]),
+    }
+}
+
+// byte offset of register `index` in a `g` packet
+fn register_offset(index: usize) -> usize {
+    match index {
+        0..=16 => index,
+        17 => 18,
+        18 => 20,
+        _ => 21,
+    }
+}
+
+fn target_description() -> String {
+    let mut registers: Vec<String> = (0..16).map(|index| format!("    <reg name=\"v{:x}\" bitsize=\"8\" type=\"uint8\"/>", index)).collect();
+    registers.push("    <reg name=\"i\" bitsize=\"16\" type=\"data_ptr\"/>".to_string());
+    registers.push("    <reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>".to_string());
+    registers.push("    <reg name=\"sp\" bitsize=\"8\" type=\"uint8\"/>".to_string());
+    format!(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n<target version=\"1.0\">\n  <feature name=\"org.chip8.cpu\">\n{}\n  </feature>\n</target>\n",
+        registers.join("\n")
+    )
+}
+
+// `address,length` in hex
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (address, length) = range.split_once(',')?;
+    Some((usize::from_str_radix(address, 16).ok()?, usize::from_str_radix(length, 16).ok()?))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok()).collect()
+}
+
+fn sum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+// `$packet#checksum` with the characters the protocol reserves escaped
+fn frame(packet: &str) -> String {
+    let mut escaped = Vec::with_capacity(packet.len());
+    for byte in packet.bytes() {
+        match byte {
+            b'#' | b'$' | b'}' | b'*' => escaped.extend_from_slice(&[b'}', byte ^ 0x20]),
+            _ => escaped.push(byte),
+        }
+    }
+    let escaped = String::from_utf8_lossy(&escaped).into_owned();
+    format!("${}#{:02x}", escaped, sum(escaped.as_bytes()))
+}
+
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut escaped = false;
+    for byte in data {
+        match (escaped, byte) {
+            (false, b'}') => escaped = true,
+            (true, _) => {
+                bytes.push(byte ^ 0x20);
+                escaped = false;
+            },
+            (false, _) => bytes.push(*byte),
+        }
+    }
+    bytes
+}
+
+// replies go out with the socket blocking so large ones aren't cut short
+fn write_all(stream: &mut TcpStream, bytes: &[u8]) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let written = stream.write_all(bytes);
+    stream.set_nonblocking(true)?;
+    written
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::Backend;
+
+    // a stub with a debugger connected to it, running `program`
+    fn connect(program: &[u8]) -> (Stub, Processor, TcpStream) {
+        let mut stub = Stub::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(stub.local_addr().unwrap()).unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(5))).unwrap();
+
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.load_program(program).unwrap();
+        while !stub.connected() {
+            stub.poll(&mut cpu).unwrap();
+        }
+        (stub, cpu, client)
+    }
+
+    // polls the stub until a reply arrives and returns its payload
+    fn reply(stub: &mut Stub, cpu: &mut Processor, client: &mut TcpStream) -> String {
+        let mut received = Vec::new();
+        for _ in 0..1000 {
+            stub.poll(cpu).unwrap();
+            let mut buffer = [0; PACKET_SIZE];
+            if let Ok(length) = client.read(&mut buffer) {
+                received.extend_from_slice(&buffer[..length]);
+            }
+            let text = String::from_utf8_lossy(&received).into_owned();
+            if let (Some(start), Some(end)) = (text.find('$'), text.find('#')) {
+                if text.len() >= end + 3 {
+                    assert_eq!(&text[end + 1..end + 3], format!("{:02x}", sum(&text.as_bytes()[start + 1..end])), "the checksum matches");
+                    return String::from_utf8(unescape(&text.as_bytes()[start + 1..end])).unwrap();
+                }
+            }
+        }
+        panic!("no reply");
+    }
+
+    fn request(stub: &mut Stub, cpu: &mut Processor, client: &mut TcpStream, packet: &str) -> String {
+        client.write_all(frame(packet).as_bytes()).unwrap();
+        reply(stub, cpu, client)
+    }
+
+    #[test]
+    fn packets_are_framed() {
+        assert_eq!(frame("OK"), "$OK#9a", "the checksum is the byte sum");
+        assert_eq!(frame("a#b"), "$a}\x03b#43", "reserved characters are escaped");
+        assert_eq!(unescape(b"a}\x03b"), b"a#b", "escapes are undone");
+    }
+
+    #[test]
+    fn connecting_halts_and_detaching_resumes() {
+        let (mut stub, mut cpu, mut client) = connect(&[0x70, 0x01, 0x12, 0x00]);
+        assert!(stub.halted(), "the debugger finds the processor stopped");
+        stub.run_frame(&mut cpu, 10).unwrap();
+        assert_eq!(cpu.registers()[0], 0, "nothing runs while halted");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "?"), "S05", "the stop reason is a trap");
+
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "D"), "OK", "detach is acknowledged");
+        assert!(!stub.connected() && !stub.halted(), "the processor runs freely");
+        stub.run_frame(&mut cpu, 10).unwrap();
+        assert_eq!(cpu.registers()[0], 5, "the frame ran");
+    }
+
+    #[test]
+    fn registers_are_read_and_written() {
+        let (mut stub, mut cpu, mut client) = connect(&[]);
+        cpu.set_register(3, 0xAB);
+        let registers = request(&mut stub, &mut cpu, &mut client, "g");
+        assert_eq!(registers.len(), 42, "21 bytes of registers");
+        assert_eq!(&registers[6..8], "ab", "V3 is in its slot");
+        assert_eq!(&registers[36..40], "0002", "the program counter is little endian");
+
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "P11=4603"), "OK", "the program counter is written");
+        assert_eq!(cpu.program_counter(), 0x346, "the new program counter is set");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "p11"), "4603", "the program counter reads back");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "P3=1"), "E01", "a short value is refused");
+
+        let mut written = "00".repeat(16) + "1003" + "0002" + "02";
+        written.replace_range(0..2, "7f");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, &format!("G{}", written)), "OK", "all registers are written");
+        assert_eq!((cpu.registers()[0], cpu.index_register(), cpu.stack_pointer()), (0x7F, 0x310, 2), "the registers are set");
+    }
+
+    #[test]
+    fn memory_is_read_and_written() {
+        let (mut stub, mut cpu, mut client) = connect(&[0x60, 0x01, 0x12, 0x02]);
+        cpu.set_backend(Backend::Threaded);
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "m200,4"), "60011202", "the rom is read");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "mffe,10"), "0000", "reads stop at the end of memory");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "m1000,1"), "E01", "reads past memory fail");
+
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "M200,2:6007"), "OK", "memory is written");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "s"), "S05", "the step stops with a trap");
+        assert_eq!(cpu.registers()[0], 7, "the written opcode ran");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "Mfff,2:0000"), "E01", "writes past memory fail");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "Mffffffffffffffff,1:00"), "E01", "writes wrapping around the address space fail");
+        assert_eq!(cpu.memory()[0xFFF], 0, "nothing was written");
+    }
+
+    #[test]
+    fn breakpoints_stop_a_continue() {
+        let (mut stub, mut cpu, mut client) = connect(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "Z0,204,2"), "OK", "the breakpoint is set");
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "Z2,300,1"), "", "watchpoints are unsupported");
+
+        client.write_all(frame("c").as_bytes()).unwrap();
+        while !stub.halted() || cpu.program_counter() == 0x200 {
+            stub.poll(&mut cpu).unwrap();
+            stub.run_frame(&mut cpu, 7).unwrap();
+        }
+        assert_eq!(reply(&mut stub, &mut cpu, &mut client), "T05swbreak:;", "the stop names the breakpoint");
+        assert_eq!(cpu.program_counter(), 0x204, "the processor stops on the breakpoint");
+        assert_eq!(cpu.registers()[0], 2, "the opcodes before it ran");
+
+        client.write_all(frame("c").as_bytes()).unwrap();
+        stub.poll(&mut cpu).unwrap();
+        stub.run_frame(&mut cpu, 7).unwrap();
+        assert_eq!(reply(&mut stub, &mut cpu, &mut client), "T05swbreak:;", "the loop comes back to the breakpoint");
+        assert_eq!(cpu.registers()[0], 3, "continuing ran the loop once");
+
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "z0,204,2"), "OK", "the breakpoint is removed");
+        client.write_all(frame("c").as_bytes()).unwrap();
+        stub.poll(&mut cpu).unwrap();
+        stub.run_frame(&mut cpu, 7).unwrap();
+        assert!(!stub.halted(), "nothing stops the processor");
+    }
+
+    #[test]
+    fn frontend_steps_stop_at_breakpoints() {
+        let (mut stub, mut cpu, mut client) = connect(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "Z0,202,2"), "OK", "the breakpoint is set");
+        client.write_all(frame("c").as_bytes()).unwrap();
+        while stub.halted() {
+            stub.poll(&mut cpu).unwrap();
+        }
+
+        stub.step(&mut cpu).unwrap();
+        assert_eq!(cpu.program_counter(), 0x202, "a step runs one opcode");
+        stub.step(&mut cpu).unwrap();
+        assert_eq!(reply(&mut stub, &mut cpu, &mut client), "T05swbreak:;", "the debugger is told about the breakpoint");
+        assert_eq!(cpu.program_counter(), 0x202, "the step stops on the breakpoint");
+        stub.step(&mut cpu).unwrap();
+        assert_eq!(cpu.registers()[0], 1, "nothing runs while halted");
+    }
+
+    #[test]
+    fn interrupts_and_errors_stop_the_processor() {
+        let (mut stub, mut cpu, mut client) = connect(&[0x12, 0x00]);
+        client.write_all(frame("c").as_bytes()).unwrap();
+        stub.poll(&mut cpu).unwrap();
+        stub.run_frame(&mut cpu, 7).unwrap();
+        client.write_all(&[0x03]).unwrap();
+        assert_eq!(reply(&mut stub, &mut cpu, &mut client), "T02", "an interrupt stops with SIGINT");
+
+        cpu.write_memory(0x200, &[0x00, 0x00]);
+        client.write_all(frame("c").as_bytes()).unwrap();
+        stub.poll(&mut cpu).unwrap();
+        stub.run_frame(&mut cpu, 7).unwrap();
+        assert_eq!(reply(&mut stub, &mut cpu, &mut client), "T04", "an unknown opcode stops with SIGILL");
+        assert!(stub.connected() && stub.halted(), "the debugger can inspect the fault");
+    }
+
+    #[test]
+    fn target_description_is_served() {
+        let (mut stub, mut cpu, mut client) = connect(&[]);
+        let supported = request(&mut stub, &mut cpu, &mut client, "qSupported:swbreak+");
+        assert!(supported.contains("qXfer:features:read+"), "the description is advertised");
+
+        let first = request(&mut stub, &mut cpu, &mut client, "qXfer:features:read:target.xml:0,10");
+        assert_eq!(first, "m<?xml version=\"1", "a partial read says there is more");
+        let whole = request(&mut stub, &mut cpu, &mut client, "qXfer:features:read:target.xml:0,fff");
+        assert!(whole.starts_with('l') && whole.contains("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>"), "the whole description arrives");
+    }
+
+    #[test]
+    fn no_ack_mode_stops_acknowledgements() {
+        let (mut stub, mut cpu, mut client) = connect(&[]);
+        assert_eq!(request(&mut stub, &mut cpu, &mut client, "QStartNoAckMode"), "OK", "no ack mode is accepted");
+        client.write_all(frame("?").as_bytes()).unwrap();
+        let mut received = Vec::new();
+        while !received.ends_with(b"#b8") {
+            stub.poll(&mut cpu).unwrap();
+            let mut buffer = [0; 64];
+            if let Ok(length) = client.read(&mut buffer) {
+                received.extend_from_slice(&buffer[..length]);
+            }
+        }
+        assert_eq!(received, b"$S05#b8", "the reply comes without an ack");
+    }
+}
@@ -0,0 +1,222 @@
+// a rom as a reinforcement learning environment in the style of openai gym.
+// each step holds a set of hex keys down for one 60Hz frame, and agents see
+// the display as a bit array. episodes replay exactly for the same seed, and
+// states can be saved and restored to search from them
+
+use crate::database::Database;
+use crate::display::{Row, HEIGHT};
+use crate::error::Error;
+use crate::processor::{Backend, Processor, Snapshot};
+use crate::quirks::Quirks;
+
+// one display row a word, the leftmost pixel in the top bit
+pub type Observation = [Row; HEIGHT];
+
+// the hex keys held for a step, bit n for key n
+pub type Action = u16;
+
+#[derive(Debug)]
+pub struct Info {
+    // frames run since the last reset
+    pub frame: u64,
+    // the sound timer is running
+    pub sound: bool,
+    // the rom stopped on an error, later steps leave it as it is
+    pub done: bool,
+    // the error that ended the episode, reported by the step it happened in
+    pub error: Option<Error>,
+}
+
+// a point in an episode to go back to
+#[derive(Clone)]
+pub struct State {
+    snapshot: Snapshot,
+    frame: u64,
+    done: bool,
+}
+
+pub struct Env {
+    cpu: Processor,
+    // the processor with the rom loaded, before its first frame
+    start: Snapshot,
+    quirks: Quirks,
+    instructions_per_frame: usize,
+    seed: u64,
+    frame: u64,
+    done: bool,
+}
+
+impl Env {
+    // `rom` with the quirks and speed the bundled database has for it
+    pub fn new(rom: &[u8]) -> Result<Env, Error> {
+        let info = Database::bundled().lookup(rom);
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.load_program(rom)?;
+        let start = cpu.snapshot();
+        let mut env = Env {
+            cpu,
+            start,
+            quirks: info.quirks,
+            instructions_per_frame: info.instructions_per_frame,
+            seed: 0,
+            frame: 0,
+            done: false,
+        };
+        env.reset();
+        Ok(env)
+    }
+
+    // the settings below apply from the next reset
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions: usize) {
+        self.instructions_per_frame = instructions;
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    // takes effect right away, episodes play the same on every backend
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.cpu.set_backend(backend);
+    }
+
+    // starts a new episode from the first instruction of the rom
+    pub fn reset(&mut self) -> Observation {
+        self.cpu.restore(&self.start);
+        self.cpu.set_quirks(self.quirks);
+        self.cpu.set_seed(self.seed);
+        self.frame = 0;
+        self.done = false;
+        self.observation()
+    }
+
+    // runs one frame with the keys in `action` down and all others up
+    pub fn step(&mut self, action: Action) -> (Observation, Info) {
+        let mut error = None;
+        if !self.done {
+            self.cpu.keyboard.set_state(action);
+            match self.cpu.run_frame(self.instructions_per_frame) {
+                Ok(()) => self.frame += 1,
+                Err(e) => {
+                    self.done = true;
+                    error = Some(e);
+                },
+            }
+        }
+        let info = Info { frame: self.frame, sound: self.cpu.sound_timer() > 0, done: self.done, error };
+        (self.observation(), info)
+    }
+
+    pub fn observation(&self) -> Observation {
+        *self.cpu.display.rows()
+    }
+
+    // memory and registers for working out rewards
+    pub fn processor(&self) -> &Processor {
+        &self.cpu
+    }
+
+    pub fn clone_state(&self) -> State {
+        State { snapshot: self.cpu.snapshot(), frame: self.frame, done: self.done }
+    }
+
+    pub fn restore_state(&mut self, state: &State) {
+        self.cpu.restore(&state.snapshot);
+        self.frame = state.frame;
+        self.done = state.done;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PONG: &[u8] = include_bytes!("../roms/pong");
+
+    // pong's left paddle moves with keys 1 and 4
+    const UP: Action = 1 << 0x1;
+    const DOWN: Action = 1 << 0x4;
+
+    fn play(env: &mut Env, actions: &[Action]) -> Vec<Observation> {
+        actions.iter().map(|action| env.step(*action).0).collect()
+    }
+
+    fn actions() -> Vec<Action> {
+        (0..300).map(|frame| if frame % 40 < 20 { UP } else { DOWN }).collect()
+    }
+
+    #[test]
+    fn episodes_replay_for_a_seed() {
+        let mut env = Env::new(PONG).unwrap();
+        let first = play(&mut env, &actions());
+        env.reset();
+        assert_eq!(play(&mut env, &actions()), first, "the same seed and actions give the same frames");
+        assert!(first.iter().any(|rows| rows.iter().any(|row| *row != 0)), "pong draws something");
+    }
+
+    #[test]
+    fn steps_count_frames() {
+        let mut env = Env::new(PONG).unwrap();
+        let (_, info) = env.step(0);
+        assert_eq!((info.frame, info.done), (1, false), "a step runs one frame");
+        env.step(0);
+        assert_eq!(env.reset(), [0; HEIGHT], "reset goes back to a blank screen");
+        assert_eq!(env.step(0).1.frame, 1, "reset starts counting again");
+    }
+
+    #[test]
+    fn actions_hold_keys_for_a_frame() {
+        let mut env = Env::new(PONG).unwrap();
+        env.step(UP | DOWN);
+        assert!(env.processor().keyboard.pressed(0x1) && env.processor().keyboard.pressed(0x4), "the keys in the action are down");
+        env.step(DOWN);
+        assert!(!env.processor().keyboard.pressed(0x1), "keys left out are released");
+    }
+
+    #[test]
+    fn states_restore() {
+        let mut env = Env::new(PONG).unwrap();
+        play(&mut env, &[UP; 50]);
+        let state = env.clone_state();
+        let ahead = play(&mut env, &actions());
+        let frame = env.step(0).1.frame;
+
+        env.restore_state(&state);
+        assert_eq!(play(&mut env, &actions()), ahead, "the episode carries on as it did from the saved state");
+        assert_eq!(env.step(0).1.frame, frame, "the frame count is restored");
+    }
+
+    #[test]
+    fn states_restore_on_every_backend() {
+        let mut env = Env::new(PONG).unwrap();
+        let expected = play(&mut env, &actions());
+
+        env.set_backend(Backend::Threaded);
+        env.reset();
+        let state = env.clone_state();
+        play(&mut env, &actions());
+        env.restore_state(&state);
+        assert_eq!(play(&mut env, &actions()), expected, "the threaded backend plays the same episode");
+    }
+
+    #[test]
+    fn errors_end_the_episode() {
+        // jumps to an opcode that does not exist
+        let mut env = Env::new(&[0x12, 0x02, 0xFF, 0xFF]).unwrap();
+        let (_, info) = env.step(0);
+        assert!(matches!(info.error, Some(Error::UnknownOpcode { opcode: 0xFFFF, pc: 0x202 })), "the error is reported");
+        assert!(info.done, "the episode is over");
+
+        let (_, info) = env.step(0);
+        assert!(info.done && info.error.is_none() && info.frame == 0, "later steps do nothing");
+        env.reset();
+        assert!(env.step(0).1.error.is_some(), "reset starts a new episode that runs into it again");
+    }
+}
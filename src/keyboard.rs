@@ -6,12 +6,18 @@ pub struct Keyboard {
     keys: [bool; 16]
 }
 
+impl Default for Keyboard {
+    fn default() -> Keyboard {
+        Keyboard::new()
+    }
+}
+
 impl Keyboard{
     pub fn new()-> Keyboard {
         Keyboard{keys: [false; 16]}
     }
 
-    pub fn pressed(&mut self, index: usize) -> bool {
+    pub fn pressed(&self, index: usize) -> bool {
         self.keys[index]
     }
 
@@ -19,6 +25,17 @@ impl Keyboard{
         self.keys = [false; 16]
     }
 
+    // every key as a bit, bit n for key n
+    pub fn state(&self) -> u16 {
+        (0..16).filter(|key| self.keys[*key]).fold(0, |state, key| state | 1 << key)
+    }
+
+    pub fn set_state(&mut self, state: u16) {
+        for (key, pressed) in self.keys.iter_mut().enumerate() {
+            *pressed = state >> key & 1 == 1;
+        }
+    }
+
     pub fn key_press(&mut self, key: u8){
         self.set_key(key as usize, true)
     }
@@ -0,0 +1,23 @@
+pub mod cheats;
+pub mod coverage;
+pub mod dap;
+pub mod database;
+pub mod disassembler;
+pub mod display;
+pub mod error;
+pub mod ffi;
+pub mod gdb;
+pub mod gym;
+pub mod keyboard;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod netplay;
+pub mod patch;
+pub mod processor;
+pub mod profiler;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quirks;
+pub mod recompiler;
+pub mod rpc;
+pub mod symbols;
@@ -0,0 +1,396 @@
+// a libretro core, built into the cdylib with the `libretro` feature so
+// frontends like retroarch can run roms. the 16 hex keys are read from the
+// keyboard through the rom's keymap, the display goes out as 64x32 xrgb8888
+// frames in the rom's colors and the buzzer as a square wave. save states
+// are the same bytes as `chip8_save_state`
+//
+// libretro calls every entry point from one thread, the core's state still
+// sits behind a lock as rust statics have to be shareable. the lock is never
+// held while calling the frontend, which may call back into the core. the
+// pointers frontends pass in follow libretro.h, which is what the unsafe
+// functions rely on
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_uint, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::database::{Database, RomInfo};
+use crate::display::{HEIGHT, WIDTH};
+use crate::processor::{Processor, Snapshot, SNAPSHOT_SIZE};
+
+const RETRO_API_VERSION: c_uint = 1;
+const RETRO_DEVICE_KEYBOARD: c_uint = 3;
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_GET_LOG_INTERFACE: c_uint = 27;
+const RETRO_LOG_ERROR: c_uint = 3;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+const RETRO_REGION_NTSC: c_uint = 0;
+
+const FRAMES_PER_SECOND: f64 = 60.0;
+const SAMPLE_RATE: usize = 48000;
+const SAMPLES_PER_FRAME: usize = SAMPLE_RATE / 60;
+// the buzzer's pitch and loudness
+const TONE: usize = 440;
+const VOLUME: i16 = 0x1000;
+
+pub type EnvironmentFn = extern "C" fn(command: c_uint, data: *mut c_void) -> bool;
+pub type VideoRefreshFn = extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+pub type AudioSampleFn = extern "C" fn(left: i16, right: i16);
+pub type AudioSampleBatchFn = extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type InputPollFn = extern "C" fn();
+pub type InputStateFn = extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+pub type LogPrintfFn = unsafe extern "C" fn(level: c_uint, format: *const c_char, ...);
+
+// filled in by the frontend for RETRO_ENVIRONMENT_GET_LOG_INTERFACE
+#[repr(C)]
+struct LogCallback {
+    log: Option<LogPrintfFn>,
+}
+
+#[repr(C)]
+pub struct SystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    pub base_width: c_uint,
+    pub base_height: c_uint,
+    pub max_width: c_uint,
+    pub max_height: c_uint,
+    pub aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    pub geometry: GameGeometry,
+    pub timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+struct Game {
+    cpu: Processor,
+    info: RomInfo,
+    rom: Vec<u8>,
+    // set once the rom fails, the last frame stays on screen
+    halted: bool,
+    // samples into the buzzer's square wave, kept across frames so it has no clicks
+    phase: usize,
+    pixels: Vec<u32>,
+    samples: Vec<i16>,
+}
+
+impl Game {
+    fn new(rom: &[u8]) -> Option<Game> {
+        let info = Database::bundled().lookup(rom);
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.set_quirks(info.quirks);
+        cpu.load_program(rom).ok()?;
+        Some(Game {
+            cpu,
+            info,
+            rom: rom.to_vec(),
+            halted: false,
+            phase: 0,
+            pixels: vec![0; WIDTH * HEIGHT],
+            samples: vec![0; SAMPLES_PER_FRAME * 2],
+        })
+    }
+
+    fn render(&mut self) {
+        let [off, on] = self.info.colors.map(|[r, g, b]| u32::from_be_bytes([0, r, g, b]));
+        for (pixel, (_, _, lit)) in self.pixels.iter_mut().zip(self.cpu.display.pixels()) {
+            *pixel = if lit { on } else { off };
+        }
+    }
+
+    fn sound(&mut self) {
+        let buzzing = self.cpu.sound_timer() > 0;
+        for frame in self.samples.chunks_mut(2) {
+            let high = (self.phase * TONE * 2 / SAMPLE_RATE).is_multiple_of(2);
+            let sample = match (buzzing, high) {
+                (false, _) => 0,
+                (true, true) => VOLUME,
+                (true, false) => -VOLUME,
+            };
+            frame.copy_from_slice(&[sample, sample]);
+            self.phase = (self.phase + 1) % SAMPLE_RATE;
+        }
+    }
+}
+
+struct Core {
+    environment: Option<EnvironmentFn>,
+    log: Option<LogPrintfFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+    game: Option<Game>,
+}
+
+static CORE: Mutex<Core> = Mutex::new(Core {
+    environment: None,
+    log: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+    game: None,
+});
+
+// a frontend callback that panicked must not lock the core for good
+fn core() -> MutexGuard<'static, Core> {
+    CORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    let mut interface = LogCallback { log: None };
+    let log = if callback(RETRO_ENVIRONMENT_GET_LOG_INTERFACE, &mut interface as *mut LogCallback as *mut c_void) { interface.log } else { None };
+    let mut core = core();
+    core.environment = Some(callback);
+    core.log = log;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    core().video_refresh = Some(callback);
+}
+
+// the buzzer goes out a frame at a time through the batch callback
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    core().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    core().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    core().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    core().game = None;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    if let Some(info) = info.as_mut() {
+        *info = SystemInfo {
+            library_name: b"chip_8\0".as_ptr() as *const c_char,
+            library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+            valid_extensions: b"ch8|c8|chip8\0".as_ptr() as *const c_char,
+            need_fullpath: false,
+            block_extract: false,
+        };
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    if let Some(info) = info.as_mut() {
+        *info = SystemAvInfo {
+            geometry: GameGeometry {
+                base_width: WIDTH as c_uint,
+                base_height: HEIGHT as c_uint,
+                max_width: WIDTH as c_uint,
+                max_height: HEIGHT as c_uint,
+                aspect_ratio: WIDTH as f32 / HEIGHT as f32,
+            },
+            timing: SystemTiming { fps: FRAMES_PER_SECOND, sample_rate: SAMPLE_RATE as f64 },
+        };
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    let mut core = core();
+    if let Some(game) = core.game.take() {
+        core.game = Game::new(&game.rom);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let (input_poll, input_state, video_refresh, audio_sample_batch, log, keymap) = {
+        let core = core();
+        let keymap = match &core.game {
+            Some(game) => game.info.keymap,
+            None => return,
+        };
+        (core.input_poll, core.input_state, core.video_refresh, core.audio_sample_batch, core.log, keymap)
+    };
+
+    if let Some(poll) = input_poll {
+        poll();
+    }
+    // retrok codes match ascii for digits and lowercase letters
+    let pressed = input_state.map(|input_state| keymap.map(|host| input_state(0, RETRO_DEVICE_KEYBOARD, 0, host as c_uint) != 0));
+
+    // the frame is run and copied out under the lock, then handed over
+    // without it
+    let (pixels, samples, error) = {
+        let mut core = core();
+        let game = match core.game.as_mut() {
+            Some(game) => game,
+            None => return,
+        };
+        for (key, pressed) in pressed.iter().flatten().enumerate() {
+            if *pressed {
+                game.cpu.keyboard.key_press(key as u8);
+            } else {
+                game.cpu.keyboard.key_release(key as u8);
+            }
+        }
+
+        let mut error = None;
+        if !game.halted {
+            if let Err(e) = game.cpu.run_frame(game.info.instructions_per_frame) {
+                error = Some(e);
+                game.halted = true;
+            }
+        }
+        game.render();
+        game.sound();
+        (game.pixels.clone(), game.samples.clone(), error)
+    };
+
+    if let (Some(log), Some(error)) = (log, error) {
+        let message = CString::new(format!("chip_8: {}\n", error)).unwrap_or_default();
+        unsafe { log(RETRO_LOG_ERROR, b"%s\0".as_ptr() as *const c_char, message.as_ptr()) };
+    }
+    if let Some(video_refresh) = video_refresh {
+        video_refresh(pixels.as_ptr() as *const c_void, WIDTH as c_uint, HEIGHT as c_uint, WIDTH * 4);
+    }
+    if let Some(audio_sample_batch) = audio_sample_batch {
+        audio_sample_batch(samples.as_ptr(), SAMPLES_PER_FRAME);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SNAPSHOT_SIZE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = core();
+    match &core.game {
+        Some(game) if !data.is_null() && size >= SNAPSHOT_SIZE => {
+            let state = game.cpu.snapshot().to_bytes();
+            slice::from_raw_parts_mut(data as *mut u8, SNAPSHOT_SIZE).copy_from_slice(&state);
+            true
+        },
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = core();
+    let game = match core.game.as_mut() {
+        Some(game) if !data.is_null() => game,
+        _ => return false,
+    };
+    match Snapshot::from_bytes(slice::from_raw_parts(data as *const u8, size.min(SNAPSHOT_SIZE))) {
+        Ok(snapshot) => {
+            game.cpu.restore(&snapshot);
+            game.halted = false;
+            true
+        },
+        Err(_) => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let game = match game.as_ref() {
+        Some(game) if !game.data.is_null() => game,
+        _ => return false,
+    };
+    let rom = slice::from_raw_parts(game.data as *const u8, game.size);
+
+    let environment = core().environment;
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    match environment {
+        Some(environment) if environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format as *mut c_uint as *mut c_void) => (),
+        _ => return false,
+    }
+    let game = Game::new(rom);
+    let loaded = game.is_some();
+    core().game = game;
+    loaded
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_kind: c_uint, _info: *const GameInfo, _count: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    core().game = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+// memory is only changed through the processor, so none is handed out
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
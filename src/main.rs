@@ -1,70 +1,445 @@
-use processor::Processor;
-extern crate piston_window;
+use chip_8::database::Database;
+use chip_8::patch;
+use chip_8::recompiler;
+use chip_8::rpc;
+use chip_8::symbols::Symbols;
+use options::{Command, Options};
+use std::io;
+use std::net::TcpListener;
+use std::path::Path;
 
-use piston_window::*;
+// serve and recompile run headless, everything else needs the window
+#[cfg(feature = "gui")]
+use {
+    chip_8::cheats::Cheats,
+    chip_8::dap::{self, Action},
+    chip_8::database::RomInfo,
+    chip_8::display,
+    chip_8::gdb::Stub,
+    chip_8::netplay::Session,
+    chip_8::processor::{Backend, Processor},
+    chip_8::profiler::Profiler,
+    menu::Menu,
+    options::Netplay,
+    piston_window::*,
+    playback::{Advance, Playback},
+    screen::Screen,
+    std::io::Stdout,
+    std::time::Instant,
+};
 
-mod processor;
-mod display;
-mod keyboard;
+#[cfg(feature = "gui")]
+mod menu;
+mod options;
+#[cfg(feature = "gui")]
+mod overlay;
+#[cfg(feature = "gui")]
+mod playback;
+#[cfg(feature = "gui")]
+mod screen;
+#[cfg(feature = "gui")]
+mod text;
 
+#[cfg(feature = "gui")]
 const SCALE: usize = 20;
+#[cfg(feature = "gui")]
+const TITLE: &str = "Chip 8 Emulator!";
+
+// timers tick at 60Hz, running the rom's instructions per frame in between
+#[cfg(feature = "gui")]
+const FRAMES_PER_SECOND: u64 = 60;
+
+// a debugger driving the processor in place of the frontend
+#[cfg(feature = "gui")]
+enum Debugger {
+    Gdb(Stub),
+    Dap(Box<dap::Server<Stdout>>),
+}
+
+// text panels drawn over or beside the game screen
+#[cfg(feature = "gui")]
+struct Panels<'a> {
+    overlay: Option<&'a [String]>,
+    menu: Option<&'a [String]>,
+}
 
 fn main() {
+    let options = match options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(usage) => {
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+    };
+
+    let mut database = Database::bundled();
+    if let Some(path) = &options.database {
+        if let Err(e) = database.load(path) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let symbols = match options.symbols.as_deref().map(Symbols::load) {
+        Some(Ok(symbols)) => symbols,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+        None => Symbols::new(),
+    };
+
+    if let (Command::Recompile { output }, Some(rom)) = (&options.command, &options.rom) {
+        if let Err(e) = recompile(rom, options.patch.as_deref(), &database, output.as_deref()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Command::Serve { port } = options.command {
+        let mut server = rpc::Server::new(database, options.backend);
+        server.set_cheat_dir(options.cheat_dir.clone());
+        if let Some(rom) = &options.rom {
+            if let Err(e) = patch::read_rom(rom, options.patch.as_deref()).and_then(|bytes| server.load(bytes)) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        let served = match port {
+            Some(port) => TcpListener::bind(("127.0.0.1", port)).and_then(|listener| server.listen(listener)),
+            None => server.serve(io::stdin().lock(), io::stdout()),
+        };
+        if let Err(e) = served {
+            eprintln!("rpc: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    play(options, database, symbols);
+
+    // writes `rom` as a rust module built for the quirks the database has for it
+    fn recompile(rom: &Path, patch: Option<&Path>, database: &Database, output: Option<&Path>) -> Result<(), chip_8::error::Error> {
+        let bytes = patch::read_rom(rom, patch)?;
+        let source = recompiler::recompile(&bytes, database.lookup(&bytes).quirks)?;
+        match output {
+            Some(path) => std::fs::write(path, source).map_err(|error| chip_8::error::Error::File { path: path.to_path_buf(), error })?,
+            None => print!("{}", source),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "gui"))]
+fn play(_options: Options, _database: Database, _symbols: Symbols) {
+    eprintln!("chip_8 was built without the gui feature, only recompile and serve are available");
+    std::process::exit(2);
+}
+
+// the emulator window, until it is closed
+#[cfg(feature = "gui")]
+fn play(options: Options, database: Database, symbols: Symbols) {
+    let mut debugger = match options.gdb_port.map(|port| Stub::bind(("127.0.0.1", port))) {
+        Some(Ok(stub)) => Some(Debugger::Gdb(stub)),
+        Some(Err(e)) => {
+            eprintln!("could not listen for gdb: {}", e);
+            std::process::exit(1);
+        },
+        None if options.dap => Some(Debugger::Dap(Box::new(dap::Server::stdio()))),
+        None => None,
+    };
+
     let mut my_chip8 = Processor::new();
-    my_chip8.reset();
-    my_chip8.load_rom("roms/pong");
+    let mut rom_info = RomInfo::default();
+    let mut cheats = Cheats::new();
+    let mut menu = Menu::new(&options.rom_dir);
+    match &options.rom {
+        Some(rom) => match load(rom, options.patch.as_deref(), &database, options.backend, options.coverage.is_some(), &options.cheat_dir) {
+            Ok((cpu, info, rom_cheats)) => {
+                my_chip8 = cpu;
+                rom_info = info;
+                cheats = rom_cheats;
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => menu.show(),
+    }
+    let mut current_rom = options.rom;
+    let mut title = rom_title(current_rom.as_deref(), &rom_info);
+
+    let mut netplay = match (&options.netplay, &current_rom) {
+        (Some(netplay), Some(rom)) => match connect(netplay, rom, options.patch.as_deref()) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            },
+        },
+        _ => None,
+    };
 
     let mut window: PistonWindow = WindowSettings::new(
-        "Chip 8 Emulator!",
+        title.as_str(),
         [(display::WIDTH * SCALE) as u32, (display::HEIGHT * SCALE) as u32])
         .exit_on_esc(true)
         .build()
         .unwrap();
+    window.set_ups(FRAMES_PER_SECOND);
+    let mut screen = Screen::new(&mut window, rom_info.colors);
 
+    let mut profiler = options.profile.as_ref().map(|_| Profiler::new());
+    let mut show_overlay = false;
+    let mut playback = Playback::new();
 
     //start game
     while let Some(e) = window.next() {
-        if let Some(_) = e.render_args() {
-            draw_screen(&my_chip8.display.get_buffer(), &mut window, &e);
+        // a rom dropped on the window or launched by the editor replaces the running one
+        let mut requested_rom = None;
+        match &mut debugger {
+            Some(Debugger::Gdb(stub)) => {
+                if let Err(e) = stub.poll(&mut my_chip8) {
+                    eprintln!("gdb: {}", e);
+                }
+            },
+            Some(Debugger::Dap(server)) => match server.poll(&mut my_chip8) {
+                Some(Action::Load(rom)) => requested_rom = Some(rom),
+                Some(Action::Quit) => break,
+                None => (),
+            },
+            None => (),
+        }
+
+        if e.render_args().is_some() {
+            let overlay_lines = if show_overlay { Some(overlay::lines(&my_chip8, &symbols)) } else { None };
+            let menu_lines = if menu.open { Some(menu.lines()) } else { None };
+            let panels = Panels { overlay: overlay_lines.as_deref(), menu: menu_lines.as_deref() };
+            screen.sync(&mut my_chip8.display);
+            draw_screen(&mut screen, panels, &mut window, &e);
+        }
+
+        if let Event::Input(Input::FileDrag(FileDrag::Drop(path)), _) = &e {
+            requested_rom = Some(path.clone());
         }
-        if let Some(u) = e.update_args() {
-            my_chip8.execute_cycle();
+
+        if menu.open {
+            if let Some(Button::Keyboard(key)) = e.press_args() {
+                match key {
+                    Key::Up => menu.up(),
+                    Key::Down => menu.down(),
+                    Key::Return => requested_rom = menu.selected().map(Path::to_path_buf),
+                    Key::F7 if current_rom.is_some() => menu.open = false,
+                    _ => (),
+                }
+            }
+        } else if let (Some(Button::Keyboard(Key::F7)), None) = (e.press_args(), &netplay) {
+            menu.show();
+        }
+
+        // both sides of a netplay game keep the rom they started with
+        if netplay.is_some() && requested_rom.take().is_some() {
+            eprintln!("netplay: the rom can't be changed during a game");
+        }
+
+        if let Some(rom) = requested_rom {
+            let loaded = load(&rom, None, &database, options.backend, options.coverage.is_some(), &options.cheat_dir).map(|(cpu, info, rom_cheats)| {
+                my_chip8 = cpu;
+                rom_info = info;
+                cheats = rom_cheats;
+                screen.set_colors(rom_info.colors);
+                title = rom_title(Some(&rom), &rom_info);
+                current_rom = Some(rom);
+                menu.open = false;
+                // a profile covers one rom
+                if profiler.is_some() {
+                    profiler = Some(Profiler::new());
+                }
+                window.set_title(playback.title(&title));
+            });
+            if let Some(Debugger::Dap(server)) = &mut debugger {
+                server.launched(&loaded);
+            }
+            if let Err(e) = loaded {
+                eprintln!("{}", e);
+            }
+        }
+
+        // the game is held while the menu covers it
+        if e.update_args().is_some() && !menu.open {
+            let result = match &mut netplay {
+                // the other side waits on every frame, so playback controls are
+                // left out. so are cheats, which the other side may not have
+                Some(session) => session.run_frame(&mut my_chip8, rom_info.instructions_per_frame),
+                None => run(&mut my_chip8, debugger.as_mut(), profiler.as_mut(), &mut cheats, playback.advance(), rom_info.instructions_per_frame),
+            };
+            if let Err(e) = result {
+                eprintln!("{}", symbols.explain(&e));
+                break;
+            }
         }
 
         if let Some(Button::Keyboard(key)) = e.release_args() {
-            if let Some(key_value) = key_value(&key) {
+            if let Some(key_value) = key_value(&key, &rom_info.keymap) {
                 my_chip8.keyboard.key_release(key_value);
             }
         }
 
+        if let Some(Button::Keyboard(Key::F1)) = e.press_args() {
+            show_overlay = !show_overlay;
+            let panel_width = if show_overlay { overlay::WIDTH } else { 0 };
+            window.set_size([(display::WIDTH * SCALE + panel_width) as u32, (display::HEIGHT * SCALE) as u32]);
+        }
+
+        if let Some(Button::Keyboard(key)) = e.press_args() {
+            let handled = match key {
+                Key::F2 => { playback.toggle_pause(); true },
+                Key::F3 => { playback.advance_frame(); true },
+                Key::F4 => { playback.step_instruction(); true },
+                Key::F5 => { playback.toggle_fast_forward(); true },
+                Key::F6 => { playback.cycle_slow_motion(); true },
+                _ => false,
+            };
+            if handled {
+                window.set_title(playback.title(&title));
+            }
+        }
+
         if let Some(Button::Keyboard(key)) = e.press_args() {
-            if let Some(key_value) = key_value(&key) {
-                my_chip8.keyboard.key_press(key_value);
+            match key_value(&key, &rom_info.keymap) {
+                Some(key_value) if !menu.open => my_chip8.keyboard.key_press(key_value),
+                _ => (),
             }
         }
     }
 
-    fn key_value(key: &Key) -> Option<u8> {
-        if key.code() >= 48 && key.code() <= 57 {
-            Some((key.code() - 48) as u8)
-        } else if key.code() >= 97 && key.code() <= 102 {
-            Some((key.code() - 97 + 10) as u8)
-        } else {
-            None
+    if let Some(Debugger::Dap(server)) = &mut debugger {
+        server.terminate();
+    }
+
+    if let (Some(profiler), Some(path)) = (&profiler, &options.profile) {
+        let written = std::fs::write(path, profiler.report(&my_chip8, &symbols))
+            .and_then(|()| std::fs::write(path.with_extension("folded"), profiler.folded(&symbols)));
+        if let Err(e) = written {
+            eprintln!("could not write the profile: {}", e);
+        }
+    }
+
+    if let (Some(coverage), Some(path)) = (my_chip8.coverage(), &options.coverage) {
+        let written = std::fs::write(path, coverage.report(&symbols))
+            .and_then(|()| std::fs::write(path.with_extension("png"), coverage.image()));
+        if let Err(e) = written {
+            eprintln!("could not write the coverage: {}", e);
         }
     }
 
-    fn draw_screen(display_buffer: &display::Buffer, window: &mut PistonWindow, event: &Event){
-        window.draw_2d(event, |context, graphics, _d| {
-            piston_window::clear(color::BLACK, graphics);
-            for (i, row) in display_buffer.iter().enumerate() {
-                for (j, val) in row.iter().enumerate() {
-                    if *val {
-                        let dimensions = [(j * SCALE) as f64, (i * SCALE) as f64, SCALE as f64, SCALE as f64];
-                        Rectangle::new(color::WHITE)
-                            .draw(dimensions, &context.draw_state, context.transform, graphics);
-                    }
+    // a fresh processor running `rom` patched with `patch` or the patch beside
+    // it, set up from the database with the cheats saved for it. the current
+    // one keeps going if this fails
+    fn load(rom: &Path, patch: Option<&Path>, database: &Database, backend: Backend, coverage: bool, cheat_dir: &Path) -> Result<(Processor, RomInfo, Cheats), chip_8::error::Error> {
+        let bytes = patch::read_rom(rom, patch)?;
+        let info = database.lookup(&bytes);
+        let cheats = Cheats::load(cheat_dir, &bytes)?;
+
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.set_quirks(info.quirks);
+        cpu.set_backend(backend);
+        cpu.set_coverage(coverage);
+        cpu.load_program(&bytes)?;
+        Ok((cpu, info, cheats))
+    }
+
+    // waits for or joins the other side of a netplay game of `rom`
+    fn connect(netplay: &Netplay, rom: &Path, patch: Option<&Path>) -> Result<Session, chip_8::error::Error> {
+        let bytes = patch::read_rom(rom, patch)?;
+        match netplay {
+            Netplay::Host { port, delay } => {
+                eprintln!("netplay: waiting for the other side on port {}", port);
+                Session::host(("0.0.0.0", *port), &bytes, *delay)
+            },
+            Netplay::Join { address } => Session::join(address.as_str(), &bytes),
+        }
+    }
+
+    fn rom_title(rom: Option<&Path>, info: &RomInfo) -> String {
+        if let Some(title) = &info.title {
+            return format!("{} - {}", TITLE, title);
+        }
+        match rom.and_then(Path::file_name) {
+            Some(name) => format!("{} - {}", TITLE, name.to_string_lossy()),
+            None => TITLE.to_string(),
+        }
+    }
+
+    // a debugger can hold the frames and single steps back or stop them
+    // early, otherwise a profiler counts what the frames run. cheats are
+    // written before each frame
+    fn run(
+        cpu: &mut Processor,
+        mut debugger: Option<&mut Debugger>,
+        mut profiler: Option<&mut Profiler>,
+        cheats: &mut Cheats,
+        advance: Advance,
+        instructions_per_frame: usize,
+    ) -> Result<(), chip_8::error::Error> {
+        let mut run_frame = |cpu: &mut Processor| {
+            cheats.apply(cpu);
+            match debugger.as_deref_mut() {
+                Some(Debugger::Gdb(stub)) => stub.run_frame(cpu, instructions_per_frame),
+                Some(Debugger::Dap(server)) => {
+                    server.run_frame(cpu, instructions_per_frame);
+                    Ok(())
+                },
+                None => match profiler.as_deref_mut() {
+                    Some(profiler) => profiler.run_frame(cpu, instructions_per_frame),
+                    None => cpu.run_frame(instructions_per_frame),
+                },
+            }
+        };
+        match advance {
+            Advance::Idle => Ok(()),
+            Advance::Frames(frames) => {
+                for _ in 0..frames {
+                    run_frame(cpu)?;
+                }
+                Ok(())
+            },
+            Advance::Instruction => match debugger {
+                Some(Debugger::Gdb(stub)) => stub.step(cpu),
+                Some(Debugger::Dap(server)) => {
+                    server.step(cpu);
+                    Ok(())
+                },
+                None => cpu.step(),
+            },
+            Advance::Until(deadline) => {
+                while Instant::now() < deadline {
+                    run_frame(cpu)?;
                 }
+                Ok(())
+            },
+        }
+    }
+
+    // hex key bound to `key` in the rom's keymap
+    fn key_value(key: &Key, keymap: &[char; 16]) -> Option<u8> {
+        let code = key.code();
+        keymap.iter().position(|c| *c as i32 == code).map(|index| index as u8)
+    }
+
+    fn draw_screen(screen: &mut Screen, panels: Panels, window: &mut PistonWindow, event: &Event){
+        window.draw_2d(event, |context, graphics, device| {
+            screen.texture_context.encoder.flush(device);
+            piston_window::clear(color::BLACK, graphics);
+            screen.draw(SCALE, &context, graphics);
+            if let Some(lines) = panels.overlay {
+                text::draw_panel(lines, [display::WIDTH * SCALE, 0, overlay::WIDTH, display::HEIGHT * SCALE], &context, graphics);
+            }
+            if let Some(lines) = panels.menu {
+                text::draw_panel(lines, [0, 0, display::WIDTH * SCALE, display::HEIGHT * SCALE], &context, graphics);
             }
         });
     }
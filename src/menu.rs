@@ -0,0 +1,69 @@
+// rom browser listing the files of a directory, leaving out patches
+
+use chip_8::patch;
+use std::path::{Path, PathBuf};
+
+// entries shown at once, the list scrolls to keep the selection visible
+const VISIBLE_ENTRIES: usize = 30;
+
+pub struct Menu {
+    dir: PathBuf,
+    entries: Vec<PathBuf>,
+    selected: usize,
+    pub open: bool,
+}
+
+impl Menu {
+    pub fn new(dir: &Path) -> Menu {
+        Menu { dir: dir.to_path_buf(), entries: Vec::new(), selected: 0, open: false }
+    }
+
+    // opens the menu with a fresh listing of the directory
+    pub fn show(&mut self) {
+        self.entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                // patches are applied to the rom beside them, not loaded
+                .filter(|path| path.is_file() && !patch::is_patch(path))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        self.entries.sort();
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.open = true;
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Path> {
+        self.entries.get(self.selected).map(|path| path.as_path())
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("ROMS IN {}", self.dir.display()),
+            "UP/DOWN SELECT  ENTER LOAD  F7 CLOSE".to_string(),
+            String::new(),
+        ];
+        if self.entries.is_empty() {
+            lines.push("  NO ROMS FOUND".to_string());
+        }
+
+        let first = (self.selected + 1).saturating_sub(VISIBLE_ENTRIES);
+        for (i, path) in self.entries.iter().enumerate().skip(first).take(VISIBLE_ENTRIES) {
+            let marker = if i == self.selected { ">" } else { " " };
+            let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+            lines.push(format!("{} {}", marker, name));
+        }
+        lines
+    }
+}
@@ -0,0 +1,363 @@
+// two emulators playing one rom over tcp in lockstep. each side sends the
+// keys it holds for a frame `delay` frames ahead and runs a frame only once
+// it has the other side's keys for it, with the keys of both held. both start
+// from the same seed so the same inputs give the same game, and every
+// `HASH_INTERVAL` frames they swap a hash of their state to catch desyncs
+//
+// messages start with a tag byte, numbers are little endian:
+//
+//   hello  "C8NP", version, rom sha1 as 40 hex digits, seed u64, delay u32
+//   input  1, frame u64, keys u16 with bit n for key n
+//   hash   2, frame u64, state hash u64
+
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::database::sha1;
+use crate::error::Error;
+use crate::processor::Processor;
+
+const MAGIC: &[u8; 4] = b"C8NP";
+const VERSION: u8 = 1;
+
+const INPUT: u8 = 1;
+const HASH: u8 = 2;
+
+// frames between state comparisons
+pub const HASH_INTERVAL: u64 = 60;
+
+// frames the keys pressed now take to reach the game, enough to hide a
+// round trip on a local network
+pub const DEFAULT_DELAY: u32 = 2;
+
+pub struct Session {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    seed: u64,
+    delay: u32,
+    // the next frame to run
+    frame: u64,
+    // keys each side holds, by the frame they apply to
+    local: HashMap<u64, u16>,
+    remote: HashMap<u64, u16>,
+    // state hashes by frame, ours until the other side's arrives and the reverse
+    hashes: HashMap<u64, u64>,
+    remote_hashes: HashMap<u64, u64>,
+}
+
+fn netplay_error(error: std::io::Error) -> Error {
+    let message = match error.kind() {
+        std::io::ErrorKind::UnexpectedEof => "the other side disconnected".to_string(),
+        _ => error.to_string(),
+    };
+    Error::Netplay { message }
+}
+
+impl Session {
+    // waits on `address` for the other side to join, `delay` applies to both
+    pub fn host<A: ToSocketAddrs>(address: A, rom: &[u8], delay: u32) -> Result<Session, Error> {
+        let listener = TcpListener::bind(address).map_err(netplay_error)?;
+        Session::accept(&listener, rom, delay)
+    }
+
+    pub fn accept(listener: &TcpListener, rom: &[u8], delay: u32) -> Result<Session, Error> {
+        let (stream, _) = listener.accept().map_err(netplay_error)?;
+        let mut session = Session::new(stream, rand::random(), delay)?;
+        session.send_hello(rom)?;
+        session.check_hello(rom, false)?;
+        Ok(session)
+    }
+
+    // joins a game hosted at `address`, taking its seed and delay
+    pub fn join<A: ToSocketAddrs>(address: A, rom: &[u8]) -> Result<Session, Error> {
+        let stream = TcpStream::connect(address).map_err(netplay_error)?;
+        let mut session = Session::new(stream, 0, 0)?;
+        session.check_hello(rom, true)?;
+        session.send_hello(rom)?;
+        Ok(session)
+    }
+
+    fn new(stream: TcpStream, seed: u64, delay: u32) -> Result<Session, Error> {
+        // inputs are a few bytes a frame and can't wait to fill a packet
+        stream.set_nodelay(true).map_err(netplay_error)?;
+        let reader = BufReader::new(stream.try_clone().map_err(netplay_error)?);
+        let mut session = Session {
+            reader,
+            writer: BufWriter::new(stream),
+            seed,
+            delay,
+            frame: 0,
+            local: HashMap::new(),
+            remote: HashMap::new(),
+            hashes: HashMap::new(),
+            remote_hashes: HashMap::new(),
+        };
+        session.fill_delay();
+        Ok(session)
+    }
+
+    // nothing is held in the frames before the first keys arrive
+    fn fill_delay(&mut self) {
+        for frame in 0..self.delay as u64 {
+            self.local.insert(frame, 0);
+            self.remote.insert(frame, 0);
+        }
+    }
+
+    fn send_hello(&mut self, rom: &[u8]) -> Result<(), Error> {
+        let mut hello = MAGIC.to_vec();
+        hello.push(VERSION);
+        hello.extend_from_slice(sha1(rom).as_bytes());
+        hello.extend_from_slice(&self.seed.to_le_bytes());
+        hello.extend_from_slice(&self.delay.to_le_bytes());
+        self.writer.write_all(&hello).and_then(|()| self.writer.flush()).map_err(netplay_error)
+    }
+
+    // the host's hello sets the seed and delay, the guest's only has to match
+    fn check_hello(&mut self, rom: &[u8], from_host: bool) -> Result<(), Error> {
+        let error = |message: &str| Error::Netplay { message: message.to_string() };
+        let [magic @ .., version] = self.read::<5>()?;
+        if magic != *MAGIC {
+            return Err(error("the other side is not a chip_8 netplay session"));
+        }
+        if version != VERSION {
+            return Err(error("the other side runs another netplay version"));
+        }
+        let hash = self.read::<40>()?;
+        if hash != sha1(rom).as_bytes() {
+            return Err(error("the other side is playing another rom"));
+        }
+        let seed = u64::from_le_bytes(self.read()?);
+        let delay = u32::from_le_bytes(self.read()?);
+        if from_host {
+            self.seed = seed;
+            self.delay = delay;
+            self.fill_delay();
+        }
+        Ok(())
+    }
+
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut bytes = [0; N];
+        self.reader.read_exact(&mut bytes).map_err(netplay_error)?;
+        Ok(bytes)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    // frames run so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // runs the next frame with the keys held on both sides. the keys held
+    // on `cpu` are this side's, sent now for `delay` frames later, and are
+    // held on it again once the frame is run
+    pub fn run_frame(&mut self, cpu: &mut Processor, instructions: usize) -> Result<(), Error> {
+        // both sides draw the same random numbers
+        if self.frame == 0 {
+            cpu.set_seed(self.seed);
+        }
+
+        let held = cpu.keyboard.state();
+        let target = self.frame + self.delay as u64;
+        self.local.insert(target, held);
+        let mut message = vec![INPUT];
+        message.extend_from_slice(&target.to_le_bytes());
+        message.extend_from_slice(&held.to_le_bytes());
+        self.writer.write_all(&message).and_then(|()| self.writer.flush()).map_err(netplay_error)?;
+
+        while !self.remote.contains_key(&self.frame) {
+            self.receive()?;
+        }
+        let keys = self.local.remove(&self.frame).unwrap_or_default() | self.remote.remove(&self.frame).unwrap_or_default();
+        cpu.keyboard.set_state(keys);
+        let result = cpu.run_frame(instructions);
+        // hashed while the keys of both sides are held, as on the other side
+        let hash = (self.frame + 1).is_multiple_of(HASH_INTERVAL).then(|| fnv1a(&cpu.snapshot().to_bytes()));
+        cpu.keyboard.set_state(held);
+        result?;
+        self.frame += 1;
+
+        if let Some(hash) = hash {
+            let mut message = vec![HASH];
+            message.extend_from_slice(&self.frame.to_le_bytes());
+            message.extend_from_slice(&hash.to_le_bytes());
+            self.writer.write_all(&message).and_then(|()| self.writer.flush()).map_err(netplay_error)?;
+            self.hashes.insert(self.frame, hash);
+            self.compare(self.frame)?;
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(), Error> {
+        let [tag] = self.read()?;
+        let frame = u64::from_le_bytes(self.read()?);
+        match tag {
+            INPUT => {
+                let keys = u16::from_le_bytes(self.read()?);
+                self.remote.insert(frame, keys);
+            },
+            HASH => {
+                let hash = u64::from_le_bytes(self.read()?);
+                self.remote_hashes.insert(frame, hash);
+                self.compare(frame)?;
+            },
+            _ => return Err(Error::Netplay { message: format!("unknown message {}", tag) }),
+        }
+        Ok(())
+    }
+
+    // checks the hashes for `frame` once both sides have sent theirs
+    fn compare(&mut self, frame: u64) -> Result<(), Error> {
+        if let (Some(ours), Some(theirs)) = (self.hashes.get(&frame), self.remote_hashes.get(&frame)) {
+            if ours != theirs {
+                return Err(Error::Desync { frame });
+            }
+            self.hashes.remove(&frame);
+            self.remote_hashes.remove(&frame);
+        }
+        Ok(())
+    }
+}
+
+// fnv-1a, enough to tell two states apart
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01B3))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::thread;
+
+    const PONG: &[u8] = include_bytes!("../roms/pong");
+
+    fn pong() -> Processor {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.set_quirks(Database::bundled().lookup(PONG).quirks);
+        cpu.load_program(PONG).unwrap();
+        cpu
+    }
+
+    type Side = Result<(Processor, Session), Error>;
+
+    // runs `frames` frames of pong on both sides of a loopback game, each
+    // holding the keys it is given for a frame, until one side fails
+    fn play<F, G>(frames: u64, host_keys: F, guest_keys: G, delay: u32) -> (Side, Side)
+    where
+        F: Fn(u64, &mut Processor) -> u16 + Send + 'static,
+        G: Fn(u64, &mut Processor) -> u16 + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let side = move |session: Result<Session, Error>, keys: &dyn Fn(u64, &mut Processor) -> u16| {
+            let mut session = session?;
+            let mut cpu = pong();
+            for frame in 0..frames {
+                let held = keys(frame, &mut cpu);
+                cpu.keyboard.set_state(held);
+                session.run_frame(&mut cpu, 8)?;
+            }
+            Ok((cpu, session))
+        };
+        let host = thread::spawn(move || side(Session::accept(&listener, PONG, delay), &host_keys));
+        let guest = side(Session::join(address, PONG), &guest_keys);
+        (host.join().unwrap(), guest)
+    }
+
+    // the left player taps 1 and the right player holds D now and then
+    fn left(frame: u64, _: &mut Processor) -> u16 {
+        if frame % 50 < 10 { 1 << 0x1 } else { 0 }
+    }
+
+    fn right(frame: u64, _: &mut Processor) -> u16 {
+        if frame % 70 > 40 { 1 << 0xD } else { 0 }
+    }
+
+    #[test]
+    fn both_sides_play_the_same_game() {
+        let (host, guest) = play(300, left, right, 3);
+        let ((host, _), (guest, session)) = (host.unwrap(), guest.unwrap());
+        assert_eq!(host.display.rows(), guest.display.rows(), "both screens match");
+        assert!(host.snapshot().to_bytes() == guest.snapshot().to_bytes(), "the whole machines match");
+        assert_eq!(guest.keyboard.state(), right(299, &mut pong()), "each side keeps its own keys held");
+        assert_eq!((session.delay(), session.frame()), (3, 300), "the guest plays with the host's delay");
+    }
+
+    #[test]
+    fn inputs_arrive_after_the_delay() {
+        let (host, _) = play(300, left, right, 3);
+        let (host, session) = host.unwrap();
+
+        // the same game on one machine, with the keys of both sides three frames late
+        let mut reference = pong();
+        reference.set_seed(session.seed());
+        for frame in 0..300 {
+            let keys = match frame {
+                0..=2 => 0,
+                _ => left(frame - 3, &mut reference) | right(frame - 3, &mut reference),
+            };
+            reference.keyboard.set_state(keys);
+            reference.run_frame(8).unwrap();
+        }
+        assert_eq!(host.display.rows(), reference.display.rows(), "the game runs as the delayed keys say");
+    }
+
+    #[test]
+    fn desyncs_are_caught() {
+        // the guest's machine drifts from the host's
+        let (host, guest) = play(
+            HASH_INTERVAL * 2,
+            left,
+            |frame, cpu: &mut Processor| {
+                if frame == 10 {
+                    cpu.set_register(0xE, 0x42);
+                }
+                0
+            },
+            2,
+        );
+        let caught = |side: &Side| matches!(side, Err(Error::Desync { frame: HASH_INTERVAL }));
+        assert!(caught(&host) || caught(&guest), "the first comparison after the drift fails");
+        assert!(host.is_err() && guest.is_err(), "both sides stop");
+    }
+
+    #[test]
+    fn roms_must_match() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let host = thread::spawn(move || Session::accept(&listener, PONG, 2).map(|_| ()));
+        let guest = Session::join(address, &[0x12, 0x00]);
+        assert!(matches!(guest, Err(Error::Netplay { .. })), "the guest refuses another rom");
+        assert!(matches!(host.join().unwrap(), Err(Error::Netplay { .. })), "the host sees it leave");
+    }
+
+    #[test]
+    fn disconnects_are_reported() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let host = thread::spawn(move || drop(Session::accept(&listener, PONG, 2).unwrap()));
+        let mut guest = Session::join(address, PONG).unwrap();
+        host.join().unwrap();
+        let mut cpu = pong();
+        let result = (0..10).try_for_each(|_| guest.run_frame(&mut cpu, 8));
+        assert!(matches!(result, Err(Error::Netplay { .. })), "the guest stops when the host leaves");
+    }
+
+    #[test]
+    fn state_hashes() {
+        assert_eq!(fnv1a(b""), 0xCBF2_9CE4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xAF63_DC4C_8601_EC8C);
+    }
+}
@@ -0,0 +1,289 @@
+// command line options for the emulator
+
+use chip_8::netplay::DEFAULT_DELAY;
+use chip_8::processor::Backend;
+use std::path::PathBuf;
+
+#[cfg(not(feature = "jit"))]
+const USAGE: &str = "usage: chip_8 [ROM [--patch FILE]] [--roms DIR] [--database FILE] [--symbols FILE] [--backend interpreter|threaded] [--gdb PORT | --dap] [--profile FILE] [--coverage FILE]
+                  [--cheats DIR] [--host PORT [--delay FRAMES] | --join ADDRESS]
+       chip_8 recompile ROM [--patch FILE] [--output FILE] [--database FILE]
+       chip_8 serve [ROM [--patch FILE]] [--port PORT] [--database FILE] [--backend interpreter|threaded] [--cheats DIR]";
+#[cfg(feature = "jit")]
+const USAGE: &str = "usage: chip_8 [ROM [--patch FILE]] [--roms DIR] [--database FILE] [--symbols FILE] [--backend interpreter|threaded|jit] [--gdb PORT | --dap] [--profile FILE] [--coverage FILE]
+                  [--cheats DIR] [--host PORT [--delay FRAMES] | --join ADDRESS]
+       chip_8 recompile ROM [--patch FILE] [--output FILE] [--database FILE]
+       chip_8 serve [ROM [--patch FILE]] [--port PORT] [--database FILE] [--backend interpreter|threaded|jit] [--cheats DIR]";
+
+pub enum Command {
+    // run the rom in the emulator window
+    Play,
+    // write the rom recompiled to a rust module to `output`, or stdout
+    Recompile { output: Option<PathBuf> },
+    // answer json-rpc requests on a local port, or stdin and stdout, without a window
+    Serve { port: Option<u16> },
+}
+
+// playing the rom with another emulator over tcp, only the window plays it
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub enum Netplay {
+    // wait for the other side on a local port, with inputs `delay` frames late
+    Host { port: u16, delay: u32 },
+    // connect to a host at `address`
+    Join { address: String },
+}
+
+pub struct Options {
+    pub command: Command,
+    // rom to start with, the rom menu opens when missing
+    pub rom: Option<PathBuf>,
+    // ips or bps patch for the rom, in place of the one beside it
+    pub patch: Option<PathBuf>,
+    // directory listed by the rom menu
+    pub rom_dir: PathBuf,
+    // local rom database extending the bundled one
+    pub database: Option<PathBuf>,
+    // assembler symbols for the rom, shown by the overlay and in errors
+    pub symbols: Option<PathBuf>,
+    // how the processor runs opcodes
+    pub backend: Backend,
+    // local port a gdb remote protocol stub listens on
+    pub gdb_port: Option<u16>,
+    // serve the debug adapter protocol on stdin and stdout
+    pub dap: bool,
+    // profile report written on exit, with the folded stacks beside it
+    pub profile: Option<PathBuf>,
+    // memory coverage report written on exit, with the png heatmap beside it
+    pub coverage: Option<PathBuf>,
+    pub netplay: Option<Netplay>,
+    // cheat files for roms, named by their sha1
+    pub cheat_dir: PathBuf,
+}
+
+pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options {
+        command: Command::Play,
+        rom: None,
+        patch: None,
+        rom_dir: PathBuf::from("roms"),
+        database: None,
+        symbols: None,
+        backend: Backend::default(),
+        gdb_port: None,
+        dap: false,
+        profile: None,
+        coverage: None,
+        netplay: None,
+        cheat_dir: PathBuf::from("cheats"),
+    };
+    let mut delay = None;
+    let mut rom_dir = None;
+
+    let mut args = args.peekable();
+    match args.peek().map(String::as_str) {
+        Some("recompile") => options.command = Command::Recompile { output: None },
+        Some("serve") => options.command = Command::Serve { port: None },
+        _ => (),
+    }
+    if let Command::Recompile { .. } | Command::Serve { .. } = options.command {
+        args.next();
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--roms" => match args.next() {
+                Some(dir) => rom_dir = Some(PathBuf::from(dir)),
+                None => return Err(USAGE.to_string()),
+            },
+            "--database" => match args.next() {
+                Some(file) => options.database = Some(PathBuf::from(file)),
+                None => return Err(USAGE.to_string()),
+            },
+            "--symbols" => match args.next() {
+                Some(file) => options.symbols = Some(PathBuf::from(file)),
+                None => return Err(USAGE.to_string()),
+            },
+            "--backend" => match args.next().as_deref() {
+                Some("interpreter") => options.backend = Backend::Interpreter,
+                Some("threaded") => options.backend = Backend::Threaded,
+                #[cfg(feature = "jit")]
+                Some("jit") => options.backend = Backend::Jit,
+                _ => return Err(USAGE.to_string()),
+            },
+            "--gdb" => match args.next().and_then(|port| port.parse().ok()) {
+                Some(port) => options.gdb_port = Some(port),
+                None => return Err(USAGE.to_string()),
+            },
+            "--dap" => options.dap = true,
+            "--profile" => match args.next() {
+                Some(file) => options.profile = Some(PathBuf::from(file)),
+                None => return Err(USAGE.to_string()),
+            },
+            "--coverage" => match args.next() {
+                Some(file) => options.coverage = Some(PathBuf::from(file)),
+                None => return Err(USAGE.to_string()),
+            },
+            "--patch" => match args.next() {
+                Some(file) => options.patch = Some(PathBuf::from(file)),
+                None => return Err(USAGE.to_string()),
+            },
+            "--cheats" => match args.next() {
+                Some(dir) => options.cheat_dir = PathBuf::from(dir),
+                None => return Err(USAGE.to_string()),
+            },
+            "--host" => match (&options.netplay, args.next().and_then(|port| port.parse().ok())) {
+                (None, Some(port)) => options.netplay = Some(Netplay::Host { port, delay: DEFAULT_DELAY }),
+                _ => return Err(USAGE.to_string()),
+            },
+            "--join" => match (&options.netplay, args.next()) {
+                (None, Some(address)) => options.netplay = Some(Netplay::Join { address }),
+                _ => return Err(USAGE.to_string()),
+            },
+            "--delay" => match args.next().and_then(|frames| frames.parse().ok()) {
+                Some(frames) => delay = Some(frames),
+                None => return Err(USAGE.to_string()),
+            },
+            "--output" => match (&mut options.command, args.next()) {
+                (Command::Recompile { output }, Some(file)) => *output = Some(PathBuf::from(file)),
+                _ => return Err(USAGE.to_string()),
+            },
+            "--port" => match (&mut options.command, args.next().and_then(|port| port.parse().ok())) {
+                (Command::Serve { port }, Some(number)) => *port = Some(number),
+                _ => return Err(USAGE.to_string()),
+            },
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with('-') || options.rom.is_some() => return Err(USAGE.to_string()),
+            _ => options.rom = Some(PathBuf::from(arg)),
+        }
+    }
+
+    // the window is all that debugs, profiles, covers or plays online, and
+    // recompile and serve have no menu or overlay
+    let play_only = options.gdb_port.is_some() || options.dap || options.netplay.is_some() || options.profile.is_some() || options.coverage.is_some() || options.symbols.is_some() || rom_dir.is_some();
+    if play_only && !matches!(options.command, Command::Play) {
+        return Err(USAGE.to_string());
+    }
+    if let Some(dir) = rom_dir {
+        options.rom_dir = dir;
+    }
+    // there is no menu to pick the rom to recompile from
+    if let (Command::Recompile { .. }, None) = (&options.command, &options.rom) {
+        return Err(USAGE.to_string());
+    }
+    // roms picked from the menu only get the patches beside them
+    if options.patch.is_some() && options.rom.is_none() {
+        return Err(USAGE.to_string());
+    }
+    // only one debugger can drive the processor
+    if options.dap && options.gdb_port.is_some() {
+        return Err(USAGE.to_string());
+    }
+    // both sides play the rom given, and nothing else may hold the game back
+    if options.netplay.is_some() && (options.rom.is_none() || options.dap || options.gdb_port.is_some()) {
+        return Err(USAGE.to_string());
+    }
+    // the host picks the delay for both sides
+    match (&mut options.netplay, delay) {
+        (Some(Netplay::Host { delay, .. }), Some(frames)) => *delay = frames,
+        (_, Some(_)) => return Err(USAGE.to_string()),
+        _ => (),
+    }
+    Ok(options)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn parse_line(line: &str) -> Result<Options, String> {
+        parse(line.split_whitespace().map(String::from))
+    }
+
+    fn rejected(line: &str) -> bool {
+        parse_line(line).is_err()
+    }
+
+    #[test]
+    fn playing_is_the_default() {
+        let options = parse_line("").unwrap();
+        assert!(matches!(options.command, Command::Play));
+        assert_eq!((options.rom, options.rom_dir, options.cheat_dir), (None, PathBuf::from("roms"), PathBuf::from("cheats")), "the menu opens on the roms directory");
+        assert_eq!(options.backend, Backend::default());
+
+        let options = parse_line("pong --roms games --symbols pong.sym --profile out.txt --coverage cov.txt --cheats mine").unwrap();
+        assert_eq!(options.rom, Some(PathBuf::from("pong")));
+        assert_eq!((options.rom_dir, options.cheat_dir), (PathBuf::from("games"), PathBuf::from("mine")));
+        assert_eq!((options.symbols, options.profile, options.coverage), (Some("pong.sym".into()), Some("out.txt".into()), Some("cov.txt".into())));
+        assert!(rejected("pong tetris"), "one rom at a time");
+        assert!(rejected("pong --frobnicate") && rejected("--help"));
+        assert!(rejected("pong --roms"), "options missing their value are refused");
+    }
+
+    #[test]
+    fn subcommands_come_first() {
+        let options = parse_line("recompile pong --output pong.rs").unwrap();
+        assert!(matches!(options.command, Command::Recompile { output: Some(output) } if output == Path::new("pong.rs")));
+        assert_eq!(options.rom, Some(PathBuf::from("pong")));
+        assert!(rejected("recompile"), "recompile needs a rom");
+        assert!(rejected("pong recompile"), "after the rom it is a second rom");
+
+        assert!(matches!(parse_line("serve").unwrap().command, Command::Serve { port: None }), "serve runs without a rom");
+        assert!(matches!(parse_line("serve pong --port 7000").unwrap().command, Command::Serve { port: Some(7000) }));
+        assert_eq!(parse_line("serve pong --backend threaded").unwrap().backend, Backend::Threaded, "the server runs the backend asked for");
+        assert!(rejected("serve --port many"));
+
+        assert!(rejected("pong --output pong.rs") && rejected("serve pong --output pong.rs"), "--output only goes with recompile");
+        assert!(rejected("pong --port 7000") && rejected("recompile pong --port 7000"), "--port only goes with serve");
+
+        for flag in ["--gdb 9000", "--dap", "--host 7000", "--join example.com:7000", "--host 7000 --delay 5", "--profile out.txt", "--coverage cov.txt", "--roms games", "--symbols pong.sym"] {
+            assert!(rejected(&format!("serve pong {}", flag)), "serve refuses {}", flag);
+            assert!(rejected(&format!("recompile pong {}", flag)), "recompile refuses {}", flag);
+            assert!(!rejected(&format!("pong {}", flag)), "playing takes {}", flag);
+        }
+    }
+
+    #[test]
+    fn backends_depend_on_the_build() {
+        assert_eq!(parse_line("pong --backend interpreter").unwrap().backend, Backend::Interpreter);
+        assert_eq!(parse_line("pong --backend threaded").unwrap().backend, Backend::Threaded);
+        assert!(rejected("pong --backend turbo") && rejected("pong --backend"));
+        #[cfg(feature = "jit")]
+        assert_eq!(parse_line("pong --backend jit").unwrap().backend, Backend::Jit);
+        #[cfg(not(feature = "jit"))]
+        assert!(rejected("pong --backend jit"), "the jit is only offered when built in");
+    }
+
+    #[test]
+    fn one_debugger_at_a_time() {
+        assert_eq!(parse_line("pong --gdb 9000").unwrap().gdb_port, Some(9000));
+        assert!(parse_line("pong --dap").unwrap().dap);
+        assert!(rejected("pong --gdb 9000 --dap") && rejected("--dap pong --gdb 9000"));
+        assert!(rejected("pong --gdb port"));
+    }
+
+    #[test]
+    fn patches_need_a_rom() {
+        assert_eq!(parse_line("pong --patch fix.ips").unwrap().patch, Some(PathBuf::from("fix.ips")));
+        assert!(rejected("--patch fix.ips"), "roms from the menu only get the patches beside them");
+        assert!(rejected("serve --patch fix.ips"));
+        assert!(parse_line("recompile pong --patch fix.bps").unwrap().patch.is_some());
+    }
+
+    #[test]
+    fn netplay_hosts_or_joins() {
+        let options = parse_line("pong --host 7000").unwrap();
+        assert!(matches!(options.netplay, Some(Netplay::Host { port: 7000, delay: DEFAULT_DELAY })));
+        let options = parse_line("pong --host 7000 --delay 5").unwrap();
+        assert!(matches!(options.netplay, Some(Netplay::Host { port: 7000, delay: 5 })), "the host picks the delay");
+        let options = parse_line("pong --join example.com:7000").unwrap();
+        assert!(matches!(options.netplay, Some(Netplay::Join { address }) if address == "example.com:7000"));
+
+        assert!(rejected("pong --host 7000 --join example.com:7000") && rejected("pong --join example.com:7000 --host 7000"), "a game is either hosted or joined");
+        assert!(rejected("pong --host 7000 --host 7001"));
+        assert!(rejected("pong --delay 5") && rejected("pong --join example.com:7000 --delay 5"), "--delay only goes with --host");
+        assert!(rejected("--host 7000"), "both sides play the rom given");
+        assert!(rejected("pong --host 7000 --dap") && rejected("pong --join example.com:7000 --gdb 9000"), "debuggers would hold the game back");
+    }
+}
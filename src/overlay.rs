@@ -0,0 +1,61 @@
+// live view of the processor state drawn beside the game screen
+
+use chip_8::disassembler::disassemble_with;
+use chip_8::processor::{Processor, ADDRESS_MASK};
+use chip_8::symbols::Symbols;
+
+// panel size in pixels
+pub const WIDTH: usize = 440;
+
+// instructions listed from the program counter onwards
+const LISTING_LENGTH: u16 = 8;
+
+// addresses are shown by label where `symbols` has one
+pub fn lines(cpu: &Processor, symbols: &Symbols) -> Vec<String> {
+    let mut lines = vec![
+        format!("PC {:03X}  I {:03X}  SP {:X}", cpu.program_counter(), cpu.index_register(), cpu.stack_pointer()),
+        format!("DT {:02X}  ST {:02X}", cpu.delay_timer(), cpu.sound_timer()),
+    ];
+
+    // where the program counter is in the source, blank without symbols
+    let mut place: Vec<String> = symbols.describe(cpu.program_counter()).into_iter().collect();
+    if let Some((file, line)) = symbols.location(cpu.program_counter()) {
+        place.push(format!("{}:{}", file.file_name().unwrap_or_default().to_string_lossy(), line));
+    }
+    lines.push(place.join(" "));
+
+    for (row_number, values) in cpu.registers().chunks(4).enumerate() {
+        let cells: Vec<String> = values.iter().enumerate()
+            .map(|(i, value)| format!("V{:X} {:02X}", row_number * 4 + i, value))
+            .collect();
+        lines.push(cells.join("  "));
+    }
+
+    lines.push(String::new());
+    lines.push("STACK".to_string());
+    if cpu.call_stack().is_empty() {
+        lines.push("  -".to_string());
+    }
+    for address in cpu.call_stack().iter().rev() {
+        match symbols.describe(*address) {
+            Some(name) => lines.push(format!("  {:03X} {}", address, name)),
+            None => lines.push(format!("  {:03X}", address)),
+        }
+    }
+
+    lines.push(String::new());
+    for i in 0..LISTING_LENGTH {
+        let address = (cpu.program_counter() + i * 2) & ADDRESS_MASK;
+        let marker = if i == 0 { ">" } else { " " };
+        lines.push(format!("{} {:03X} {}", marker, address, disassemble_with(cpu.opcode_at(address), symbols)));
+    }
+
+    lines.push(String::new());
+    let held: Vec<String> = (0..16)
+        .filter(|key| cpu.keyboard.pressed(*key))
+        .map(|key| format!("{:X}", key))
+        .collect();
+    lines.push(format!("KEYS {}", if held.is_empty() { "-".to_string() } else { held.join(" ") }));
+
+    lines
+}
@@ -0,0 +1,325 @@
+// ips and bps patches, so fixes and translations can be shared without the
+// roms they change. a rom read from a file gets the patch named on the
+// command line, or else one with the rom's name and a .bps or .ips extension
+// sitting beside it. the database, cheats and netplay all go by the patched
+// rom's hash
+//
+// ips is records of an offset and bytes to write there, with runs of one
+// byte stored as a count. bps builds the new rom from copies out of the old
+// one, the new one and the patch, and carries crc32s of the old rom, the new
+// rom and itself which are all checked
+
+use std::path::{Path, PathBuf};
+
+use crate::coverage::crc32;
+use crate::error::Error;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_END: &[u8] = b"EOF";
+const BPS_MAGIC: &[u8] = b"BPS1";
+// crc32s of the source, target and patch
+const BPS_FOOTER: usize = 12;
+// patch files beside a rom, bps first as it is checked
+const EXTENSIONS: [&str; 2] = ["bps", "ips"];
+
+fn damaged() -> Error {
+    Error::Patch { message: "the patch is damaged".to_string() }
+}
+
+// `rom` with `patch` applied, the kind of patch is told from its header
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if let Some(records) = patch.strip_prefix(IPS_MAGIC) {
+        apply_ips(rom, records)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(rom, patch)
+    } else {
+        Err(Error::Patch { message: "not an ips or bps patch".to_string() })
+    }
+}
+
+// a patch named after `rom` next to it
+pub fn beside(rom: &Path) -> Option<PathBuf> {
+    EXTENSIONS.iter().map(|extension| rom.with_extension(extension)).find(|path| path.is_file())
+}
+
+// whether `path` is named like a patch rather than a rom
+pub fn is_patch(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| EXTENSIONS.iter().any(|patch| extension.eq_ignore_ascii_case(patch)))
+}
+
+// the rom at `path` with `patch` or the patch beside it applied
+pub fn read_rom(path: &Path, patch: Option<&Path>) -> Result<Vec<u8>, Error> {
+    let rom = std::fs::read(path)?;
+    match patch.map(Path::to_path_buf).or_else(|| beside(path)) {
+        Some(patch) => apply(&rom, &std::fs::read(&patch).map_err(|error| Error::File { path: patch, error })?),
+        None => Ok(rom),
+    }
+}
+
+fn apply_ips(rom: &[u8], records: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut target = rom.to_vec();
+    let mut reader = Reader { bytes: records };
+    loop {
+        let offset = reader.take(3)?;
+        if offset == IPS_END {
+            break;
+        }
+        let offset = offset.iter().fold(0, |offset, byte| offset << 8 | *byte as usize);
+        let size = reader.word()?;
+        // a size of 0 marks a run of one byte
+        let (length, run) = match size {
+            0 => (reader.word()?, Some(reader.take(1)?[0])),
+            _ => (size, None),
+        };
+        if target.len() < offset + length {
+            target.resize(offset + length, 0);
+        }
+        match run {
+            Some(byte) => target[offset..offset + length].fill(byte),
+            None => target[offset..offset + length].copy_from_slice(reader.take(length)?),
+        }
+    }
+    // patches can end with the length to cut the rom down to
+    match reader.bytes.len() {
+        0 => (),
+        3 => target.truncate(reader.bytes.iter().fold(0, |length, byte| length << 8 | *byte as usize)),
+        _ => return Err(damaged()),
+    }
+    Ok(target)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if patch.len() < BPS_MAGIC.len() + BPS_FOOTER {
+        return Err(damaged());
+    }
+    let (body, footer) = patch.split_at(patch.len() - BPS_FOOTER);
+    let checksum = |index: usize| u32::from_le_bytes([footer[index * 4], footer[index * 4 + 1], footer[index * 4 + 2], footer[index * 4 + 3]]);
+    if crc32(&patch[..patch.len() - 4]) != checksum(2) {
+        return Err(damaged());
+    }
+
+    let mut reader = Reader { bytes: &body[BPS_MAGIC.len()..] };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata = reader.number()?;
+    reader.take(metadata)?;
+    if source_size != rom.len() || crc32(rom) != checksum(0) {
+        return Err(Error::Patch { message: "the patch is for another rom".to_string() });
+    }
+
+    let mut target = Vec::new();
+    // where the next copies out of the source and target start
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while !reader.bytes.is_empty() {
+        let command = reader.number()?;
+        let length = (command >> 2) + 1;
+        if target.len() + length > target_size {
+            return Err(damaged());
+        }
+        match command & 3 {
+            // the bytes at the same place in the source
+            0 => target.extend_from_slice(rom.get(target.len()..target.len() + length).ok_or_else(damaged)?),
+            // bytes from the patch
+            1 => target.extend_from_slice(reader.take(length)?),
+            // bytes from elsewhere in the source
+            2 => {
+                let start = reader.relative(source_offset)?;
+                source_offset = start.checked_add(length).ok_or_else(damaged)?;
+                target.extend_from_slice(rom.get(start..source_offset).ok_or_else(damaged)?);
+            },
+            // bytes already written, which can overlap the ones being written
+            _ => {
+                target_offset = reader.relative(target_offset)?;
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or_else(damaged)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            },
+        }
+    }
+    if target.len() != target_size || crc32(&target) != checksum(1) {
+        return Err(Error::Patch { message: "the patched rom fails its checksum".to_string() });
+    }
+    Ok(target)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        if length > self.bytes.len() {
+            return Err(damaged());
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    // big endian, as ips stores them
+    fn word(&mut self) -> Result<usize, Error> {
+        let bytes = self.take(2)?;
+        Ok((bytes[0] as usize) << 8 | bytes[1] as usize)
+    }
+
+    // bps numbers, 7 bits a byte with the last byte's top bit set
+    fn number(&mut self) -> Result<usize, Error> {
+        let mut number: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.take(1)?[0];
+            number = ((byte & 0x7F) as usize).checked_mul(shift).and_then(|bits| number.checked_add(bits)).ok_or_else(damaged)?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift.checked_mul(0x80).ok_or_else(damaged)?;
+            number = number.checked_add(shift).ok_or_else(damaged)?;
+        }
+    }
+
+    // `offset` moved by a bps number, its lowest bit is the sign
+    fn relative(&mut self, offset: usize) -> Result<usize, Error> {
+        let delta = self.number()?;
+        let moved = match delta & 1 {
+            0 => offset.checked_add(delta >> 1),
+            _ => offset.checked_sub(delta >> 1),
+        };
+        moved.ok_or_else(damaged)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: &[u8] = &[0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08, 0xD0, 0x1F, 0x12, 0x0A];
+
+    fn number(mut value: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let bits = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(0x80 | bits);
+                return bytes;
+            }
+            bytes.push(bits);
+            value -= 1;
+        }
+    }
+
+    // a bps patch of `actions` turning `source` into `target`
+    fn bps(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        patch.extend(number(source.len()));
+        patch.extend(number(target.len()));
+        patch.extend(number(4));
+        patch.extend_from_slice(b"test");
+        patch.extend_from_slice(actions);
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let checksum = crc32(&patch);
+        patch.extend_from_slice(&checksum.to_le_bytes());
+        patch
+    }
+
+    fn action(kind: usize, length: usize) -> Vec<u8> {
+        number((length - 1) << 2 | kind)
+    }
+
+    #[test]
+    fn ips_records_write_bytes_and_runs() {
+        let mut patch = IPS_MAGIC.to_vec();
+        // 2 bytes at 0x0003, then a run of 3 0xFF at 0x000E past the end
+        patch.extend_from_slice(&[0x00, 0x00, 0x03, 0x00, 0x02, 0xBB, 0xCC]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x0E, 0x00, 0x00, 0x00, 0x03, 0xFF]);
+        patch.extend_from_slice(IPS_END);
+        let patched = apply(ROM, &patch).unwrap();
+        assert_eq!(&patched[..6], &[0x00, 0xE0, 0xA2, 0xBB, 0xCC, 0x0C]);
+        assert_eq!(&patched[12..], &[0x00, 0x00, 0xFF, 0xFF, 0xFF], "records past the end grow the rom");
+
+        patch.extend_from_slice(&[0x00, 0x00, 0x04]);
+        assert_eq!(apply(ROM, &patch).unwrap(), [0x00, 0xE0, 0xA2, 0xBB], "a length after the end cuts the rom");
+        assert!(matches!(apply(ROM, &patch[..patch.len() - 5]), Err(Error::Patch { .. })), "patches cut short are refused");
+        assert!(matches!(apply(ROM, b"GARBAGE"), Err(Error::Patch { .. })));
+    }
+
+    #[test]
+    fn bps_actions_build_the_target() {
+        let target = [0x00, 0xE0, 0x12, 0x0A, 0x12, 0x0A, 0x12, 0x0A, 0xD0, 0x1F, 0x00, 0xE0];
+        let mut actions = Vec::new();
+        // the first 2 bytes as they are
+        actions.extend(action(0, 2));
+        // 2 from the end of the source
+        actions.extend(action(2, 2));
+        actions.extend(number(10 << 1));
+        // 4 repeating the last 2 written
+        actions.extend(action(3, 4));
+        actions.extend(number(2 << 1));
+        // the source's D01F at 8, 4 bytes before where the last copy ended
+        actions.extend(action(2, 2));
+        actions.extend(number(4 << 1 | 1));
+        // 2 from the patch
+        actions.extend(action(1, 2));
+        actions.extend_from_slice(&[0x00, 0xE0]);
+        assert_eq!(apply(ROM, &bps(ROM, &target, &actions)).unwrap(), target);
+    }
+
+    #[test]
+    fn bps_checksums_are_verified() {
+        let target = [0x00, 0xE0, 0x12, 0x00];
+        let mut actions = action(0, 2);
+        actions.extend(action(1, 2));
+        actions.extend_from_slice(&[0x12, 0x00]);
+        let patch = bps(ROM, &target, &actions);
+        assert_eq!(apply(ROM, &patch).unwrap(), target);
+
+        let error = |result: Result<Vec<u8>, Error>| match result {
+            Err(Error::Patch { message }) => message,
+            other => panic!("expected a patch error, got {:?}", other),
+        };
+        let mut other_rom = ROM.to_vec();
+        other_rom[5] ^= 1;
+        assert_eq!(error(apply(&other_rom, &patch)), "the patch is for another rom");
+
+        // the last action's byte
+        let mut broken = patch.clone();
+        broken[patch.len() - BPS_FOOTER - 1] ^= 1;
+        assert_eq!(error(apply(ROM, &broken)), "the patch is damaged");
+
+        // a patch that checks out itself but makes the wrong rom
+        let wrong = bps(ROM, &[0x00, 0xE0, 0x12, 0x02], &actions);
+        assert_eq!(error(apply(ROM, &wrong)), "the patched rom fails its checksum");
+    }
+
+    #[test]
+    fn patches_beside_the_rom_are_applied() {
+        let dir = std::env::temp_dir().join(format!("chip_8_patch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("game.ch8");
+        std::fs::write(&rom, ROM).unwrap();
+        assert_eq!(read_rom(&rom, None).unwrap(), ROM, "roms without a patch are read as they are");
+
+        let mut ips = IPS_MAGIC.to_vec();
+        ips.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x01, 0x11]);
+        ips.extend_from_slice(IPS_END);
+        std::fs::write(dir.join("game.ips"), &ips).unwrap();
+        assert_eq!(read_rom(&rom, None).unwrap()[0], 0x11, "a patch with the rom's name is applied");
+
+        let mut actions = action(1, 1);
+        actions.push(0x22);
+        actions.extend(action(0, ROM.len() - 1));
+        let mut target = ROM.to_vec();
+        target[0] = 0x22;
+        std::fs::write(dir.join("game.bps"), bps(ROM, &target, &actions)).unwrap();
+        assert_eq!(read_rom(&rom, None).unwrap()[0], 0x22, "bps patches go first");
+        assert_eq!(read_rom(&rom, Some(&dir.join("game.ips"))).unwrap()[0], 0x11, "a patch given by name wins");
+        assert!(is_patch(&dir.join("game.ips")) && is_patch(Path::new("GAME.BPS")), "patches are told by their extension");
+        assert!(!is_patch(&rom) && !is_patch(Path::new("pong")), "roms are not patches");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
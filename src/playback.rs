@@ -0,0 +1,97 @@
+// pause, single stepping and speed control for the main loop
+
+use std::time::{Duration, Instant};
+
+// wall clock time an uncapped update may spend running frames
+const UNCAPPED_BUDGET: Duration = Duration::from_millis(14);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Speed {
+    Quarter,
+    Half,
+    Normal,
+    Uncapped,
+}
+
+// work the main loop should do on an update event
+pub enum Advance {
+    Idle,
+    Frames(usize),
+    Instruction,
+    Until(Instant),
+}
+
+pub struct Playback {
+    paused: bool,
+    speed: Speed,
+    // fraction of a frame carried between updates in slow motion
+    progress: f64,
+    // frame advance or single step queued while paused
+    requested: Option<Advance>,
+}
+
+impl Playback {
+    pub fn new() -> Playback {
+        Playback { paused: false, speed: Speed::Normal, progress: 0.0, requested: None }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.requested = None;
+    }
+
+    // pauses if running and queues exactly one frame
+    pub fn advance_frame(&mut self) {
+        self.paused = true;
+        self.requested = Some(Advance::Frames(1));
+    }
+
+    // pauses if running and queues exactly one instruction
+    pub fn step_instruction(&mut self) {
+        self.paused = true;
+        self.requested = Some(Advance::Instruction);
+    }
+
+    pub fn toggle_fast_forward(&mut self) {
+        self.speed = if self.speed == Speed::Uncapped { Speed::Normal } else { Speed::Uncapped };
+    }
+
+    // cycles normal -> 0.5x -> 0.25x -> normal
+    pub fn cycle_slow_motion(&mut self) {
+        self.speed = match self.speed {
+            Speed::Normal | Speed::Uncapped => Speed::Half,
+            Speed::Half => Speed::Quarter,
+            Speed::Quarter => Speed::Normal,
+        };
+        self.progress = 0.0;
+    }
+
+    // called once per 60Hz update event
+    pub fn advance(&mut self) -> Advance {
+        if self.paused {
+            return self.requested.take().unwrap_or(Advance::Idle);
+        }
+
+        let rate = match self.speed {
+            Speed::Quarter => 0.25,
+            Speed::Half => 0.5,
+            Speed::Normal => 1.0,
+            Speed::Uncapped => return Advance::Until(Instant::now() + UNCAPPED_BUDGET),
+        };
+        self.progress += rate;
+        let frames = self.progress as usize;
+        self.progress -= frames as f64;
+        Advance::Frames(frames)
+    }
+
+    pub fn title(&self, name: &str) -> String {
+        let speed = match self.speed {
+            Speed::Quarter => " [0.25x]",
+            Speed::Half => " [0.5x]",
+            Speed::Normal => "",
+            Speed::Uncapped => " [fast forward]",
+        };
+        let state = if self.paused { " [paused]" } else { "" };
+        format!("{}{}{}", name, state, speed)
+    }
+}
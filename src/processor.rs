@@ -1,5 +1,36 @@
+use crate::coverage::Coverage;
 use crate::display::Display;
+use crate::error::Error;
 use crate::keyboard::Keyboard;
+use crate::quirks::Quirks;
+
+#[cfg(feature = "jit")]
+mod jit;
+mod snapshot;
+mod threaded;
+
+pub use snapshot::{Snapshot, SNAPSHOT_SIZE};
+
+// programs are loaded after the reserved interpreter area
+pub const PROGRAM_START: usize = 0x200;
+
+// addresses in I and the program counter wrap at the 12 bit (4096 byte)
+// memory boundary, XO-CHIP's 64K memory would use 0xFFFF
+pub const ADDRESS_MASK: u16 = 0xFFF;
+
+// how `step` and `run_frame` execute instructions, both behave the same
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Backend {
+    // decodes every opcode as it is fetched
+    #[default]
+    Interpreter,
+    // runs blocks of pre-decoded handlers cached by address
+    Threaded,
+    // compiles hot blocks to native code with cranelift
+    #[cfg(feature = "jit")]
+    Jit,
+}
+
 
 pub struct Processor {
     // storage
@@ -16,20 +47,53 @@ pub struct Processor {
     sound_timer: u8,
     delay_timer: u8,
 
+    // interpreter behaviour the rom expects
+    quirks: Quirks,
+
+    // execution backend and the blocks decoded by the threaded one
+    backend: Backend,
+    blocks: threaded::Cache,
+    #[cfg(feature = "jit")]
+    jit: Option<Box<jit::Jit>>,
+
+    // memory use, recorded while the interpreter runs
+    coverage: Option<Box<Coverage>>,
+
+    // source of CXNN's random numbers, seeded to replay a run
+    rng: Random,
+
     // hardware
     pub display : Display,
     pub keyboard : Keyboard,
 
 }
 
-fn read_word(memory: [u8; 4096], index: u16) -> u16 {
+// splitmix64, its whole state is one word that snapshots can save
+#[derive(Clone, Copy)]
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u8 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        (z ^ (z >> 31)) as u8
+    }
+}
+
+fn read_word(memory: &[u8; 4096], index: u16) -> u16 {
     // Apply XOR to index and index +1
-    (memory[index as usize] as u16) << 8
-        | (memory[(index + 1) as usize] as u16)
+    (memory[(index & ADDRESS_MASK) as usize] as u16) << 8
+        | (memory[((index + 1) & ADDRESS_MASK) as usize] as u16)
 }
 
-fn not_implemented(op: u16, pc: u16) { panic!("Not implemented:: op: {:x}, pc: {:x}", op, pc) }
 
+impl Default for Processor {
+    fn default() -> Processor {
+        Processor::new()
+    }
+}
 
 impl Processor {
     pub fn new() -> Processor {
@@ -42,6 +106,13 @@ impl Processor {
             stack_pointer: 0,
             sound_timer: 0,
             delay_timer: 0,
+            quirks: Quirks::default(),
+            backend: Backend::default(),
+            blocks: threaded::Cache::default(),
+            #[cfg(feature = "jit")]
+            jit: None,
+            coverage: None,
+            rng: Random(rand::random()),
             keyboard: Keyboard::new(),
             display: Display::new()
         }
@@ -61,33 +132,188 @@ impl Processor {
         self.keyboard.clear();
         // set reserved memory
         self.memory[ 0 .. 80].copy_from_slice(&FONT_SET);
+        self.forget_code();
     }
 
-    pub fn load_rom(&mut self, rom: &str){
-        match std::fs::read(rom) {
-            Ok(bytes) => {
-                self.memory[ 0x200 .. 0x200 + bytes.len() as usize].copy_from_slice(&bytes);
-            },
-            Err(e) => panic!("{}", e)
+    // loads the rom at `rom` with the patch beside it, if there is one
+    pub fn load_rom<P: AsRef<std::path::Path>>(&mut self, rom: P) -> Result<(), Error> {
+        let bytes = crate::patch::read_rom(rom.as_ref(), None)?;
+        self.load_program(&bytes)
+    }
+
+    pub fn load_program(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        if bytes.len() > self.memory.len() - PROGRAM_START {
+            return Err(Error::RomTooLarge { size: bytes.len() });
         }
+        self.memory[PROGRAM_START..PROGRAM_START + bytes.len()].copy_from_slice(bytes);
+        self.forget_code();
+        Ok(())
+    }
 
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
     }
 
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
 
-    pub fn execute_cycle(&mut self) {
-        // fetch opcode
-        let opcode = read_word(self.memory, self.program_counter);
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        // the interpreter doesn't keep cached blocks up to date
+        self.forget_code();
+        self.backend = backend;
+    }
+
+    // opcodes run through the interpreter whatever the backend while
+    // coverage is recorded, turning it on starts from nothing
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.forget_code();
+        self.coverage = if enabled { Some(Box::default()) } else { None };
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_deref()
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.register
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn stack_pointer(&self) -> u8 {
+        self.stack_pointer
+    }
+
+    // return addresses currently on the stack, oldest first
+    pub fn call_stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8; 4096] {
+        &self.memory
+    }
+
+    pub fn set_register(&mut self, index: usize, value: u8) {
+        self.register[index] = value;
+    }
+
+    pub fn set_program_counter(&mut self, address: u16) {
+        self.program_counter = address & ADDRESS_MASK;
+    }
+
+    pub fn set_index_register(&mut self, address: u16) {
+        self.index_register = address & ADDRESS_MASK;
+    }
+
+    // depths past the 16 stack slots are clamped
+    pub fn set_stack_pointer(&mut self, depth: u8) {
+        self.stack_pointer = depth.min(self.stack.len() as u8);
+    }
+
+    // copies `bytes` into memory from `address`, wrapping at the end of memory
+    pub fn write_memory(&mut self, address: u16, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.memory[(address as usize + offset) & ADDRESS_MASK as usize] = *byte;
+        }
+        // the bytes may hold code that was already decoded or compiled
+        self.forget_code();
+    }
+
+    // CXNN draws the same numbers after each call with the same seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Random(seed);
+    }
+
+    // opcode stored at `address`, wrapping at the end of memory
+    pub fn opcode_at(&self, address: u16) -> u16 {
+        read_word(&self.memory, address & ADDRESS_MASK)
+    }
+
+
+    pub fn execute_cycle(&mut self) -> Result<(), Error> {
+        self.step()?;
+        self.tick_timers();
+        Ok(())
+    }
+
+    // runs a 60Hz frame, the timers tick once after `instructions` instructions
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Error> {
+        match self.running_backend() {
+            Backend::Interpreter => {
+                for _ in 0..instructions {
+                    self.interpret()?;
+                }
+            },
+            Backend::Threaded => self.run_threaded(instructions)?,
+            #[cfg(feature = "jit")]
+            Backend::Jit => self.run_jit(instructions)?,
+        }
+        self.tick_timers();
+        Ok(())
+    }
 
-        // execute opcode
-        self.execute_opcode(opcode);
+    // executes a single instruction without touching the timers
+    pub fn step(&mut self) -> Result<(), Error> {
+        match self.running_backend() {
+            Backend::Interpreter => self.interpret(),
+            Backend::Threaded => self.run_threaded(1),
+            #[cfg(feature = "jit")]
+            Backend::Jit => self.run_jit(1),
+        }
+    }
 
-        // update timers
+    pub fn tick_timers(&mut self) {
         self.decrement_delay_timer();
         self.decrement_sound_timer();
     }
 
-    fn execute_opcode(&mut self, opcode: u16) {
-        // break up into nibbles
+    fn running_backend(&self) -> Backend {
+        if self.coverage.is_some() {
+            Backend::Interpreter
+        } else {
+            self.backend
+        }
+    }
+
+    #[inline(always)]
+    fn interpret(&mut self) -> Result<(), Error> {
+        // fetch opcode
+        let opcode = read_word(&self.memory, self.program_counter);
+        let (pc, index) = (self.program_counter, self.index_register);
+
+        // execute opcode, failing ones did nothing to be recorded
+        self.execute_opcode(opcode)?;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(pc, opcode, index);
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn execute_opcode(&mut self, opcode: u16) -> Result<(), Error> {
+        // break up into nibbles. decoding on every fetch is a few shifts and
+        // a jump table, and measured faster than a table of opcodes decoded
+        // by address, which needs a lookup and a check that memory hasn't
+        // changed. the threaded backend is the one that runs pre-decoded code
         let op_1 = (opcode & 0xF000) >> 12;
         let op_2 = (opcode & 0x0F00) >> 8;
         let op_3 = (opcode & 0x00F0) >> 4;
@@ -106,8 +332,11 @@ impl Processor {
 
         // println!("Nibbles: {} {} {} {}", op_1, op_2, op_3, op_4);
 
-        // we read the opcode so move program counter forward
-        self.program_counter += 2;
+        // we read the opcode so move program counter forward, the new value is
+        // kept local and stored once so the fetch of the next opcode doesn't
+        // wait on a store. a failing opcode leaves the program counter on itself
+        let pc = self.program_counter;
+        let mut next = (pc + 2) & ADDRESS_MASK;
 
         match (op_1, op_2, op_3, op_4) {
             // Clear Screen
@@ -115,48 +344,63 @@ impl Processor {
 
             // Return from subroutine
             (0, 0, 0xE, 0xE) => {
+                if self.stack_pointer == 0 {
+                    return Err(Error::StackUnderflow { pc });
+                }
                 self.stack_pointer -= 1;
-                self.program_counter = self.stack[self.stack_pointer as usize]
+                next = self.stack[self.stack_pointer as usize]
             },
 
             // Jump to Address NNN
-            (0x1, _, _, _) => self.program_counter = nnn,
+            (0x1, _, _, _) => next = nnn,
 
             // Call subroutine
             (0x2, _, _, _) => {
-                self.stack[self.stack_pointer as usize] = self.program_counter;
+                if self.stack_pointer as usize == self.stack.len() {
+                    return Err(Error::StackOverflow { pc });
+                }
+                self.stack[self.stack_pointer as usize] = next;
                 self.stack_pointer += 1;
-                self.program_counter = nnn;
+                next = nnn;
             },
 
             // Skips the next instruction if VX equals NN
-            (0x3, _, _, _) => self.program_counter += if vx == nn { 2 } else { 0 },
+            (0x3, _, _, _) => next += if vx == nn { 2 } else { 0 },
 
             // Skips the next instruction if VX doesn't equals NN
-            (0x4, _, _, _) => self.program_counter += if vx != nn { 2 } else { 0 },
+            (0x4, _, _, _) => next += if vx != nn { 2 } else { 0 },
 
             // Skips the next instruction if VX equals VY
-            (0x5, _, _, _) => self.program_counter += if vx == vy { 2 } else { 0 },
+            (0x5, _, _, _) => next += if vx == vy { 2 } else { 0 },
 
             // Sets VX to NN
             (0x6, _, _, _) => self.register[x] = nn,
 
             // Adds NN to VX
             (0x7, _, _, _) => {
-                self.register[x] += nn
+                self.register[x] = vx.wrapping_add(nn)
             },
 
             // Sets VX to the value of VY.
             (0x8, _, _, 0x0) => self.register[x] = vy,
 
             // Sets VX to VX or VY (Bitwise OR operation)
-            (0x8, _, _, 0x1) => self.register[x] = vx | vy,
+            (0x8, _, _, 0x1) => {
+                self.register[x] = vx | vy;
+                self.logic_reset_vf();
+            }
 
             // Sets VX to VX and VY (Bitwise AND operation)
-            (0x8, _, _, 0x2) => self.register[x] = vx & vy,
+            (0x8, _, _, 0x2) => {
+                self.register[x] = vx & vy;
+                self.logic_reset_vf();
+            }
 
             // Sets VX to VX xor VY
-            (0x8, _, _, 0x3) => self.register[x] = vx ^ vy,
+            (0x8, _, _, 0x3) => {
+                self.register[x] = vx ^ vy;
+                self.logic_reset_vf();
+            }
 
             // Adds VY to VX. VF is set to 1 if there's a carry
             (0x8, _, _, 0x4) => {
@@ -174,8 +418,9 @@ impl Processor {
 
             // Store least significant bit of VX in VF and shifts VX to the right by 1
             (0x8, _, _, 0x6) => {
-                self.register[0xF] = vx & 0x1;
-                self.register[x] >>= 1;
+                let source = if self.quirks.shift_uses_vy { vy } else { vx };
+                self.register[0xF] = source & 0x1;
+                self.register[x] = source >> 1;
             }
 
             // Sets VX to VY minus VX. VF is set to 0 when there's a borrow
@@ -187,47 +432,59 @@ impl Processor {
 
             // Stores the most significant bit of VX in VF and then shifts VX to the left by 1
             (0x8, _, _, 0xE) => {
-                self.register[0xF] = vx & 0x80;
-                self.register[x] <<= 1;
+                let source = if self.quirks.shift_uses_vy { vy } else { vx };
+                self.register[0xF] = (source & 0x80) >> 7;
+                self.register[x] = source << 1;
             }
 
             // Skips the next instruction if VX doesn't equal VY
-            (0x9, _, _, 0x0) => self.program_counter += if vx != vy { 2 } else { 0 },
+            (0x9, _, _, 0x0) => next += if vx != vy { 2 } else { 0 },
 
             // Sets I to the address NNN
             (0xA, _, _, _) => self.index_register = nnn,
 
             // Jumps to the address NNN plus V0.
-            (0xB, _, _, _) => self.index_register = nnn + self.register[0] as u16,
+            (0xB, _, _, _) => {
+                let offset = if self.quirks.jump_uses_vx { vx } else { self.register[0] };
+                next = nnn + offset as u16;
+            }
 
             // Set VX to random number and NN
-            (0xC, _, _, _) => self.register[x] = nn & rand::random::<u8>(),
+            (0xC, _, _, _) => self.register[x] = nn & self.rng.next(),
 
             // Draws a sprite at coordinate (VX, VY), set VF to 1 if pixels unset else 0
             (0xD, _, _, _) => {
-                let sprite = &self.memory
-                    [self.index_register as usize..(self.index_register + n as u16) as usize];
-                self.register[0xF] = self.display.draw(vx, vy, sprite) as u8
+                let mut sprite = [0; 15];
+                for (row, byte) in sprite.iter_mut().enumerate().take(n as usize) {
+                    *byte = self.memory[self.address(row)];
+                }
+                let sprite = &sprite[..n as usize];
+                let collision = if self.quirks.clip_sprites {
+                    self.display.draw_clipped(vx, vy, sprite)
+                } else {
+                    self.display.draw(vx, vy, sprite)
+                };
+                self.register[0xF] = collision as u8
             },
 
             // Skips the next instruction if the key stored in VX is pressed
-            (0xE, _, 0x9, 0xE) => self.program_counter += if self.keyboard.pressed(vx as usize) { 2 } else { 0 },
+            (0xE, _, 0x9, 0xE) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 2 } else { 0 },
 
             // Skips the next instruction if the key stored in VX isn't pressed
-            (0xE, _, 0xA, 0x1) => self.program_counter += if self.keyboard.pressed(vx as usize) { 0 } else { 2 },
+            (0xE, _, 0xA, 0x1) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 0 } else { 2 },
 
             // Sets VX to the value of the delay timer
             (0xF, _, 0x0, 0x7) => self.register[x] = self.delay_timer,
 
             // A key press is awaited, and then stored in VX
             (0xF, _, 0x0, 0xA) => {
-                // move back register (no key is pressed)
-                self.program_counter -= 2;
+                // stay on this opcode while no key is pressed
+                next = pc;
 
-                for key in 0..0xF {
+                for key in 0..16 {
                     if self.keyboard.pressed(key) {
                         self.register[x] = key as u8;
-                        self.program_counter += 2;
+                        next = pc + 2;
                     }
                 }
             },
@@ -239,31 +496,67 @@ impl Processor {
             (0xF, _, 0x1, 0x8) => self.sound_timer = vx,
 
             // Adds VX to I
-            (0xF, _, 0x1, 0xE) => self.index_register += vx as u16,
+            (0xF, _, 0x1, 0xE) => self.index_register = (self.index_register + vx as u16) & ADDRESS_MASK,
 
             // Sets I to the location of the sprite for the character in VX
-            (0xF, _, 0x2, 0x9) => self.index_register = (vx * 5) as u16,
+            (0xF, _, 0x2, 0x9) => self.index_register = vx as u16 * 5,
 
             // Set the decimal rep of VX to memory
             (0xF, _, 0x3, 0x3) => {
-                self.memory[self.index_register as usize] = vx / 100;
-                self.memory[self.index_register as usize + 1] = (vx / 10) % 10;
-                self.memory[self.index_register as usize + 2] = (vx % 100) % 10;
+                self.memory[self.address(0)] = vx / 100;
+                self.memory[self.address(1)] = (vx / 10) % 10;
+                self.memory[self.address(2)] = (vx % 100) % 10;
             }
 
             // Stores V0 to VX (including VX) in memory starting at address I
-            (0xF, _, 0x5, 0x5) => self.memory
-                [self.index_register as usize..(x + 1 + self.index_register as usize)]
-                .copy_from_slice(&self.register[0..x + 1]),
+            (0xF, _, 0x5, 0x5) => {
+                for offset in 0..=x {
+                    self.memory[self.address(offset)] = self.register[offset];
+                }
+                self.load_store_increment_i(x);
+            }
 
             // Fills V0 to VX (including VX) with values from memory starting at address I
-            (0xF, _, 0x6, 0x5) => self.register[0..x + 1]
-                .copy_from_slice(&self.memory
-                    [self.index_register as usize..(x + 1 + self.index_register as usize)]),
+            (0xF, _, 0x6, 0x5) => {
+                for offset in 0..=x {
+                    self.register[offset] = self.memory[self.address(offset)];
+                }
+                self.load_store_increment_i(x);
+            }
 
             // ...
-            (_, _, _, _) => not_implemented(opcode, self.program_counter)
+            (_, _, _, _) => return Err(Error::UnknownOpcode { opcode, pc })
         }
+
+        // jumps and skips also wrap at the end of memory
+        self.program_counter = next & ADDRESS_MASK;
+        Ok(())
+    }
+
+    // drops decoded and compiled blocks after memory changed under them
+    fn forget_code(&mut self) {
+        self.blocks.clear();
+        #[cfg(feature = "jit")]
+        {
+            self.jit = None;
+        }
+    }
+
+    fn logic_reset_vf(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.register[0xF] = 0;
+        }
+    }
+
+    fn load_store_increment_i(&mut self, x: usize) {
+        if self.quirks.load_store_increments_i {
+            self.index_register = (self.index_register + x as u16 + 1) & ADDRESS_MASK;
+        }
+    }
+
+    // memory index `offset` bytes past I, wrapping at the end of memory
+    fn address(&self, offset: usize) -> usize {
+        (self.index_register as usize + offset) & ADDRESS_MASK as usize
     }
 
     fn decrement_delay_timer(&mut self) {
@@ -277,7 +570,7 @@ impl Processor {
         }
     }
 }
-static FONT_SET: [u8; 80] = [0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70,
+pub(crate) static FONT_SET: [u8; 80] = [0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70,
 0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0,
 0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0,
 0xF0, 0x80, 0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40,
@@ -294,7 +587,7 @@ mod tests {
     #[test]
     fn opcode_jp() {
         let mut cpu = Processor::new();
-        cpu.execute_opcode(0x1A2A);
+        cpu.execute_opcode(0x1A2A).unwrap();
         assert_eq!(cpu.program_counter, 0x0A2A, "the program counter is updated");
     }
 
@@ -304,7 +597,7 @@ mod tests {
         let addr = 0x23;
         cpu.program_counter = addr;
 
-        cpu.execute_opcode(0x2ABC);
+        cpu.execute_opcode(0x2ABC).unwrap();
 
         assert_eq!(cpu.program_counter, 0x0ABC, "the program counter is updated to the new address");
         assert_eq!(cpu.stack_pointer, 1, "the stack pointer is incremented");
@@ -317,11 +610,11 @@ mod tests {
         cpu.register[1] = 0xFE;
 
         // vx == kk
-        cpu.execute_opcode(0x31FE);
+        cpu.execute_opcode(0x31FE).unwrap();
         assert_eq!(cpu.program_counter, 4, "the stack pointer skips");
 
         // vx != kk
-        cpu.execute_opcode(0x31FA);
+        cpu.execute_opcode(0x31FA).unwrap();
         assert_eq!(cpu.program_counter, 6, "the stack pointer is incremented");
     }
 
@@ -331,11 +624,11 @@ mod tests {
         cpu.register[1] = 0xFE;
 
         // vx == kk
-        cpu.execute_opcode(0x41FE);
+        cpu.execute_opcode(0x41FE).unwrap();
         assert_eq!(cpu.program_counter, 2, "the stack pointer is incremented");
 
         // vx != kk
-        cpu.execute_opcode(0x41FA);
+        cpu.execute_opcode(0x41FA).unwrap();
         assert_eq!(cpu.program_counter, 6, "the stack pointer skips");
     }
 
@@ -347,11 +640,11 @@ mod tests {
         cpu.register[3] = 3;
 
         // vx == vy
-        cpu.execute_opcode(0x5230);
+        cpu.execute_opcode(0x5230).unwrap();
         assert_eq!(cpu.program_counter, 4, "the stack pointer skips");
 
         // vx != vy
-        cpu.execute_opcode(0x5130);
+        cpu.execute_opcode(0x5130).unwrap();
         assert_eq!(cpu.program_counter, 6, "the stack pointer is incremented");
     }
 
@@ -363,11 +656,11 @@ mod tests {
         cpu.register[3] = 3;
 
         // vx == vy
-        cpu.execute_opcode(0x9230);
+        cpu.execute_opcode(0x9230).unwrap();
         assert_eq!(cpu.program_counter, 2, "the stack pointer is incremented");
 
         // vx != vy
-        cpu.execute_opcode(0x9130);
+        cpu.execute_opcode(0x9130).unwrap();
         assert_eq!(cpu.program_counter, 6, "the stack pointer skips");
     }
 
@@ -376,7 +669,7 @@ mod tests {
         let mut cpu = Processor::new();
         cpu.register[1] = 3;
 
-        cpu.execute_opcode(0x7101);
+        cpu.execute_opcode(0x7101).unwrap();
         assert_eq!(cpu.register[1], 4, "Vx was incremented by one");
     }
 
@@ -386,7 +679,7 @@ mod tests {
         cpu.register[1] = 3;
         cpu.register[0] = 0;
 
-        cpu.execute_opcode(0x8010);
+        cpu.execute_opcode(0x8010).unwrap();
         assert_eq!(cpu.register[0], 3, "Vx was loaded with vy");
     }
 
@@ -396,7 +689,7 @@ mod tests {
         cpu.register[2] = 0b01101100;
         cpu.register[3] = 0b11001110;
 
-        cpu.execute_opcode(0x8231);
+        cpu.execute_opcode(0x8231).unwrap();
         assert_eq!(cpu.register[2], 0b11101110, "Vx was loaded with vx OR vy");
     }
 
@@ -406,7 +699,7 @@ mod tests {
         cpu.register[2] = 0b01101100;
         cpu.register[3] = 0b11001110;
 
-        cpu.execute_opcode(0x8232);
+        cpu.execute_opcode(0x8232).unwrap();
         assert_eq!(cpu.register[2], 0b01001100, "Vx was loaded with vx AND vy");
     }
 
@@ -416,7 +709,7 @@ mod tests {
         cpu.register[2] = 0b01101100;
         cpu.register[3] = 0b11001110;
 
-        cpu.execute_opcode(0x8233);
+        cpu.execute_opcode(0x8233).unwrap();
         assert_eq!(cpu.register[2], 0b10100010, "Vx was loaded with vx XOR vy");
     }
 
@@ -427,11 +720,11 @@ mod tests {
         cpu.register[2] = 100;
         cpu.register[3] = 250;
 
-        cpu.execute_opcode(0x8124);
+        cpu.execute_opcode(0x8124).unwrap();
         assert_eq!(cpu.register[1], 110, "Vx was loaded with vx + vy");
         assert_eq!(cpu.register[0xF], 0, "no overflow occurred");
 
-        cpu.execute_opcode(0x8134);
+        cpu.execute_opcode(0x8134).unwrap();
         assert_eq!(cpu.register[1], 0x68, "Vx was loaded with vx + vy");
         assert_eq!(cpu.register[0xF], 1, "overflow occurred");
     }
@@ -446,7 +739,7 @@ mod tests {
         cpu.index_register = 0x300;
 
         // load v0 - v2 into memory at i
-        cpu.execute_opcode(0xF255);
+        cpu.execute_opcode(0xF255).unwrap();
         assert_eq!(cpu.memory[cpu.index_register as usize], 5, "V0 was loaded into memory at i");
         assert_eq!(cpu.memory[cpu.index_register as usize + 1], 4, "V1 was loaded into memory at i + 1");
         assert_eq!(cpu.memory[cpu.index_register as usize + 2], 3, "V2 was loaded into memory at i + 2");
@@ -460,7 +753,7 @@ mod tests {
         cpu.register[2] = 234;
 
         // load v0 - v2 from memory at i
-        cpu.execute_opcode(0xF233);
+        cpu.execute_opcode(0xF233).unwrap();
         assert_eq!(cpu.memory[cpu.index_register as usize], 2, "hundreds");
         assert_eq!(cpu.memory[cpu.index_register as usize + 1], 3, "tens");
         assert_eq!(cpu.memory[cpu.index_register as usize + 2], 4, "digits");
@@ -477,7 +770,7 @@ mod tests {
 
 
         // load v0 - v2 from memory at i
-        cpu.execute_opcode(0xF265);
+        cpu.execute_opcode(0xF265).unwrap();
         assert_eq!(cpu.register[0], 5, "V0 was loaded from memory at i");
         assert_eq!(cpu.register[1], 4, "V1 was loaded from memory at i + 1");
         assert_eq!(cpu.register[2], 3, "V2 was loaded from memory at i + 2");
@@ -491,9 +784,9 @@ mod tests {
         cpu.program_counter = addr;
 
         // jump to 0x0ABC
-        cpu.execute_opcode(0x2ABC);
+        cpu.execute_opcode(0x2ABC).unwrap();
         // return
-        cpu.execute_opcode(0x00EE);
+        cpu.execute_opcode(0x00EE).unwrap();
 
         assert_eq!(cpu.program_counter, 0x25, "the program counter is updated to the new address");
         assert_eq!(cpu.stack_pointer, 0, "the stack pointer is decremented");
@@ -504,15 +797,15 @@ mod tests {
     fn opcode_ld_i_addr() {
         let mut cpu = Processor::new();
 
-        cpu.execute_opcode(0x61AA);
+        cpu.execute_opcode(0x61AA).unwrap();
         assert_eq!(cpu.register[1], 0xAA, "V1 is set");
         assert_eq!(cpu.program_counter, 2, "the program counter is advanced two bytes");
 
-        cpu.execute_opcode(0x621A);
+        cpu.execute_opcode(0x621A).unwrap();
         assert_eq!(cpu.register[2], 0x1A, "V2 is set");
         assert_eq!(cpu.program_counter, 4, "the program counter is advanced two bytes");
 
-        cpu.execute_opcode(0x6A15);
+        cpu.execute_opcode(0x6A15).unwrap();
         assert_eq!(cpu.register[10], 0x15, "V10 is set");
         assert_eq!(cpu.program_counter, 6, "the program counter is advanced two bytes");
     }
@@ -520,9 +813,348 @@ mod tests {
     #[test]
     fn opcode_axxx() {
         let mut cpu = Processor::new();
-        cpu.execute_opcode(0xAFAF);
+        cpu.execute_opcode(0xAFAF).unwrap();
 
         assert_eq!(cpu.index_register, 0x0FAF, "the 'i' register is updated");
         assert_eq!(cpu.program_counter, 2, "the program counter is advanced two bytes");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn opcode_ld_vx_k() {
+        let mut cpu = Processor::new();
+        cpu.program_counter = 0x200;
+        cpu.execute_opcode(0xF30A).unwrap();
+        assert_eq!(cpu.program_counter, 0x200, "the program counter waits while no key is pressed");
+
+        cpu.keyboard.key_press(0xF);
+        cpu.execute_opcode(0xF30A).unwrap();
+        assert_eq!(cpu.register[3], 0xF, "key F is stored");
+        assert_eq!(cpu.program_counter, 0x202, "key F ends the wait");
+    }
+
+    #[test]
+    fn opcode_ret_empty_stack() {
+        let mut cpu = Processor::new();
+        let result = cpu.execute_opcode(0x00EE);
+        assert!(matches!(result, Err(Error::StackUnderflow { pc: 0 })), "return without call is reported");
+    }
+
+    #[test]
+    fn opcode_call_full_stack() {
+        let mut cpu = Processor::new();
+        for _ in 0..16 {
+            cpu.execute_opcode(0x2000).unwrap();
+        }
+        let result = cpu.execute_opcode(0x2000);
+        assert!(matches!(result, Err(Error::StackOverflow { .. })), "17th nested call is reported");
+    }
+
+
+    #[test]
+    fn opcode_unknown() {
+        let mut cpu = Processor::new();
+        let result = cpu.execute_opcode(0xFFFF);
+        assert!(matches!(result, Err(Error::UnknownOpcode { opcode: 0xFFFF, pc: 0 })), "unknown opcode is reported");
+        assert_eq!(cpu.program_counter, 0, "the program counter stays on the failing opcode");
+    }
+
+    #[test]
+    fn load_program_too_large() {
+        let mut cpu = Processor::new();
+        assert!(cpu.load_program(&[0; 4096 - PROGRAM_START]).is_ok(), "rom filling memory loads");
+        let result = cpu.load_program(&[0; 4096 - PROGRAM_START + 1]);
+        assert!(matches!(result, Err(Error::RomTooLarge { .. })), "oversized rom is reported");
+    }
+
+    #[test]
+    fn random_programs_never_panic() {
+        for _ in 0..50 {
+            let rom: Vec<u8> = (0..4096 - PROGRAM_START).map(|_| rand::random()).collect();
+            let mut cpu = Processor::new();
+            cpu.reset();
+            cpu.load_program(&rom).unwrap();
+
+            for cycle in 0..2000 {
+                cpu.keyboard.key_press(cycle as u8 & 0xF);
+                if cpu.execute_cycle().is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn opcode_add_vx_kk_wraps() {
+        let mut cpu = Processor::new();
+        cpu.register[1] = 0xFF;
+
+        cpu.execute_opcode(0x7102).unwrap();
+        assert_eq!(cpu.register[1], 0x01, "Vx wraps past 0xFF");
+        assert_eq!(cpu.register[0xF], 0, "VF is untouched by ADD Vx, byte");
+    }
+
+    #[test]
+    fn opcode_sub_vx_vy_borrow() {
+        let mut cpu = Processor::new();
+        cpu.register[1] = 0x01;
+        cpu.register[2] = 0x02;
+
+        cpu.execute_opcode(0x8125).unwrap();
+        assert_eq!(cpu.register[1], 0xFF, "Vx wraps below zero");
+        assert_eq!(cpu.register[0xF], 0, "borrow occurred");
+
+        cpu.execute_opcode(0x8125).unwrap();
+        assert_eq!(cpu.register[1], 0xFD, "Vx was loaded with vx - vy");
+        assert_eq!(cpu.register[0xF], 1, "no borrow occurred");
+    }
+
+    #[test]
+    fn opcode_subn_vx_vy_borrow() {
+        let mut cpu = Processor::new();
+        cpu.register[1] = 0x02;
+        cpu.register[2] = 0x01;
+
+        cpu.execute_opcode(0x8127).unwrap();
+        assert_eq!(cpu.register[1], 0xFF, "Vx wraps below zero");
+        assert_eq!(cpu.register[0xF], 0, "borrow occurred");
+    }
+
+    #[test]
+    fn opcode_shl_vx_overflow() {
+        let mut cpu = Processor::new();
+        cpu.register[1] = 0b10000001;
+
+        cpu.execute_opcode(0x810E).unwrap();
+        assert_eq!(cpu.register[1], 0b00000010, "the top bit is shifted out");
+        assert_eq!(cpu.register[0xF], 1, "VF holds the shifted out bit");
+    }
+
+    #[test]
+    fn opcode_add_i_vx_wraps() {
+        let mut cpu = Processor::new();
+        cpu.index_register = 0xFFE;
+        cpu.register[1] = 3;
+
+        cpu.execute_opcode(0xF11E).unwrap();
+        assert_eq!(cpu.index_register, 0x001, "I wraps at the end of memory");
+    }
+
+    #[test]
+    fn opcode_ld_f_vx_large() {
+        let mut cpu = Processor::new();
+        cpu.register[1] = 0xFF;
+
+        cpu.execute_opcode(0xF129).unwrap();
+        assert_eq!(cpu.index_register, 0xFF * 5, "the sprite address is computed in 16 bits");
+    }
+
+    #[test]
+    fn opcode_jp_v0_wraps() {
+        let mut cpu = Processor::new();
+        cpu.register[0] = 2;
+
+        cpu.execute_opcode(0xBFFF).unwrap();
+        assert_eq!(cpu.program_counter, 0x001, "the jump target wraps at the end of memory");
+    }
+
+    #[test]
+    fn program_counter_wraps() {
+        let mut cpu = Processor::new();
+        cpu.program_counter = 0xFFE;
+
+        cpu.execute_opcode(0x6000).unwrap();
+        assert_eq!(cpu.program_counter, 0x000, "the fetch advance wraps");
+
+        cpu.program_counter = 0xFFC;
+        cpu.execute_opcode(0x3000).unwrap();
+        assert_eq!(cpu.program_counter, 0x000, "a skip wraps");
+
+        cpu.program_counter = 0xFFE;
+        cpu.execute_opcode(0x2ABC).unwrap();
+        assert_eq!(cpu.stack[0], 0x000, "the return address wraps");
+
+        cpu.program_counter = 0xFFE;
+        cpu.execute_opcode(0xF00A).unwrap();
+        assert_eq!(cpu.program_counter, 0xFFE, "a key wait at the end of memory stays put");
+        cpu.keyboard.key_press(0x3);
+        cpu.execute_opcode(0xF00A).unwrap();
+        assert_eq!(cpu.program_counter, 0x000, "and wraps once a key is pressed");
+    }
+
+    #[test]
+    fn fetch_wraps() {
+        let mut cpu = Processor::new();
+        cpu.program_counter = 0xFFF;
+        cpu.memory[0xFFF] = 0x61;
+        cpu.memory[0x000] = 0x23;
+
+        cpu.execute_cycle().unwrap();
+        assert_eq!(cpu.register[1], 0x23, "the opcode is read across the end of memory");
+        assert_eq!(cpu.program_counter, 0x001, "the program counter wraps");
+    }
+
+    #[test]
+    fn opcode_drw_wraps() {
+        let mut cpu = Processor::new();
+        cpu.index_register = 0xFFF;
+        cpu.memory[0xFFF] = 0x80;
+        cpu.memory[0x000] = 0x80;
+
+        cpu.execute_opcode(0xD002).unwrap();
+        let buffer = cpu.display.get_buffer();
+        assert!(buffer[0][0] && buffer[1][0], "the sprite is read across the end of memory");
+    }
+
+    #[test]
+    fn opcode_ld_b_vx_wraps() {
+        let mut cpu = Processor::new();
+        cpu.index_register = 0xFFE;
+        cpu.register[2] = 234;
+
+        cpu.execute_opcode(0xF233).unwrap();
+        assert_eq!(cpu.memory[0xFFE], 2, "hundreds");
+        assert_eq!(cpu.memory[0xFFF], 3, "tens");
+        assert_eq!(cpu.memory[0x000], 4, "digits wrap to the start of memory");
+    }
+
+    #[test]
+    fn opcode_ld_i_vx_wraps() {
+        let mut cpu = Processor::new();
+        cpu.index_register = 0xFFF;
+        cpu.register[0] = 5;
+        cpu.register[1] = 4;
+
+        cpu.execute_opcode(0xF155).unwrap();
+        assert_eq!(cpu.memory[0xFFF], 5, "V0 was stored at i");
+        assert_eq!(cpu.memory[0x000], 4, "V1 wraps to the start of memory");
+
+        cpu.register[0] = 0;
+        cpu.register[1] = 0;
+        cpu.execute_opcode(0xF165).unwrap();
+        assert_eq!(cpu.register[0], 5, "V0 was loaded from i");
+        assert_eq!(cpu.register[1], 4, "V1 was loaded from the start of memory");
+    }
+
+    #[test]
+    fn run_frame_ticks_timers_once() {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        // LD V0, 0x05; LD DT, V0; JP 0x204
+        cpu.load_program(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04]).unwrap();
+
+        cpu.run_frame(10).unwrap();
+        assert_eq!(cpu.delay_timer, 4, "the delay timer ticks once per frame");
+        assert_eq!(cpu.program_counter, 0x204, "all instructions of the frame ran");
+    }
+
+    #[test]
+    fn step_leaves_timers() {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.load_program(&[0x60, 0x01]).unwrap();
+        cpu.delay_timer = 5;
+        cpu.sound_timer = 5;
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.delay_timer, 5, "the delay timer is untouched");
+        assert_eq!(cpu.sound_timer, 5, "the sound timer is untouched");
+        assert_eq!(cpu.program_counter, 0x202, "one instruction ran");
+    }
+
+    #[test]
+    fn quirk_shift_uses_vy() {
+        let mut cpu = Processor::new();
+        cpu.set_quirks(Quirks { shift_uses_vy: true, ..Quirks::default() });
+        cpu.register[1] = 0;
+        cpu.register[2] = 0b10000011;
+
+        cpu.execute_opcode(0x8126).unwrap();
+        assert_eq!(cpu.register[1], 0b01000001, "VY shifted right into VX");
+        assert_eq!(cpu.register[0xF], 1, "VF holds the bit of VY shifted out");
+
+        cpu.execute_opcode(0x812E).unwrap();
+        assert_eq!(cpu.register[1], 0b00000110, "VY shifted left into VX");
+        assert_eq!(cpu.register[0xF], 1, "VF holds the bit of VY shifted out");
+    }
+
+    #[test]
+    fn quirk_load_store_increments_i() {
+        let mut cpu = Processor::new();
+        cpu.set_quirks(Quirks { load_store_increments_i: true, ..Quirks::default() });
+        cpu.index_register = 0x300;
+
+        cpu.execute_opcode(0xF255).unwrap();
+        assert_eq!(cpu.index_register, 0x303, "I points past the stored registers");
+
+        cpu.execute_opcode(0xF065).unwrap();
+        assert_eq!(cpu.index_register, 0x304, "I points past the loaded register");
+    }
+
+    #[test]
+    fn quirk_jump_uses_vx() {
+        let mut cpu = Processor::new();
+        cpu.set_quirks(Quirks { jump_uses_vx: true, ..Quirks::default() });
+        cpu.register[0] = 1;
+        cpu.register[2] = 4;
+
+        cpu.execute_opcode(0xB220).unwrap();
+        assert_eq!(cpu.program_counter, 0x224, "the jump is offset by VX");
+    }
+
+    #[test]
+    fn quirk_logic_resets_vf() {
+        let mut cpu = Processor::new();
+        cpu.set_quirks(Quirks { logic_resets_vf: true, ..Quirks::default() });
+
+        for opcode in [0x8121, 0x8122, 0x8123].iter() {
+            cpu.register[0xF] = 1;
+            cpu.execute_opcode(*opcode).unwrap();
+            assert_eq!(cpu.register[0xF], 0, "VF is cleared");
+        }
+    }
+
+    #[test]
+    fn quirk_clip_sprites() {
+        let mut cpu = Processor::new();
+        cpu.index_register = 0x300;
+        cpu.memory[0x300] = 0xFF;
+        cpu.register[0] = 60;
+
+        cpu.execute_opcode(0xD011).unwrap();
+        assert!(cpu.display.get_buffer()[0][0], "the sprite wraps by default");
+
+        cpu.display.clear();
+        cpu.set_quirks(Quirks { clip_sprites: true, ..Quirks::default() });
+        cpu.execute_opcode(0xD011).unwrap();
+        let buffer = cpu.display.get_buffer();
+        assert!(buffer[0][63] && !buffer[0][0], "the sprite is cut off at the edge");
+    }
+
+    #[test]
+    fn write_memory_replaces_decoded_code() {
+        let mut cpu = Processor::new();
+        cpu.set_backend(Backend::Threaded);
+        cpu.reset();
+        cpu.load_program(&[0x60, 0x01, 0x12, 0x00]).unwrap();
+        cpu.run_frame(2).unwrap();
+        assert_eq!(cpu.register[0], 1, "the program ran once");
+
+        cpu.write_memory(0x200, &[0x60, 0x02]);
+        cpu.run_frame(1).unwrap();
+        assert_eq!(cpu.register[0], 2, "the written opcode runs instead of the decoded one");
+    }
+
+    #[test]
+    fn setters_mask_addresses() {
+        let mut cpu = Processor::new();
+        cpu.set_program_counter(0x1234);
+        cpu.set_index_register(0xF300);
+        cpu.set_stack_pointer(200);
+        assert_eq!(cpu.program_counter, 0x234, "the program counter wraps");
+        assert_eq!(cpu.index_register, 0x300, "I wraps");
+        assert_eq!(cpu.stack_pointer, 16, "the stack pointer stays inside the stack");
+
+        cpu.write_memory(0xFFF, &[1, 2]);
+        assert_eq!((cpu.memory[0xFFF], cpu.memory[0]), (1, 2), "writes wrap at the end of memory");
+    }
+}
@@ -0,0 +1,813 @@
+// cranelift backend: a block of opcodes reached often enough is compiled to
+// a native function working on the processor in place. draws, random
+// numbers, keys, stores and anything unknown end a block and run in the
+// interpreter, stores into compiled code drop the blocks they hit
+
+use std::mem::{self, offset_of, ManuallyDrop};
+
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{self, types, AbiParam, InstBuilder, MemFlags, Type, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+
+use super::{read_word, Processor, ADDRESS_MASK};
+use crate::error::Error;
+use crate::quirks::Quirks;
+
+// visits before a block is compiled
+const HOT: u32 = 8;
+
+// longest run of opcodes compiled into one block
+const MAX_BLOCK: usize = 32;
+
+// compiled blocks dropped by stores before the code memory is given back
+const MAX_DEAD: usize = 1024;
+
+// runs a compiled block, over and over while it loops to itself and the
+// instruction budget allows, and returns how many opcodes it executed
+type Function = unsafe extern "C" fn(*mut Processor, u32) -> u32;
+
+// set in the result when the block stopped for the interpreter to raise an
+// error on the next opcode
+const BAILED: u32 = 1 << 31;
+
+enum Entry {
+    // visits so far
+    Cold(u32),
+    // the address of each opcode compiled, in order
+    Compiled { function: Function, addresses: Vec<u16> },
+    // the first opcode can't be compiled
+    Interpreted,
+}
+
+pub(super) struct Jit {
+    module: ManuallyDrop<JITModule>,
+    context: cranelift_codegen::Context,
+    builder_context: FunctionBuilderContext,
+    // quirks the compiled code was specialised for
+    quirks: Quirks,
+    pub(super) threshold: u32,
+    entries: Vec<Entry>,
+    // bytes holding a compiled opcode, bit n % 64 of word n / 64
+    code: [u64; 64],
+    dead: usize,
+}
+
+impl Jit {
+    pub(super) fn new(quirks: Quirks) -> Jit {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").expect("valid cranelift setting");
+        let isa = cranelift_native::builder()
+            .expect("host supported by cranelift")
+            .finish(settings::Flags::new(flags))
+            .expect("host isa");
+        let module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+
+        Jit {
+            context: module.make_context(),
+            module: ManuallyDrop::new(module),
+            builder_context: FunctionBuilderContext::new(),
+            quirks,
+            threshold: HOT,
+            entries: (0..4096).map(|_| Entry::Cold(0)).collect(),
+            code: [0; 64],
+            dead: 0,
+        }
+    }
+
+    // counts a visit to `address`, compiling the block there once it is hot
+    fn visit(&mut self, memory: &[u8; 4096], address: u16) {
+        let index = address as usize;
+        match &self.entries[index] {
+            Entry::Cold(visits) if *visits >= self.threshold => {
+                self.entries[index] = self.compile(memory, address).unwrap_or(Entry::Interpreted);
+            },
+            Entry::Cold(visits) => self.entries[index] = Entry::Cold(*visits + 1),
+            _ => (),
+        }
+    }
+
+    // drops the blocks compiled from the `length` bytes written at `address`
+    fn invalidate(&mut self, address: u16, length: u16) {
+        let written = (0..length).any(|offset| {
+            let address = ((address + offset) & ADDRESS_MASK) as usize;
+            (self.code[address / 64] >> (address % 64)) & 1 == 1
+        });
+        if !written {
+            return;
+        }
+
+        self.code = [0; 64];
+        let mut entries = mem::take(&mut self.entries);
+        for entry in entries.iter_mut() {
+            if let Entry::Compiled { addresses, .. } = entry {
+                let overlaps = addresses.iter().any(|opcode| {
+                    (0..2).any(|byte| ((opcode + byte).wrapping_sub(address) & ADDRESS_MASK) < length)
+                });
+                if overlaps {
+                    *entry = Entry::Cold(0);
+                    self.dead += 1;
+                } else {
+                    self.mark(addresses);
+                }
+            }
+        }
+        self.entries = entries;
+    }
+
+    fn mark(&mut self, addresses: &[u16]) {
+        for opcode in addresses {
+            for byte in 0..2 {
+                let address = ((opcode + byte) & ADDRESS_MASK) as usize;
+                self.code[address / 64] |= 1 << (address % 64);
+            }
+        }
+    }
+
+    fn compile(&mut self, memory: &[u8; 4096], start: u16) -> Option<Entry> {
+        let block = Block::decode(memory, start, self.quirks)?;
+
+        let pointer = self.module.target_config().pointer_type();
+        self.context.func.signature.params.push(AbiParam::new(pointer));
+        self.context.func.signature.params.push(AbiParam::new(types::I32));
+        self.context.func.signature.returns.push(AbiParam::new(types::I32));
+
+        let mut builder = FunctionBuilder::new(&mut self.context.func, &mut self.builder_context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let (cpu, budget) = (builder.block_params(entry)[0], builder.block_params(entry)[1]);
+        let header = builder.create_block();
+
+        let mut emitter = Emitter {
+            builder,
+            cpu,
+            budget,
+            header,
+            pointer,
+            quirks: self.quirks,
+            touched: block.touched,
+            start,
+            length: block.opcodes.len(),
+        };
+        emitter.load_state();
+        for (index, &(address, opcode)) in block.opcodes.iter().enumerate() {
+            emitter.emit(index, address, opcode);
+        }
+        if !block.terminated {
+            emitter.jump_to(block.end, block.opcodes.len());
+        }
+        emitter.builder.seal_all_blocks();
+        emitter.builder.finalize();
+
+        let id = self.module.declare_anonymous_function(&self.context.func.signature).ok();
+        let defined = id.filter(|id| self.module.define_function(*id, &mut self.context).is_ok());
+        self.module.clear_context(&mut self.context);
+        let id = defined?;
+        self.module.finalize_definitions().ok()?;
+
+        // safety: the function was compiled with the `Function` signature
+        let function = unsafe { mem::transmute::<*const u8, Function>(self.module.get_finalized_function(id)) };
+        let addresses: Vec<u16> = block.opcodes.iter().map(|(address, _)| *address).collect();
+        self.mark(&addresses);
+        Some(Entry::Compiled { function, addresses })
+    }
+}
+
+impl Drop for Jit {
+    fn drop(&mut self) {
+        // safety: the compiled functions are only reachable through `entries`
+        // which goes away with the module
+        unsafe { ManuallyDrop::take(&mut self.module).free_memory() };
+    }
+}
+
+// opcodes the compiled code runs itself
+fn compilable(opcode: u16) -> bool {
+    match ((opcode & 0xF000) >> 12, opcode & 0x000F, opcode & 0x00FF) {
+        (0x0, _, _) => opcode == 0x00EE,
+        (0x1..=0x4, _, _) | (0x6, _, _) | (0x7, _, _) | (0xA, _, _) | (0xB, _, _) => true,
+        (0x5, 0x0, _) | (0x9, 0x0, _) => true,
+        (0x8, 0x0..=0x7, _) | (0x8, 0xE, _) => true,
+        (0xF, _, 0x07) | (0xF, _, 0x15) | (0xF, _, 0x18) | (0xF, _, 0x1E) | (0xF, _, 0x29) | (0xF, _, 0x65) => true,
+        _ => false,
+    }
+}
+
+// opcodes after which the program counter isn't known when compiling
+fn terminates(opcode: u16) -> bool {
+    opcode == 0x00EE || matches!((opcode & 0xF000) >> 12, 0x2 | 0x3 | 0x4 | 0x5 | 0x9 | 0xB)
+}
+
+// bytes written from I by FX33 and FX55
+fn stored_bytes(opcode: u16) -> u16 {
+    match (opcode & 0xF000, opcode & 0x00FF) {
+        (0xF000, 0x33) => 3,
+        (0xF000, 0x55) => ((opcode & 0x0F00) >> 8) + 1,
+        _ => 0,
+    }
+}
+
+struct Block {
+    // address and opcode of each compiled opcode
+    opcodes: Vec<(u16, u16)>,
+    // address the last opcode continues at
+    end: u16,
+    // whether the last opcode sets the program counter itself
+    terminated: bool,
+    // registers used, bit n for VN, bit 16 for I
+    touched: u32,
+}
+
+impl Block {
+    fn decode(memory: &[u8; 4096], start: u16, quirks: Quirks) -> Option<Block> {
+        let mut block = Block { opcodes: Vec::new(), end: start, terminated: false, touched: 0 };
+        let mut address = start;
+        while block.opcodes.len() < MAX_BLOCK {
+            let opcode = read_word(memory, address);
+            if !compilable(opcode) {
+                break;
+            }
+            block.opcodes.push((address, opcode));
+            block.touched |= touched(opcode, quirks);
+            if terminates(opcode) {
+                block.terminated = true;
+                break;
+            }
+
+            // jumps are followed so a loop runs as one block
+            let next = (address + 2) & ADDRESS_MASK;
+            block.end = if opcode & 0xF000 == 0x1000 { opcode & 0x0FFF } else { next };
+            if next < address && opcode & 0xF000 != 0x1000 {
+                break;
+            }
+            address = block.end;
+        }
+
+        if block.opcodes.is_empty() {
+            None
+        } else {
+            Some(block)
+        }
+    }
+}
+
+// registers an opcode reads or writes, bit n for VN, bit 16 for I
+fn touched(opcode: u16, quirks: Quirks) -> u32 {
+    let x = 1 << ((opcode & 0x0F00) >> 8);
+    let y = 1 << ((opcode & 0x00F0) >> 4);
+    let vf = 1 << 0xF;
+    let index = 1 << 16;
+    match ((opcode & 0xF000) >> 12, opcode & 0x00FF) {
+        (0x3, _) | (0x4, _) | (0x6, _) | (0x7, _) => x,
+        (0x5, _) | (0x9, _) => x | y,
+        (0x8, _) => x | y | vf,
+        (0xA, _) => index,
+        (0xB, _) => if quirks.jump_uses_vx { x } else { 1 },
+        (0xF, 0x1E) | (0xF, 0x29) => x | index,
+        (0xF, 0x65) => ((x << 1) - 1) | index,
+        (0xF, _) => x,
+        _ => 0,
+    }
+}
+
+struct Emitter<'a> {
+    builder: FunctionBuilder<'a>,
+    cpu: Value,
+    // opcodes the block may run before it has to return
+    budget: Value,
+    // where each run through the block starts
+    header: ir::Block,
+    pointer: Type,
+    quirks: Quirks,
+    touched: u32,
+    start: u16,
+    length: usize,
+}
+
+// I and the opcodes executed by earlier runs through the block live in the
+// variables after the sixteen registers
+const INDEX: usize = 16;
+const EXECUTED: usize = 17;
+
+impl Emitter<'_> {
+    fn load_state(&mut self) {
+        for register in 0..16 {
+            if self.touched & (1 << register) != 0 {
+                self.builder.declare_var(Variable::from_u32(register as u32), types::I8);
+                let value = self.load(types::I8, offset_of!(Processor, register) + register);
+                self.builder.def_var(Variable::from_u32(register as u32), value);
+            }
+        }
+        if self.touched & (1 << INDEX) != 0 {
+            self.builder.declare_var(Variable::from_u32(INDEX as u32), types::I16);
+            let value = self.load(types::I16, offset_of!(Processor, index_register));
+            self.builder.def_var(Variable::from_u32(INDEX as u32), value);
+        }
+
+        self.builder.declare_var(Variable::from_u32(EXECUTED as u32), types::I32);
+        let zero = self.builder.ins().iconst(types::I32, 0);
+        self.builder.def_var(Variable::from_u32(EXECUTED as u32), zero);
+        self.builder.ins().jump(self.header, &[]);
+        self.builder.switch_to_block(self.header);
+    }
+
+    // continues at `target` after `executed` opcodes of this run, running
+    // the block again when it loops to its own start and the budget allows
+    fn jump_to(&mut self, target: u16, executed: usize) {
+        if target != self.start || executed != self.length {
+            let program_counter = self.constant(types::I16, target);
+            self.exit(program_counter, executed);
+            return;
+        }
+
+        let previous = self.builder.use_var(Variable::from_u32(EXECUTED as u32));
+        let total = self.builder.ins().iadd_imm(previous, self.length as i64);
+        let needed = self.builder.ins().iadd_imm(total, self.length as i64);
+        let fits = self.builder.ins().icmp(IntCC::UnsignedLessThanOrEqual, needed, self.budget);
+        let again = self.builder.create_block();
+        let leave = self.builder.create_block();
+        self.builder.ins().brif(fits, again, &[], leave, &[]);
+
+        self.builder.switch_to_block(again);
+        self.builder.def_var(Variable::from_u32(EXECUTED as u32), total);
+        self.builder.ins().jump(self.header, &[]);
+
+        self.builder.switch_to_block(leave);
+        let program_counter = self.constant(types::I16, target);
+        self.finish(program_counter, total);
+    }
+
+    // returns at `program_counter` after `executed` opcodes of this run
+    fn exit(&mut self, program_counter: Value, executed: usize) {
+        let previous = self.builder.use_var(Variable::from_u32(EXECUTED as u32));
+        let total = self.builder.ins().iadd_imm(previous, executed as i64);
+        self.finish(program_counter, total);
+    }
+
+    // writes the registers back and returns
+    fn finish(&mut self, program_counter: Value, executed: Value) {
+        for register in 0..16 {
+            if self.touched & (1 << register) != 0 {
+                let value = self.builder.use_var(Variable::from_u32(register as u32));
+                self.store(value, offset_of!(Processor, register) + register);
+            }
+        }
+        if self.touched & (1 << INDEX) != 0 {
+            let value = self.builder.use_var(Variable::from_u32(INDEX as u32));
+            self.store(value, offset_of!(Processor, index_register));
+        }
+        self.store(program_counter, offset_of!(Processor, program_counter));
+        self.builder.ins().return_(&[executed]);
+    }
+
+    fn emit(&mut self, index: usize, address: u16, opcode: u16) {
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        let nn = opcode & 0x00FF;
+        let nnn = opcode & 0x0FFF;
+        let next = (address + 2) & ADDRESS_MASK;
+        let skip = (address + 4) & ADDRESS_MASK;
+
+        match ((opcode & 0xF000) >> 12, opcode & 0x000F) {
+            // Return from subroutine
+            (0x0, _) => {
+                let stack_pointer = self.load(types::I8, offset_of!(Processor, stack_pointer));
+                let empty = self.builder.ins().icmp_imm(IntCC::Equal, stack_pointer, 0);
+                self.bail_if(empty, address, index);
+                let stack_pointer = self.builder.ins().iadd_imm(stack_pointer, -1);
+                self.store(stack_pointer, offset_of!(Processor, stack_pointer));
+                let slot = self.stack_slot(stack_pointer);
+                let address = self.builder.ins().load(types::I16, MemFlags::trusted(), slot, offset_of!(Processor, stack) as i32);
+                let program_counter = self.builder.ins().band_imm(address, ADDRESS_MASK as i64);
+                self.exit(program_counter, index + 1);
+            },
+
+            // Jump to Address NNN, the block carries on at NNN
+            (0x1, _) => (),
+
+            // Call subroutine
+            (0x2, _) => {
+                let stack_pointer = self.load(types::I8, offset_of!(Processor, stack_pointer));
+                let full = self.builder.ins().icmp_imm(IntCC::Equal, stack_pointer, 16);
+                self.bail_if(full, address, index);
+                let slot = self.stack_slot(stack_pointer);
+                let return_address = self.constant(types::I16, next);
+                self.builder.ins().store(MemFlags::trusted(), return_address, slot, offset_of!(Processor, stack) as i32);
+                let stack_pointer = self.builder.ins().iadd_imm(stack_pointer, 1);
+                self.store(stack_pointer, offset_of!(Processor, stack_pointer));
+                self.jump_to(nnn, index + 1);
+            },
+
+            // Skips
+            (0x3, _) | (0x4, _) | (0x5, _) | (0x9, _) => {
+                let vx = self.register(x);
+                let other = match (opcode & 0xF000) >> 12 {
+                    0x3 | 0x4 => self.constant(types::I8, nn),
+                    _ => self.register(y),
+                };
+                let condition = match (opcode & 0xF000) >> 12 {
+                    0x3 | 0x5 => IntCC::Equal,
+                    _ => IntCC::NotEqual,
+                };
+                let skips = self.builder.ins().icmp(condition, vx, other);
+                if skip == self.start || next == self.start {
+                    let skipped = self.builder.create_block();
+                    let not_skipped = self.builder.create_block();
+                    self.builder.ins().brif(skips, skipped, &[], not_skipped, &[]);
+                    self.builder.switch_to_block(skipped);
+                    self.jump_to(skip, index + 1);
+                    self.builder.switch_to_block(not_skipped);
+                    self.jump_to(next, index + 1);
+                } else {
+                    let skip = self.constant(types::I16, skip);
+                    let next = self.constant(types::I16, next);
+                    let program_counter = self.builder.ins().select(skips, skip, next);
+                    self.exit(program_counter, index + 1);
+                }
+            },
+
+            // Sets VX to NN
+            (0x6, _) => {
+                let value = self.constant(types::I8, nn);
+                self.set_register(x, value);
+            },
+
+            // Adds NN to VX
+            (0x7, _) => {
+                let vx = self.register(x);
+                let value = self.builder.ins().iadd_imm(vx, nn as i64);
+                self.set_register(x, value);
+            },
+
+            // Arithmetic and logic on VX and VY, VF is written before VX
+            // except for the quirk clearing it after the logic opcodes
+            (0x8, 0x1..=0x3) => {
+                let vx = self.register(x);
+                let vy = self.register(y);
+                let value = match opcode & 0x000F {
+                    0x1 => self.builder.ins().bor(vx, vy),
+                    0x2 => self.builder.ins().band(vx, vy),
+                    _ => self.builder.ins().bxor(vx, vy),
+                };
+                self.set_register(x, value);
+                if self.quirks.logic_resets_vf {
+                    let zero = self.constant(types::I8, 0);
+                    self.set_register(0xF, zero);
+                }
+            },
+            (0x8, operation) => {
+                let vx = self.register(x);
+                let vy = self.register(y);
+                let (value, flag) = match operation {
+                    0x0 => (vy, None),
+                    0x4 => {
+                        let sum = self.builder.ins().iadd(vx, vy);
+                        (sum, Some(self.builder.ins().icmp(IntCC::UnsignedLessThan, sum, vx)))
+                    },
+                    0x5 => (self.builder.ins().isub(vx, vy), Some(self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, vx, vy))),
+                    0x7 => (self.builder.ins().isub(vy, vx), Some(self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, vy, vx))),
+                    0x6 => {
+                        let source = if self.quirks.shift_uses_vy { vy } else { vx };
+                        (self.builder.ins().ushr_imm(source, 1), Some(self.builder.ins().band_imm(source, 1)))
+                    },
+                    _ => {
+                        let source = if self.quirks.shift_uses_vy { vy } else { vx };
+                        (self.builder.ins().ishl_imm(source, 1), Some(self.builder.ins().ushr_imm(source, 7)))
+                    },
+                };
+                if let Some(flag) = flag {
+                    self.set_register(0xF, flag);
+                }
+                self.set_register(x, value);
+            },
+
+            // Sets I to the address NNN
+            (0xA, _) => {
+                let value = self.constant(types::I16, nnn);
+                self.builder.def_var(Variable::from_u32(INDEX as u32), value);
+            },
+
+            // Jumps to the address NNN plus V0 (or VX)
+            (0xB, _) => {
+                let offset = self.register(if self.quirks.jump_uses_vx { x } else { 0 });
+                let offset = self.builder.ins().uextend(types::I16, offset);
+                let target = self.builder.ins().iadd_imm(offset, nnn as i64);
+                let program_counter = self.builder.ins().band_imm(target, ADDRESS_MASK as i64);
+                self.exit(program_counter, index + 1);
+            },
+
+            // Timers, I, the font and loads from memory
+            _ => match nn {
+                0x07 => {
+                    let value = self.load(types::I8, offset_of!(Processor, delay_timer));
+                    self.set_register(x, value);
+                },
+                0x15 => {
+                    let vx = self.register(x);
+                    self.store(vx, offset_of!(Processor, delay_timer));
+                },
+                0x18 => {
+                    let vx = self.register(x);
+                    self.store(vx, offset_of!(Processor, sound_timer));
+                },
+                0x1E => {
+                    let vx = self.register(x);
+                    let vx = self.builder.ins().uextend(types::I16, vx);
+                    let index_register = self.builder.use_var(Variable::from_u32(INDEX as u32));
+                    let sum = self.builder.ins().iadd(index_register, vx);
+                    let value = self.builder.ins().band_imm(sum, ADDRESS_MASK as i64);
+                    self.builder.def_var(Variable::from_u32(INDEX as u32), value);
+                },
+                0x29 => {
+                    let vx = self.register(x);
+                    let vx = self.builder.ins().uextend(types::I16, vx);
+                    let value = self.builder.ins().imul_imm(vx, 5);
+                    self.builder.def_var(Variable::from_u32(INDEX as u32), value);
+                },
+                _ => {
+                    let index_register = self.builder.use_var(Variable::from_u32(INDEX as u32));
+                    for register in 0..=x {
+                        let address = self.builder.ins().iadd_imm(index_register, register as i64);
+                        let address = self.builder.ins().band_imm(address, ADDRESS_MASK as i64);
+                        let address = self.builder.ins().uextend(self.pointer, address);
+                        let address = self.builder.ins().iadd(self.cpu, address);
+                        let value = self.builder.ins().load(types::I8, MemFlags::trusted(), address, offset_of!(Processor, memory) as i32);
+                        self.set_register(register, value);
+                    }
+                    if self.quirks.load_store_increments_i {
+                        let value = self.builder.ins().iadd_imm(index_register, x as i64 + 1);
+                        let value = self.builder.ins().band_imm(value, ADDRESS_MASK as i64);
+                        self.builder.def_var(Variable::from_u32(INDEX as u32), value);
+                    }
+                },
+            },
+        }
+    }
+
+    // leaves the block before the opcode at `address` when `condition` holds
+    // so the interpreter runs it and raises the error
+    fn bail_if(&mut self, condition: Value, address: u16, executed: usize) {
+        let bail = self.builder.create_block();
+        let continue_block = self.builder.create_block();
+        self.builder.ins().brif(condition, bail, &[], continue_block, &[]);
+
+        self.builder.switch_to_block(bail);
+        let program_counter = self.constant(types::I16, address);
+        let previous = self.builder.use_var(Variable::from_u32(EXECUTED as u32));
+        let total = self.builder.ins().iadd_imm(previous, executed as i64);
+        let total = self.builder.ins().bor_imm(total, BAILED as i64);
+        self.finish(program_counter, total);
+
+        self.builder.switch_to_block(continue_block);
+    }
+
+    fn stack_slot(&mut self, stack_pointer: Value) -> Value {
+        let stack_pointer = self.builder.ins().uextend(self.pointer, stack_pointer);
+        let offset = self.builder.ins().ishl_imm(stack_pointer, 1);
+        self.builder.ins().iadd(self.cpu, offset)
+    }
+
+    fn register(&mut self, register: usize) -> Value {
+        self.builder.use_var(Variable::from_u32(register as u32))
+    }
+
+    fn set_register(&mut self, register: usize, value: Value) {
+        self.builder.def_var(Variable::from_u32(register as u32), value);
+    }
+
+    fn constant(&mut self, ty: Type, value: u16) -> Value {
+        self.builder.ins().iconst(ty, value as i64)
+    }
+
+    fn load(&mut self, ty: Type, offset: usize) -> Value {
+        self.builder.ins().load(ty, MemFlags::trusted(), self.cpu, offset as i32)
+    }
+
+    fn store(&mut self, value: Value, offset: usize) {
+        self.builder.ins().store(MemFlags::trusted(), value, self.cpu, offset as i32);
+    }
+}
+
+impl Processor {
+    // runs `instructions` opcodes, compiled blocks where there are any
+    pub(super) fn run_jit(&mut self, instructions: usize) -> Result<(), Error> {
+        // compiled code is specialised for the quirks
+        let mut jit = match self.jit.take() {
+            Some(jit) if jit.quirks == self.quirks && jit.dead < MAX_DEAD => jit,
+            _ => Box::new(Jit::new(self.quirks)),
+        };
+        let result = self.run_compiled(&mut jit, instructions);
+        self.jit = Some(jit);
+        result
+    }
+
+    fn run_compiled(&mut self, jit: &mut Jit, mut instructions: usize) -> Result<(), Error> {
+        while instructions > 0 {
+            let address = self.program_counter;
+            jit.visit(&self.memory, address);
+            if let Entry::Compiled { function, addresses } = &jit.entries[address as usize] {
+                let (function, length) = (*function, addresses.len());
+                if length <= instructions {
+                    let budget = instructions.min((BAILED - 1) as usize) as u32;
+                    // safety: the block was compiled for this processor layout
+                    // and only touches fields inside it
+                    let result = unsafe { function(self, budget) };
+                    instructions -= (result & !BAILED) as usize;
+                    if result & BAILED == 0 {
+                        continue;
+                    }
+                }
+            }
+
+            let opcode = read_word(&self.memory, self.program_counter);
+            let index_register = self.index_register;
+            self.interpret()?;
+            instructions -= 1;
+
+            let stored = stored_bytes(opcode);
+            if stored > 0 {
+                jit.invalidate(index_register, stored);
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{Backend, PROGRAM_START};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // switches to the jit compiling blocks after `threshold` visits
+    fn attach_jit(cpu: &mut Processor, threshold: u32) {
+        cpu.set_backend(Backend::Jit);
+        let mut jit = Jit::new(cpu.quirks);
+        jit.threshold = threshold;
+        cpu.jit = Some(Box::new(jit));
+    }
+
+    fn assert_same(interpreter: &Processor, jit: &Processor, context: &str) {
+        assert_eq!(interpreter.register, jit.register, "registers after {}", context);
+        assert_eq!(interpreter.program_counter, jit.program_counter, "program counter after {}", context);
+        assert_eq!(interpreter.index_register, jit.index_register, "I after {}", context);
+        assert_eq!(interpreter.call_stack(), jit.call_stack(), "stack after {}", context);
+        assert_eq!(interpreter.delay_timer, jit.delay_timer, "delay timer after {}", context);
+        assert_eq!(interpreter.sound_timer, jit.sound_timer, "sound timer after {}", context);
+        assert!(interpreter.memory[..] == jit.memory[..], "memory after {}", context);
+        assert_eq!(interpreter.display.rows(), jit.display.rows(), "display after {}", context);
+    }
+
+    // runs both processors a frame at a time until `frames` or an error
+    fn run_lockstep(interpreter: &mut Processor, jit: &mut Processor, frames: usize) {
+        for frame in 0..frames {
+            // frames of odd sizes stop short of compiled blocks
+            let expected = interpreter.run_frame(frame % 23);
+            let result = jit.run_frame(frame % 23);
+            assert_eq!(format!("{:?}", expected), format!("{:?}", result), "result of frame {}", frame);
+            assert_same(interpreter, jit, &format!("frame {}", frame));
+            if expected.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn load_both(program: &[u8], threshold: u32) -> (Processor, Processor) {
+        let mut interpreter = Processor::new();
+        let mut jit = Processor::new();
+        for cpu in [&mut interpreter, &mut jit].iter_mut() {
+            cpu.reset();
+            cpu.load_program(program).unwrap();
+        }
+        attach_jit(&mut jit, threshold);
+        (interpreter, jit)
+    }
+
+    #[test]
+    fn compilable_opcodes_match_interpreter() {
+        let mut rng = StdRng::seed_from_u64(36);
+        let mut tested = 0;
+        while tested < 2000 {
+            let opcode = rng.gen::<u16>();
+            if !compilable(opcode) {
+                continue;
+            }
+            tested += 1;
+
+            let mut interpreter = Processor::new();
+            rng.fill(&mut interpreter.memory[..]);
+            rng.fill(&mut interpreter.register[..]);
+            interpreter.program_counter = rng.gen::<u16>() & ADDRESS_MASK & !1;
+            interpreter.index_register = rng.gen::<u16>() & ADDRESS_MASK;
+            interpreter.stack_pointer = rng.gen_range(0, 17);
+            for address in interpreter.stack.iter_mut() {
+                *address = rng.gen::<u16>() & ADDRESS_MASK;
+            }
+            interpreter.delay_timer = rng.gen();
+            interpreter.sound_timer = rng.gen();
+            interpreter.quirks = Quirks {
+                shift_uses_vy: rng.gen(),
+                load_store_increments_i: rng.gen(),
+                jump_uses_vx: rng.gen(),
+                logic_resets_vf: rng.gen(),
+                clip_sprites: rng.gen(),
+            };
+            // the opcode followed by one the jit leaves alone, so the
+            // compiled block is just the opcode
+            let pc = interpreter.program_counter as usize;
+            interpreter.memory[pc..pc + 2].copy_from_slice(&opcode.to_be_bytes());
+            interpreter.memory[(pc + 2) & 0xFFF] = 0x00;
+            interpreter.memory[(pc + 3) & 0xFFF] = 0x00;
+
+            let mut jit = Processor::new();
+            jit.memory = interpreter.memory;
+            jit.register = interpreter.register;
+            jit.program_counter = interpreter.program_counter;
+            jit.index_register = interpreter.index_register;
+            jit.stack_pointer = interpreter.stack_pointer;
+            jit.stack = interpreter.stack;
+            jit.delay_timer = interpreter.delay_timer;
+            jit.sound_timer = interpreter.sound_timer;
+            jit.quirks = interpreter.quirks;
+            attach_jit(&mut jit, 0);
+
+            let expected = interpreter.step();
+            let result = jit.step();
+            assert_eq!(format!("{:?}", expected), format!("{:?}", result), "result of {:04X}", opcode);
+            assert_same(&interpreter, &jit, &format!("{:04X}", opcode));
+        }
+    }
+
+    #[test]
+    fn roms_match_interpreter() {
+        // pong and c8_test are left out as they draw random numbers
+        let (mut interpreter, mut jit) = load_both(include_bytes!("../../roms/test_opcode.ch8"), HOT);
+        run_lockstep(&mut interpreter, &mut jit, 3000);
+        assert!(jit.jit.as_ref().unwrap().entries.iter().any(|entry| matches!(entry, Entry::Compiled { .. })), "blocks were compiled");
+    }
+
+    #[test]
+    fn random_programs_match_interpreter() {
+        let mut rng = StdRng::seed_from_u64(36);
+        for _ in 0..100 {
+            let mut program = [0; 256];
+            rng.fill(&mut program[..]);
+            // random numbers can't be compared
+            for opcode in program.chunks_mut(2) {
+                if opcode[0] & 0xF0 == 0xC0 {
+                    opcode[0] = 0x60 | (opcode[0] & 0x0F);
+                }
+            }
+            let (mut interpreter, mut jit) = load_both(&program, 0);
+            run_lockstep(&mut interpreter, &mut jit, 300);
+        }
+    }
+
+    #[test]
+    fn stores_into_code_are_seen() {
+        let program = [
+            0x62, 0x01, // 200: LD V2, 0x01, rewritten to LD V2, 0x02
+            0x72, 0x00, // 202: ADD V2, 0x00
+            0x33, 0x00, // 204: SE V3, 0x00
+            0x12, 0x06, // 206: JP 0x206
+            0x63, 0x01, // 208: LD V3, 0x01
+            0xA2, 0x00, // 20A: LD I, 0x200
+            0x60, 0x62, // 20C: LD V0, 0x62
+            0x61, 0x02, // 20E: LD V1, 0x02
+            0xF1, 0x55, // 210: LD [I], V1
+            0x12, 0x00, // 212: JP 0x200
+        ];
+        let (mut interpreter, mut jit) = load_both(&program, 0);
+        run_lockstep(&mut interpreter, &mut jit, 10);
+        assert_eq!(jit.register[2], 0x02, "the rewritten opcode runs instead of the compiled one");
+        assert_eq!(jit.program_counter as usize, PROGRAM_START + 6);
+    }
+
+    #[test]
+    fn stack_errors_leave_compiled_code() {
+        let program = [0x60, 0x01, 0x00, 0xEE];
+        let (mut interpreter, mut jit) = load_both(&program, 0);
+        let expected = interpreter.run_frame(2);
+        let result = jit.run_frame(2);
+        assert!(matches!(result, Err(Error::StackUnderflow { pc: 0x202 })), "the interpreter raises the error");
+        assert_eq!(format!("{:?}", expected), format!("{:?}", result));
+        assert_same(&interpreter, &jit, "the error");
+    }
+
+    #[test]
+    fn returns_stay_in_memory() {
+        let program = [0x60, 0x01, 0x00, 0xEE];
+        let (mut interpreter, mut jit) = load_both(&program, 0);
+        for cpu in [&mut interpreter, &mut jit].iter_mut() {
+            cpu.stack[0] = 0xFFFF;
+            cpu.stack_pointer = 1;
+        }
+        run_lockstep(&mut interpreter, &mut jit, 3);
+        assert_eq!(jit.program_counter, 0xFFF, "the return address is masked like the interpreter's");
+    }
+}
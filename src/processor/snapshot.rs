@@ -0,0 +1,251 @@
+// a copy of everything a running rom can observe, decoded and compiled code
+// is not part of it. snapshots convert to and from a fixed size byte layout
+// for save states:
+//
+//   magic "C8ST", version
+//   memory, V0-VF, stack as little endian words
+//   pc, I as little endian words, stack pointer, sound timer, delay timer
+//   quirks as bit flags, random number state as a little endian word
+//   display rows as little endian words, held keys as a little endian word
+
+use super::{Processor, Random, ADDRESS_MASK};
+use crate::display::{Row, HEIGHT};
+use crate::error::Error;
+use crate::quirks::Quirks;
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 1;
+
+pub const SNAPSHOT_SIZE: usize = 4 + 1 + 4096 + 16 + 16 * 2 + 2 + 2 + 3 + 1 + 8 + HEIGHT * 8 + 2;
+
+#[derive(Clone)]
+pub struct Snapshot {
+    memory: [u8; 4096],
+    register: [u8; 16],
+    stack: [u16; 16],
+    program_counter: u16,
+    index_register: u16,
+    stack_pointer: u8,
+    sound_timer: u8,
+    delay_timer: u8,
+    quirks: Quirks,
+    rng: u64,
+    rows: [Row; HEIGHT],
+    keys: u16,
+}
+
+impl Processor {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.memory,
+            register: self.register,
+            stack: self.stack,
+            program_counter: self.program_counter,
+            index_register: self.index_register,
+            stack_pointer: self.stack_pointer,
+            sound_timer: self.sound_timer,
+            delay_timer: self.delay_timer,
+            quirks: self.quirks,
+            rng: self.rng.0,
+            rows: *self.display.rows(),
+            keys: self.keyboard.state(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        // code decoded from the same memory is still good
+        if self.memory != snapshot.memory {
+            self.memory = snapshot.memory;
+            self.forget_code();
+        }
+        self.register = snapshot.register;
+        self.stack = snapshot.stack;
+        self.program_counter = snapshot.program_counter;
+        self.index_register = snapshot.index_register;
+        self.stack_pointer = snapshot.stack_pointer;
+        self.sound_timer = snapshot.sound_timer;
+        self.delay_timer = snapshot.delay_timer;
+        self.quirks = snapshot.quirks;
+        self.rng = Random(snapshot.rng);
+        self.display.set_rows(&snapshot.rows);
+        self.keyboard.set_state(snapshot.keys);
+    }
+}
+
+const QUIRK_FLAGS: usize = 5;
+
+fn quirk_flags(quirks: &Quirks) -> [bool; QUIRK_FLAGS] {
+    [quirks.shift_uses_vy, quirks.load_store_increments_i, quirks.jump_uses_vx, quirks.logic_resets_vf, quirks.clip_sprites]
+}
+
+impl Snapshot {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SNAPSHOT_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.memory);
+        bytes.extend_from_slice(&self.register);
+        for address in self.stack.iter() {
+            bytes.extend_from_slice(&address.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.program_counter.to_le_bytes());
+        bytes.extend_from_slice(&self.index_register.to_le_bytes());
+        bytes.extend_from_slice(&[self.stack_pointer, self.sound_timer, self.delay_timer]);
+        let flags = quirk_flags(&self.quirks).iter().enumerate().fold(0, |flags, (bit, on)| flags | (*on as u8) << bit);
+        bytes.push(flags);
+        bytes.extend_from_slice(&self.rng.to_le_bytes());
+        for row in self.rows.iter() {
+            bytes.extend_from_slice(&row.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.keys.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, Error> {
+        let error = |message: &str| Error::State { message: message.to_string() };
+        if bytes.len() != SNAPSHOT_SIZE || !bytes.starts_with(MAGIC) {
+            return Err(error("not a save state"));
+        }
+        if bytes[4] != VERSION {
+            return Err(error("saved by another version"));
+        }
+
+        let mut reader = Reader { bytes: &bytes[5..] };
+        let mut snapshot = Snapshot {
+            memory: [0; 4096],
+            register: [0; 16],
+            stack: [0; 16],
+            program_counter: 0,
+            index_register: 0,
+            stack_pointer: 0,
+            sound_timer: 0,
+            delay_timer: 0,
+            quirks: Quirks::default(),
+            rng: 0,
+            rows: [0; HEIGHT],
+            keys: 0,
+        };
+        snapshot.memory.copy_from_slice(reader.take(4096));
+        snapshot.register.copy_from_slice(reader.take(16));
+        for address in snapshot.stack.iter_mut() {
+            *address = u16::from_le_bytes(reader.array());
+        }
+        snapshot.program_counter = u16::from_le_bytes(reader.array());
+        snapshot.index_register = u16::from_le_bytes(reader.array());
+        let [stack_pointer, sound_timer, delay_timer] = reader.array();
+        snapshot.stack_pointer = stack_pointer;
+        snapshot.sound_timer = sound_timer;
+        snapshot.delay_timer = delay_timer;
+        let [flags] = reader.array();
+        let flag = |bit: usize| flags >> bit & 1 == 1;
+        snapshot.quirks = Quirks {
+            shift_uses_vy: flag(0),
+            load_store_increments_i: flag(1),
+            jump_uses_vx: flag(2),
+            logic_resets_vf: flag(3),
+            clip_sprites: flag(4),
+        };
+        snapshot.rng = u64::from_le_bytes(reader.array());
+        for row in snapshot.rows.iter_mut() {
+            *row = Row::from_le_bytes(reader.array());
+        }
+        snapshot.keys = u16::from_le_bytes(reader.array());
+
+        // a processor never gets into these states itself
+        if snapshot.stack_pointer as usize > snapshot.stack.len() {
+            return Err(error("stack pointer is past the stack"));
+        }
+        if snapshot.program_counter > ADDRESS_MASK
+            || snapshot.index_register > ADDRESS_MASK
+            || snapshot.stack.iter().any(|address| *address > ADDRESS_MASK)
+        {
+            return Err(error("address is not in memory"));
+        }
+        if flags >> QUIRK_FLAGS != 0 {
+            return Err(error("unknown quirks"));
+        }
+        Ok(snapshot)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> &'a [u8] {
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        taken
+    }
+
+    fn array<const N: usize>(&mut self) -> [u8; N] {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N));
+        array
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running() -> Processor {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.set_quirks(Quirks::chip8());
+        cpu.set_seed(7);
+        cpu.load_program(include_bytes!("../../roms/pong")).unwrap();
+        cpu.keyboard.key_press(0x4);
+        for _ in 0..40 {
+            cpu.run_frame(10).unwrap();
+        }
+        cpu
+    }
+
+    #[test]
+    fn snapshots_round_trip_through_bytes() {
+        let mut cpu = running();
+        let bytes = cpu.snapshot().to_bytes();
+        assert_eq!(bytes.len(), SNAPSHOT_SIZE, "the layout has a fixed size");
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+        assert!(snapshot.to_bytes() == bytes, "nothing is lost on the way");
+
+        let mut later = running();
+        for _ in 0..40 {
+            later.run_frame(10).unwrap();
+        }
+        for _ in 0..40 {
+            cpu.run_frame(10).unwrap();
+        }
+        let mut restored = Processor::new();
+        restored.restore(&snapshot);
+        for _ in 0..40 {
+            restored.run_frame(10).unwrap();
+        }
+        assert_eq!(restored.display.rows(), later.display.rows(), "a restored processor carries on the same");
+        assert_eq!(restored.registers(), later.registers(), "random numbers carry on the same");
+        assert!(restored.keyboard.pressed(0x4), "held keys are restored");
+    }
+
+    #[test]
+    fn bad_states_are_refused() {
+        let bytes = running().snapshot().to_bytes();
+        assert!(matches!(Snapshot::from_bytes(&bytes[1..]), Err(Error::State { .. })), "short states are refused");
+
+        let mut version = bytes.clone();
+        version[4] = VERSION + 1;
+        assert!(matches!(Snapshot::from_bytes(&version), Err(Error::State { .. })), "other versions are refused");
+
+        // the stack pointer follows memory, registers, the stack and two addresses
+        let mut stack = bytes.clone();
+        stack[5 + 4096 + 16 + 32 + 4] = 17;
+        assert!(matches!(Snapshot::from_bytes(&stack), Err(Error::State { .. })), "out of range values are refused");
+
+        // the stack follows memory and registers, a little endian word an entry
+        let mut returns = bytes.clone();
+        returns[5 + 4096 + 16..][..2].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        assert!(matches!(Snapshot::from_bytes(&returns), Err(Error::State { .. })), "return addresses past memory are refused");
+    }
+}
@@ -0,0 +1,508 @@
+// threaded code backend: the opcodes up to the next call, skip or store are
+// decoded once into a chain of handlers cached by their start address. a
+// store that writes over a cached block drops it so it is decoded again
+
+use std::mem;
+
+use super::{read_word, Processor, ADDRESS_MASK};
+use crate::error::Error;
+
+// longest run of opcodes decoded into one block
+const MAX_BLOCK: usize = 64;
+
+// runs one decoded opcode and returns the address of the next one, the
+// error is boxed to keep the result small enough to return in registers
+type Handler = fn(&mut Processor, &Instruction) -> Result<u16, Box<Error>>;
+
+struct Instruction {
+    handler: Handler,
+    opcode: u16,
+    // where the opcode sits and the address following it
+    address: u16,
+    next: u16,
+    x: usize,
+    y: usize,
+    nn: u8,
+    nnn: u16,
+    // bytes written from I, FX33 and FX55 (and XO-CHIP's stores once they
+    // exist) must say so here for the cache to see self-modifying code
+    stores: u16,
+}
+
+impl Instruction {
+    fn skip_if(&self, condition: bool) -> u16 {
+        if condition { (self.next + 2) & ADDRESS_MASK } else { self.next }
+    }
+}
+
+struct Block {
+    instructions: Vec<Instruction>,
+}
+
+impl Block {
+    // whether an opcode of the block sits on one of `length` bytes from `address`
+    fn overlaps(&self, address: u16, length: u16) -> bool {
+        self.instructions.iter().any(|instruction| {
+            (0..2).any(|byte| {
+                let offset = ((instruction.address + byte) & ADDRESS_MASK).wrapping_sub(address) & ADDRESS_MASK;
+                offset < length
+            })
+        })
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Cache {
+    // block starting at each address, allocated on first use
+    blocks: Vec<Option<Block>>,
+    // bytes holding a cached opcode, bit n % 64 of word n / 64
+    code: Vec<u64>,
+}
+
+impl Cache {
+    pub(super) fn clear(&mut self) {
+        self.blocks.clear();
+        self.code.clear();
+    }
+
+    fn insert(&mut self, start: u16, block: Block) {
+        for instruction in &block.instructions {
+            for byte in 0..2 {
+                let address = ((instruction.address + byte) & ADDRESS_MASK) as usize;
+                self.code[address / 64] |= 1 << (address % 64);
+            }
+        }
+        self.blocks[start as usize] = Some(block);
+    }
+
+    // drops the blocks decoded from the `length` bytes written at `address`
+    fn invalidate(&mut self, address: u16, length: u16) {
+        let written = (0..length).any(|offset| {
+            let address = ((address + offset) & ADDRESS_MASK) as usize;
+            (self.code[address / 64] >> (address % 64)) & 1 == 1
+        });
+        if !written {
+            return;
+        }
+
+        let mut blocks = mem::take(&mut self.blocks);
+        for block in blocks.iter_mut() {
+            if matches!(block, Some(block) if block.overlaps(address, length)) {
+                *block = None;
+            }
+        }
+
+        // rebuild the code map from the blocks left
+        self.code.iter_mut().for_each(|word| *word = 0);
+        self.blocks.resize_with(blocks.len(), || None);
+        for (start, block) in blocks.into_iter().enumerate() {
+            if let Some(block) = block {
+                self.insert(start as u16, block);
+            }
+        }
+    }
+}
+
+impl Processor {
+    // runs `instructions` opcodes a block at a time
+    pub(super) fn run_threaded(&mut self, instructions: usize) -> Result<(), Error> {
+        // the cache is moved out while its handlers borrow the processor
+        let mut cache = mem::take(&mut self.blocks);
+        if cache.blocks.is_empty() {
+            cache.blocks.resize_with(self.memory.len(), || None);
+            cache.code.resize(self.memory.len() / 64, 0);
+        }
+        let result = self.run_blocks(&mut cache, instructions);
+        self.blocks = cache;
+        result
+    }
+
+    fn run_blocks(&mut self, cache: &mut Cache, mut instructions: usize) -> Result<(), Error> {
+        while instructions > 0 {
+            let start = self.program_counter;
+            if cache.blocks[start as usize].is_none() {
+                cache.insert(start, decode_block(&self.memory, start));
+            }
+            let block = cache.blocks[start as usize].as_ref().expect("block was just decoded");
+
+            let count = block.instructions.len().min(instructions);
+            let (last, body) = block.instructions[..count].split_last().expect("blocks are never empty");
+            for instruction in body {
+                if let Err(e) = (instruction.handler)(self, instruction) {
+                    // like the interpreter, stay on the failing opcode
+                    self.program_counter = instruction.address;
+                    return Err(*e);
+                }
+            }
+
+            // stores end a block so only the last opcode can write over code
+            let index_register = self.index_register;
+            match (last.handler)(self, last) {
+                Ok(address) => self.program_counter = address,
+                Err(e) => {
+                    self.program_counter = last.address;
+                    return Err(*e);
+                }
+            }
+            if last.stores > 0 {
+                cache.invalidate(index_register, last.stores);
+            }
+            instructions -= count;
+        }
+        Ok(())
+    }
+}
+
+fn decode_block(memory: &[u8; 4096], start: u16) -> Block {
+    let mut instructions = Vec::new();
+    let mut address = start;
+    loop {
+        let (instruction, ends) = decode(read_word(memory, address), address);
+        // jumps are followed so a loop runs as one block
+        address = if instruction.opcode & 0xF000 == 0x1000 { instruction.nnn } else { instruction.next };
+        instructions.push(instruction);
+
+        // stop at other control flow or a full block
+        if ends || instructions.len() == MAX_BLOCK {
+            return Block { instructions };
+        }
+    }
+}
+
+// the handler for `opcode` and whether it ends a block
+fn decode(opcode: u16, address: u16) -> (Instruction, bool) {
+    // break up into nibbles
+    let op_1 = (opcode & 0xF000) >> 12;
+    let op_2 = (opcode & 0x0F00) >> 8;
+    let op_3 = (opcode & 0x00F0) >> 4;
+    let op_4 = opcode & 0x000F;
+
+    // the common opcodes get their own handler, the rest go through the
+    // interpreter. each handler does what `execute_opcode` does for it
+    let handler: Handler = match (op_1, op_2, op_3, op_4) {
+        // Return from subroutine
+        (0, 0, 0xE, 0xE) => |cpu, op| {
+            if cpu.stack_pointer == 0 {
+                return Err(Box::new(Error::StackUnderflow { pc: op.address }));
+            }
+            cpu.stack_pointer -= 1;
+            Ok(cpu.stack[cpu.stack_pointer as usize] & ADDRESS_MASK)
+        },
+
+        // Jump to Address NNN
+        (0x1, _, _, _) => |_, op| Ok(op.nnn),
+
+        // Call subroutine
+        (0x2, _, _, _) => |cpu, op| {
+            if cpu.stack_pointer as usize == cpu.stack.len() {
+                return Err(Box::new(Error::StackOverflow { pc: op.address }));
+            }
+            cpu.stack[cpu.stack_pointer as usize] = op.next;
+            cpu.stack_pointer += 1;
+            Ok(op.nnn)
+        },
+
+        // Skips
+        (0x3, _, _, _) => |cpu, op| Ok(op.skip_if(cpu.register[op.x] == op.nn)),
+        (0x4, _, _, _) => |cpu, op| Ok(op.skip_if(cpu.register[op.x] != op.nn)),
+        (0x5, _, _, 0x0) => |cpu, op| Ok(op.skip_if(cpu.register[op.x] == cpu.register[op.y])),
+        (0x9, _, _, 0x0) => |cpu, op| Ok(op.skip_if(cpu.register[op.x] != cpu.register[op.y])),
+        (0xE, _, 0x9, 0xE) => |cpu, op| Ok(op.skip_if(cpu.keyboard.pressed((cpu.register[op.x] & 0xF) as usize))),
+        (0xE, _, 0xA, 0x1) => |cpu, op| Ok(op.skip_if(!cpu.keyboard.pressed((cpu.register[op.x] & 0xF) as usize))),
+
+        // Sets VX to NN
+        (0x6, _, _, _) => |cpu, op| {
+            cpu.register[op.x] = op.nn;
+            Ok(op.next)
+        },
+
+        // Adds NN to VX
+        (0x7, _, _, _) => |cpu, op| {
+            cpu.register[op.x] = cpu.register[op.x].wrapping_add(op.nn);
+            Ok(op.next)
+        },
+
+        // Arithmetic and logic on VX and VY
+        (0x8, _, _, 0x0) => |cpu, op| {
+            cpu.register[op.x] = cpu.register[op.y];
+            Ok(op.next)
+        },
+        (0x8, _, _, 0x1) => |cpu, op| {
+            cpu.register[op.x] |= cpu.register[op.y];
+            cpu.logic_reset_vf();
+            Ok(op.next)
+        },
+        (0x8, _, _, 0x2) => |cpu, op| {
+            cpu.register[op.x] &= cpu.register[op.y];
+            cpu.logic_reset_vf();
+            Ok(op.next)
+        },
+        (0x8, _, _, 0x3) => |cpu, op| {
+            cpu.register[op.x] ^= cpu.register[op.y];
+            cpu.logic_reset_vf();
+            Ok(op.next)
+        },
+        (0x8, _, _, 0x4) => |cpu, op| {
+            let (res, overflow) = cpu.register[op.x].overflowing_add(cpu.register[op.y]);
+            cpu.register[0xF] = overflow as u8;
+            cpu.register[op.x] = res;
+            Ok(op.next)
+        },
+        (0x8, _, _, 0x5) => |cpu, op| {
+            let (res, overflow) = cpu.register[op.x].overflowing_sub(cpu.register[op.y]);
+            cpu.register[0xF] = !overflow as u8;
+            cpu.register[op.x] = res;
+            Ok(op.next)
+        },
+        (0x8, _, _, 0x6) => |cpu, op| {
+            let source = if cpu.quirks.shift_uses_vy { cpu.register[op.y] } else { cpu.register[op.x] };
+            cpu.register[0xF] = source & 0x1;
+            cpu.register[op.x] = source >> 1;
+            Ok(op.next)
+        },
+        (0x8, _, _, 0x7) => |cpu, op| {
+            let (res, overflow) = cpu.register[op.y].overflowing_sub(cpu.register[op.x]);
+            cpu.register[0xF] = !overflow as u8;
+            cpu.register[op.x] = res;
+            Ok(op.next)
+        },
+        (0x8, _, _, 0xE) => |cpu, op| {
+            let source = if cpu.quirks.shift_uses_vy { cpu.register[op.y] } else { cpu.register[op.x] };
+            cpu.register[0xF] = (source & 0x80) >> 7;
+            cpu.register[op.x] = source << 1;
+            Ok(op.next)
+        },
+
+        // Sets I to the address NNN
+        (0xA, _, _, _) => |cpu, op| {
+            cpu.index_register = op.nnn;
+            Ok(op.next)
+        },
+
+        // Timers, I and the font
+        (0xF, _, 0x0, 0x7) => |cpu, op| {
+            cpu.register[op.x] = cpu.delay_timer;
+            Ok(op.next)
+        },
+        (0xF, _, 0x1, 0x5) => |cpu, op| {
+            cpu.delay_timer = cpu.register[op.x];
+            Ok(op.next)
+        },
+        (0xF, _, 0x1, 0x8) => |cpu, op| {
+            cpu.sound_timer = cpu.register[op.x];
+            Ok(op.next)
+        },
+        (0xF, _, 0x1, 0xE) => |cpu, op| {
+            cpu.index_register = (cpu.index_register + cpu.register[op.x] as u16) & ADDRESS_MASK;
+            Ok(op.next)
+        },
+        (0xF, _, 0x2, 0x9) => |cpu, op| {
+            cpu.index_register = cpu.register[op.x] as u16 * 5;
+            Ok(op.next)
+        },
+
+        // draws, random numbers, key waits, BNNN, memory and unknown opcodes
+        _ => |cpu, op| {
+            cpu.program_counter = op.address;
+            cpu.execute_opcode(op.opcode).map_err(Box::new)?;
+            Ok(cpu.program_counter)
+        },
+    };
+
+    let stores = match (op_1, op_3, op_4) {
+        (0xF, 0x3, 0x3) => 3,
+        (0xF, 0x5, 0x5) => op_2 + 1,
+        _ => 0,
+    };
+
+    // anything that may not continue with the next opcode, or that writes
+    // memory, ends the block
+    let ends = stores > 0
+        || opcode == 0x00EE
+        || opcode & 0xF0FF == 0xF00A
+        || matches!(op_1, 0x2 | 0x3 | 0x4 | 0x5 | 0x9 | 0xB | 0xE);
+
+    let instruction = Instruction {
+        handler,
+        opcode,
+        address,
+        next: (address + 2) & ADDRESS_MASK,
+        x: op_2 as usize,
+        y: op_3 as usize,
+        nn: (opcode & 0x00FF) as u8,
+        nnn: opcode & 0x0FFF,
+        stores,
+    };
+    (instruction, ends)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{Backend, PROGRAM_START};
+    use crate::quirks::Quirks;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // a processor in a random state running `opcode` next
+    fn random_processor(rng: &mut StdRng, memory: &[u8; 4096], opcode: u16) -> Processor {
+        let mut cpu = Processor::new();
+        cpu.memory = *memory;
+        rng.fill(&mut cpu.register[..]);
+        cpu.program_counter = rng.gen::<u16>() & ADDRESS_MASK;
+        cpu.index_register = rng.gen::<u16>() & ADDRESS_MASK;
+        cpu.stack_pointer = rng.gen_range(0, 17);
+        for address in cpu.stack.iter_mut() {
+            *address = rng.gen::<u16>() & ADDRESS_MASK;
+        }
+        cpu.delay_timer = rng.gen();
+        cpu.sound_timer = rng.gen();
+        for key in 0..16 {
+            if rng.gen() {
+                cpu.keyboard.key_press(key);
+            }
+        }
+        cpu.quirks = Quirks {
+            shift_uses_vy: rng.gen(),
+            load_store_increments_i: rng.gen(),
+            jump_uses_vx: rng.gen(),
+            logic_resets_vf: rng.gen(),
+            clip_sprites: rng.gen(),
+        };
+
+        let [high, low] = opcode.to_be_bytes();
+        cpu.memory[cpu.program_counter as usize] = high;
+        cpu.memory[((cpu.program_counter + 1) & ADDRESS_MASK) as usize] = low;
+        cpu
+    }
+
+    fn assert_same(interpreter: &Processor, threaded: &Processor, context: &str) {
+        assert_eq!(interpreter.register, threaded.register, "registers after {}", context);
+        assert_eq!(interpreter.program_counter, threaded.program_counter, "program counter after {}", context);
+        assert_eq!(interpreter.index_register, threaded.index_register, "I after {}", context);
+        assert_eq!(interpreter.call_stack(), threaded.call_stack(), "stack after {}", context);
+        assert_eq!(interpreter.delay_timer, threaded.delay_timer, "delay timer after {}", context);
+        assert_eq!(interpreter.sound_timer, threaded.sound_timer, "sound timer after {}", context);
+        assert!(interpreter.memory[..] == threaded.memory[..], "memory after {}", context);
+        assert_eq!(interpreter.display.rows(), threaded.display.rows(), "display after {}", context);
+    }
+
+    #[test]
+    fn every_opcode_matches_interpreter() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut memory = [0; 4096];
+
+        for opcode in 0..=0xFFFF {
+            // random numbers can't be compared
+            if opcode & 0xF000 == 0xC000 {
+                continue;
+            }
+            if opcode & 0xFF == 0 {
+                rng.fill(&mut memory[..]);
+            }
+
+            let seed = rng.gen();
+            let mut interpreter = random_processor(&mut StdRng::seed_from_u64(seed), &memory, opcode);
+            let mut threaded = random_processor(&mut StdRng::seed_from_u64(seed), &memory, opcode);
+            threaded.set_backend(Backend::Threaded);
+
+            let expected = interpreter.step();
+            let result = threaded.step();
+            assert_eq!(format!("{:?}", expected), format!("{:?}", result), "result of {:04X}", opcode);
+            assert_same(&interpreter, &threaded, &format!("{:04X}", opcode));
+        }
+    }
+
+    fn run_lockstep(program: &[u8], frames: usize) {
+        let mut interpreter = Processor::new();
+        let mut threaded = Processor::new();
+        threaded.set_backend(Backend::Threaded);
+        for cpu in [&mut interpreter, &mut threaded].iter_mut() {
+            cpu.reset();
+            cpu.load_program(program).unwrap();
+        }
+
+        for frame in 0..frames {
+            // frames of odd sizes end blocks part way through
+            let expected = interpreter.run_frame(frame % 7);
+            let result = threaded.run_frame(frame % 7);
+            assert_eq!(format!("{:?}", expected), format!("{:?}", result), "result of frame {}", frame);
+            assert_same(&interpreter, &threaded, &format!("frame {}", frame));
+            if expected.is_err() {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn roms_match_interpreter() {
+        // pong and c8_test are left out as they draw random numbers
+        run_lockstep(include_bytes!("../../roms/test_opcode.ch8"), 2000);
+    }
+
+    #[test]
+    fn random_programs_match_interpreter() {
+        let mut rng = StdRng::seed_from_u64(35);
+        for _ in 0..200 {
+            let mut program = [0; 256];
+            rng.fill(&mut program[..]);
+            // random numbers can't be compared
+            for opcode in program.chunks_mut(2) {
+                if opcode[0] & 0xF0 == 0xC0 {
+                    opcode[0] = 0x60 | (opcode[0] & 0x0F);
+                }
+            }
+            run_lockstep(&program, 500);
+        }
+    }
+
+    #[test]
+    fn stores_into_code_are_seen() {
+        let program = [
+            0x62, 0x01, // 200: LD V2, 0x01, rewritten to LD V2, 0x02
+            0x33, 0x00, // 202: SE V3, 0x00
+            0x12, 0x04, // 204: JP 0x204
+            0x63, 0x01, // 206: LD V3, 0x01
+            0xA2, 0x00, // 208: LD I, 0x200
+            0x60, 0x62, // 20A: LD V0, 0x62
+            0x61, 0x02, // 20C: LD V1, 0x02
+            0xF1, 0x55, // 20E: LD [I], V1
+            0x12, 0x00, // 210: JP 0x200
+        ];
+        let mut cpu = Processor::new();
+        cpu.set_backend(Backend::Threaded);
+        cpu.reset();
+        cpu.load_program(&program).unwrap();
+
+        cpu.run_frame(11).unwrap();
+        assert_eq!(cpu.register[2], 0x02, "the rewritten opcode runs instead of the cached one");
+        assert_eq!(cpu.program_counter as usize, PROGRAM_START + 4);
+    }
+
+    #[test]
+    fn failed_opcode_keeps_program_counter() {
+        let mut cpu = Processor::new();
+        cpu.set_backend(Backend::Threaded);
+        cpu.reset();
+        cpu.load_program(&[0x60, 0x01, 0xFF, 0xFF]).unwrap();
+
+        let result = cpu.run_frame(2);
+        assert!(matches!(result, Err(Error::UnknownOpcode { opcode: 0xFFFF, pc: 0x202 })), "unknown opcode is reported");
+        assert_eq!(cpu.register[0], 0x01, "opcodes before it ran");
+        assert_eq!(cpu.program_counter, 0x202, "the program counter stays on the failing opcode");
+    }
+
+    #[test]
+    fn returns_stay_in_memory() {
+        let mut cpu = Processor::new();
+        cpu.set_backend(Backend::Threaded);
+        cpu.reset();
+        cpu.load_program(&[0x00, 0xEE]).unwrap();
+        cpu.stack[0] = 0xFFFF;
+        cpu.stack_pointer = 1;
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.program_counter, 0xFFF, "the return address is masked like the interpreter's");
+    }
+}
@@ -0,0 +1,299 @@
+// execution profiler. frames are run an instruction at a time to count how
+// often each address and opcode runs, how long each subroutine takes going by
+// the calls and returns on the stack, and how many frames only wait on a key
+// press or the delay timer. the report is text and the call stacks are also
+// written folded, one `main;outer;inner count` line each, for flame graphs
+
+use crate::disassembler::disassemble_with;
+use crate::error::Error;
+use crate::processor::Processor;
+use crate::symbols::Symbols;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// addresses listed as hot spots
+const HOT_SPOTS: usize = 20;
+
+// a frame looping over at most this many addresses around an FX07 is
+// waiting for the delay timer
+const TIMER_LOOP: usize = 8;
+
+pub struct Profiler {
+    // executions of each address
+    addresses: Vec<u64>,
+    // executions of each opcode, grouped by mnemonic in the report
+    opcodes: Vec<u64>,
+    // entry addresses of the subroutines being run, outermost first
+    calls: Vec<u16>,
+    // each distinct call stack and the instructions run with it on top
+    stacks: HashMap<Vec<u16>, usize>,
+    counts: Vec<u64>,
+    // index of `calls` in `counts`
+    current: usize,
+    // instructions run since the timers last ticked
+    progress: usize,
+    instructions: u64,
+    frames: u64,
+    key_frames: u64,
+    timer_frames: u64,
+    // addresses run this frame, until there are too many for a timer loop
+    frame_addresses: Vec<u16>,
+    frame_reads_timer: bool,
+    frame_repeats: bool,
+    frame_timer: u8,
+    // the last instruction was an FX0A still waiting for its key
+    blocked: bool,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        let mut stacks = HashMap::new();
+        stacks.insert(Vec::new(), 0);
+        Profiler {
+            addresses: vec![0; 0x1000],
+            opcodes: vec![0; 0x10000],
+            calls: Vec::new(),
+            stacks,
+            counts: vec![0],
+            current: 0,
+            progress: 0,
+            instructions: 0,
+            frames: 0,
+            key_frames: 0,
+            timer_frames: 0,
+            frame_addresses: Vec::new(),
+            frame_reads_timer: false,
+            frame_repeats: false,
+            frame_timer: 0,
+            blocked: false,
+        }
+    }
+
+    // runs what is left of a frame of `instructions` instructions, the same
+    // as `Processor::run_frame` but counting each one
+    pub fn run_frame(&mut self, cpu: &mut Processor, instructions: usize) -> Result<(), Error> {
+        if self.progress == 0 {
+            self.frame_addresses.clear();
+            self.frame_reads_timer = false;
+            self.frame_repeats = false;
+            self.frame_timer = cpu.delay_timer();
+        }
+        while self.progress < instructions {
+            self.progress += 1;
+            self.step(cpu)?;
+        }
+
+        if self.blocked {
+            self.key_frames += 1;
+        } else if self.frame_timer > 0 && self.frame_reads_timer && self.frame_repeats && self.frame_addresses.len() <= TIMER_LOOP {
+            self.timer_frames += 1;
+        }
+        self.frames += 1;
+        self.progress = 0;
+        cpu.tick_timers();
+        Ok(())
+    }
+
+    fn step(&mut self, cpu: &mut Processor) -> Result<(), Error> {
+        let pc = cpu.program_counter();
+        let opcode = cpu.opcode_at(pc);
+        let depth = cpu.stack_pointer() as usize;
+        cpu.step()?;
+
+        self.instructions += 1;
+        self.addresses[pc as usize] += 1;
+        self.opcodes[opcode as usize] += 1;
+        self.counts[self.current] += 1;
+
+        if self.frame_addresses.len() <= TIMER_LOOP {
+            if self.frame_addresses.contains(&pc) {
+                self.frame_repeats = true;
+            } else {
+                self.frame_addresses.push(pc);
+            }
+        }
+        self.frame_reads_timer |= opcode & 0xF0FF == 0xF007;
+        self.blocked = opcode & 0xF0FF == 0xF00A && cpu.program_counter() == pc;
+
+        // calls push the subroutine they enter and returns pop it, anything
+        // else moving the stack is followed as well as it can be
+        let sp = cpu.stack_pointer() as usize;
+        if sp != depth || sp != self.calls.len() {
+            self.calls.truncate(sp);
+            while self.calls.len() < sp {
+                self.calls.push(cpu.program_counter());
+            }
+            let next = self.counts.len();
+            self.current = *self.stacks.entry(self.calls.clone()).or_insert(next);
+            if self.current == next {
+                self.counts.push(0);
+            }
+        }
+        Ok(())
+    }
+
+    // instructions run with each call stack on top, outermost first
+    fn samples(&self) -> Vec<(&[u16], u64)> {
+        let mut samples: Vec<(&[u16], u64)> =
+            self.stacks.iter().map(|(stack, index)| (stack.as_slice(), self.counts[*index])).filter(|(_, count)| *count > 0).collect();
+        samples.sort();
+        samples
+    }
+
+    // call stacks for flame graph tools, `main;draw;sprite 1234` per line
+    pub fn folded(&self, symbols: &Symbols) -> String {
+        let mut folded = String::new();
+        for (stack, count) in self.samples() {
+            let mut names = vec!["main".to_string()];
+            names.extend(stack.iter().map(|entry| subroutine(*entry, symbols)));
+            let _ = writeln!(folded, "{} {}", names.join(";"), count);
+        }
+        folded
+    }
+
+    // the disassembly of the hot spots is read from `cpu`'s memory
+    pub fn report(&self, cpu: &Processor, symbols: &Symbols) -> String {
+        let mut report = String::new();
+        let total = self.instructions.max(1);
+        let frames = self.frames.max(1);
+        let percent = |count: u64, total: u64| count as f64 * 100.0 / total as f64;
+
+        let _ = writeln!(report, "{} instructions over {} frames\n", self.instructions, self.frames);
+        let _ = writeln!(report, "frames waiting");
+        let _ = writeln!(report, "  {:>10} {:>6.2}%  key press (FX0A)", self.key_frames, percent(self.key_frames, frames));
+        let _ = writeln!(report, "  {:>10} {:>6.2}%  delay timer loop", self.timer_frames, percent(self.timer_frames, frames));
+
+        let mut hot: Vec<(usize, u64)> = self.addresses.iter().copied().enumerate().filter(|(_, count)| *count > 0).collect();
+        hot.sort_by_key(|(address, count)| (std::cmp::Reverse(*count), *address));
+        let _ = writeln!(report, "\nhot spots");
+        for (address, count) in hot.into_iter().take(HOT_SPOTS) {
+            let address = address as u16;
+            let place = symbols.describe(address).unwrap_or_default();
+            let instruction = disassemble_with(cpu.opcode_at(address), symbols);
+            let _ = writeln!(report, "  {:>10} {:>6.2}%  {:03X} {:<16} {}", count, percent(count, total), address, place, instruction);
+        }
+
+        let mut classes: HashMap<String, u64> = HashMap::new();
+        for (opcode, count) in self.opcodes.iter().enumerate().filter(|(_, count)| **count > 0) {
+            let mnemonic = disassemble_with(opcode as u16, symbols).split(' ').next().unwrap_or_default().to_string();
+            *classes.entry(mnemonic).or_insert(0) += count;
+        }
+        let mut classes: Vec<(String, u64)> = classes.into_iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let _ = writeln!(report, "\nopcode classes");
+        for (mnemonic, count) in classes {
+            let _ = writeln!(report, "  {:>10} {:>6.2}%  {}", count, percent(count, total), mnemonic);
+        }
+
+        // self time is spent with the subroutine on top of the stack, total
+        // time with it anywhere on the stack, counted once under recursion
+        let mut times: HashMap<Option<u16>, (u64, u64)> = HashMap::new();
+        for (stack, count) in self.samples() {
+            times.entry(stack.last().copied()).or_insert((0, 0)).0 += count;
+            let mut seen: Vec<Option<u16>> = vec![None];
+            seen.extend(stack.iter().copied().map(Some));
+            seen.sort();
+            seen.dedup();
+            for entry in seen {
+                times.entry(entry).or_insert((0, 0)).1 += count;
+            }
+        }
+        let mut times: Vec<(Option<u16>, (u64, u64))> = times.into_iter().collect();
+        times.sort_by_key(|(entry, (own, all))| (std::cmp::Reverse(*all), std::cmp::Reverse(*own), *entry));
+        let _ = writeln!(report, "\nsubroutines        self                total");
+        for (entry, (own, all)) in times {
+            let name = entry.map_or_else(|| "main".to_string(), |entry| subroutine(entry, symbols));
+            let _ = writeln!(report, "  {:>10} {:>6.2}%  {:>10} {:>6.2}%  {}", own, percent(own, total), all, percent(all, total), name);
+        }
+        report
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Profiler {
+        Profiler::new()
+    }
+}
+
+fn subroutine(entry: u16, symbols: &Symbols) -> String {
+    match symbols.name(entry) {
+        Some(name) => name.to_string(),
+        None => format!("sub_{:03x}", entry),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processor(program: &[u8]) -> Processor {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.load_program(program).unwrap();
+        cpu
+    }
+
+    // calls a subroutine then waits for a key
+    const WAIT_FOR_KEY: [u8; 12] = [0x60, 0x05, 0x22, 0x08, 0xF0, 0x0A, 0x12, 0x06, 0x71, 0x01, 0x00, 0xEE];
+
+    #[test]
+    fn executions_are_counted() {
+        let mut cpu = processor(&WAIT_FOR_KEY);
+        let mut profiler = Profiler::new();
+        profiler.run_frame(&mut cpu, 10).unwrap();
+        assert_eq!(profiler.instructions, 10, "every instruction is counted");
+        assert_eq!(profiler.addresses[0x204], 6, "the key wait ran for the rest of the frame");
+        assert_eq!(profiler.opcodes[0x2208], 1, "the call ran once");
+
+        let report = profiler.report(&cpu, &Symbols::new());
+        assert!(report.contains("         6  60.00%  204                  LD V0, K"), "the key wait is the hot spot");
+        assert!(report.contains("         7  70.00%  LD"), "loads are grouped into a class");
+    }
+
+    #[test]
+    fn subroutines_are_timed() {
+        let mut cpu = processor(&WAIT_FOR_KEY);
+        let mut profiler = Profiler::new();
+        profiler.run_frame(&mut cpu, 10).unwrap();
+        assert_eq!(profiler.folded(&Symbols::new()), "main 8\nmain;sub_208 2\n", "the call and its return are folded");
+
+        let symbols = Symbols::parse("208 bump\n", std::path::Path::new("")).unwrap();
+        assert_eq!(profiler.folded(&symbols), "main 8\nmain;bump 2\n", "subroutines are named by label");
+        let report = profiler.report(&cpu, &symbols);
+        assert!(report.contains("         8  80.00%          10 100.00%  main"), "main holds everything");
+        assert!(report.contains("         2  20.00%           2  20.00%  bump"), "the subroutine has its own time");
+    }
+
+    #[test]
+    fn recursion_is_counted_once() {
+        // a subroutine calling itself twice before returning
+        let program = [0x22, 0x04, 0x12, 0x02, 0x70, 0x01, 0x30, 0x02, 0x22, 0x04, 0x00, 0xEE];
+        let mut cpu = processor(&program);
+        let mut profiler = Profiler::new();
+        profiler.run_frame(&mut cpu, 12).unwrap();
+        assert_eq!(profiler.folded(&Symbols::new()), "main 5\nmain;sub_204 4\nmain;sub_204;sub_204 3\n", "each depth is its own stack");
+        assert!(profiler.report(&cpu, &Symbols::new()).contains("          7  58.33%  sub_204"), "total time counts each instruction once");
+    }
+
+    #[test]
+    fn waiting_frames_are_counted() {
+        let mut cpu = processor(&WAIT_FOR_KEY);
+        let mut profiler = Profiler::new();
+        for _ in 0..3 {
+            profiler.run_frame(&mut cpu, 10).unwrap();
+        }
+        cpu.keyboard.key_press(3);
+        profiler.run_frame(&mut cpu, 10).unwrap();
+        assert_eq!((profiler.frames, profiler.key_frames), (4, 3), "frames ending on a key wait are counted");
+
+        // sets the delay timer and loops reading it until it runs out
+        let program = [0x60, 0x03, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x04, 0x12, 0x0A];
+        let mut cpu = processor(&program);
+        let mut profiler = Profiler::new();
+        for _ in 0..6 {
+            profiler.run_frame(&mut cpu, 10).unwrap();
+        }
+        assert_eq!(profiler.timer_frames, 2, "frames looping while the timer runs are counted");
+    }
+}
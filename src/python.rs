@@ -0,0 +1,307 @@
+// the `chip8` python module, a machine to script experiments with:
+//
+//   import chip8, numpy
+//   machine = chip8.Machine(open("pong", "rb").read())
+//   machine.press(0x1)
+//   machine.step(60)
+//   screen = numpy.frombuffer(machine.framebuffer(), numpy.uint8).reshape(chip8.HEIGHT, chip8.WIDTH)
+//
+// errors from the rom raise chip8.Error, unreadable files raise OSError
+
+use std::path::PathBuf;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::database::Database;
+use crate::display::{HEIGHT, WIDTH};
+use crate::error::Error;
+use crate::patch;
+use crate::processor::{Processor, Snapshot, ADDRESS_MASK};
+
+// named so python sees chip8.Error
+mod exceptions {
+    pyo3::create_exception!(chip8, Error, pyo3::exceptions::PyException);
+}
+
+impl From<Error> for PyErr {
+    fn from(error: Error) -> PyErr {
+        match error {
+            Error::Io(e) => e.into(),
+            other => exceptions::Error::new_err(other.to_string()),
+        }
+    }
+}
+
+fn check_key(key: u8) -> PyResult<u8> {
+    match key {
+        0..=0xF => Ok(key),
+        _ => Err(PyValueError::new_err(format!("key {} is not a hex key", key))),
+    }
+}
+
+// the processor at a point in time, from `Machine.save_state`
+#[pyclass(module = "chip8", frozen)]
+pub struct State(Snapshot);
+
+// the processor holds compiled code that stays on the thread it was made on
+#[pyclass(module = "chip8", unsendable)]
+pub struct Machine {
+    cpu: Processor,
+    instructions_per_frame: usize,
+}
+
+impl Machine {
+    fn load_rom(&mut self, rom: &[u8]) -> Result<(), Error> {
+        let info = Database::bundled().lookup(rom);
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.set_quirks(info.quirks);
+        cpu.load_program(rom)?;
+        self.cpu = cpu;
+        self.instructions_per_frame = info.instructions_per_frame;
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl Machine {
+    #[new]
+    #[pyo3(signature = (rom=None))]
+    fn new(rom: Option<&[u8]>) -> PyResult<Machine> {
+        let mut machine = Machine { cpu: Processor::new(), instructions_per_frame: 0 };
+        machine.load_rom(rom.unwrap_or_default())?;
+        Ok(machine)
+    }
+
+    // starts `rom` from scratch with the quirks and speed the database has for it
+    fn load(&mut self, rom: &[u8]) -> PyResult<()> {
+        Ok(self.load_rom(rom)?)
+    }
+
+    // reads the rom with `patch` or the ips or bps patch beside it applied
+    #[pyo3(signature = (path, patch=None))]
+    fn load_file(&mut self, path: PathBuf, patch: Option<PathBuf>) -> PyResult<()> {
+        let rom = patch::read_rom(&path, patch.as_deref())?;
+        self.load(&rom)
+    }
+
+    // runs `frames` 60Hz frames
+    #[pyo3(signature = (frames=1))]
+    fn step(&mut self, frames: usize) -> PyResult<()> {
+        for _ in 0..frames {
+            self.cpu.run_frame(self.instructions_per_frame)?;
+        }
+        Ok(())
+    }
+
+    // runs one instruction without ticking the timers
+    fn step_instruction(&mut self) -> PyResult<()> {
+        Ok(self.cpu.step()?)
+    }
+
+    fn press(&mut self, key: u8) -> PyResult<()> {
+        self.cpu.keyboard.key_press(check_key(key)?);
+        Ok(())
+    }
+
+    fn release(&mut self, key: u8) -> PyResult<()> {
+        self.cpu.keyboard.key_release(check_key(key)?);
+        Ok(())
+    }
+
+    // one byte a pixel, 1 when lit, row by row from the top left
+    fn framebuffer<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let pixels: Vec<u8> = self.cpu.display.pixels().map(|(_, _, on)| on as u8).collect();
+        PyBytes::new(py, &pixels)
+    }
+
+    // `length` bytes from `address`, wrapping at the end of memory
+    fn read_memory<'py>(&self, py: Python<'py>, address: u16, length: usize) -> Bound<'py, PyBytes> {
+        let memory = self.cpu.memory();
+        let bytes: Vec<u8> = (0..length).map(|offset| memory[(address as usize + offset) & ADDRESS_MASK as usize]).collect();
+        PyBytes::new(py, &bytes)
+    }
+
+    fn write_memory(&mut self, address: u16, data: &[u8]) {
+        self.cpu.write_memory(address, data);
+    }
+
+    #[getter]
+    fn registers(&self) -> Vec<u8> {
+        self.cpu.registers().to_vec()
+    }
+
+    fn set_register(&mut self, index: usize, value: u8) -> PyResult<()> {
+        if index > 0xF {
+            return Err(PyValueError::new_err(format!("V{} is not a register", index)));
+        }
+        self.cpu.set_register(index, value);
+        Ok(())
+    }
+
+    #[getter]
+    fn pc(&self) -> u16 {
+        self.cpu.program_counter()
+    }
+
+    #[setter]
+    fn set_pc(&mut self, address: u16) {
+        self.cpu.set_program_counter(address);
+    }
+
+    #[getter]
+    fn index(&self) -> u16 {
+        self.cpu.index_register()
+    }
+
+    #[setter]
+    fn set_index(&mut self, address: u16) {
+        self.cpu.set_index_register(address);
+    }
+
+    #[getter]
+    fn stack(&self) -> Vec<u16> {
+        self.cpu.call_stack().to_vec()
+    }
+
+    #[getter]
+    fn delay_timer(&self) -> u8 {
+        self.cpu.delay_timer()
+    }
+
+    #[getter]
+    fn sound_timer(&self) -> u8 {
+        self.cpu.sound_timer()
+    }
+
+    #[getter]
+    fn instructions_per_frame(&self) -> usize {
+        self.instructions_per_frame
+    }
+
+    #[setter]
+    fn set_instructions_per_frame(&mut self, instructions: usize) {
+        self.instructions_per_frame = instructions;
+    }
+
+    // CXNN draws the same numbers after each call with the same seed
+    fn seed(&mut self, seed: u64) {
+        self.cpu.set_seed(seed);
+    }
+
+    fn save_state(&self) -> State {
+        State(self.cpu.snapshot())
+    }
+
+    fn load_state(&mut self, state: &State) {
+        self.cpu.restore(&state.0);
+    }
+}
+
+#[pymodule]
+fn chip8(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Machine>()?;
+    module.add_class::<State>()?;
+    module.add("Error", module.py().get_type::<exceptions::Error>())?;
+    module.add("WIDTH", WIDTH)?;
+    module.add("HEIGHT", HEIGHT)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    // runs python `code` with the module imported as chip8 and pong as PONG
+    fn python(code: &str) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            locals.set_item("chip8", pyo3::wrap_pymodule!(chip8)(py)).unwrap();
+            locals.set_item("PONG", PyBytes::new(py, include_bytes!("../roms/pong"))).unwrap();
+            if let Err(e) = py.run(&std::ffi::CString::new(code).unwrap(), None, Some(&locals)) {
+                panic!("{}", e);
+            }
+        });
+    }
+
+    #[test]
+    fn frames_and_keys() {
+        python(
+            r#"
+machine = chip8.Machine(PONG)
+assert machine.instructions_per_frame > 0, "the database sets the speed"
+assert machine.pc == 0x200, "roms start at 0x200"
+machine.step(10)
+screen = machine.framebuffer()
+assert len(screen) == chip8.WIDTH * chip8.HEIGHT, "a byte a pixel"
+assert set(screen) == {0, 1}, "pong draws paddles"
+machine.press(0xF)
+machine.release(0xF)
+try:
+    machine.press(16)
+    assert False, "keys past F are refused"
+except ValueError:
+    pass
+"#,
+        );
+    }
+
+    #[test]
+    fn memory_and_registers() {
+        python(
+            r#"
+machine = chip8.Machine()
+machine.write_memory(0x200, bytes([0x60, 0x2A, 0xA3, 0x00, 0xF0, 0x55]))
+for _ in range(3):
+    machine.step_instruction()
+assert machine.registers[0] == 0x2A, "6XNN loads V0"
+assert machine.index == 0x300, "ANNN loads I"
+assert machine.read_memory(0x300, 1) == b"\x2a", "FX55 stores V0"
+assert machine.read_memory(0xFFF, 2) == bytes([0, 0xF0]), "reads wrap to the font"
+machine.set_register(3, 7)
+machine.pc = 0x204
+assert (machine.registers[3], machine.pc) == (7, 0x204), "registers can be written"
+"#,
+        );
+    }
+
+    #[test]
+    fn states_restore() {
+        python(
+            r#"
+machine = chip8.Machine(PONG)
+machine.seed(1)
+machine.step(30)
+state = machine.save_state()
+machine.step(100)
+later = (machine.framebuffer(), machine.registers)
+machine.load_state(state)
+machine.step(100)
+assert (machine.framebuffer(), machine.registers) == later, "the game replays from the state"
+"#,
+        );
+    }
+
+    #[test]
+    fn errors_raise() {
+        python(
+            r#"
+try:
+    chip8.Machine(bytes([0xFF, 0xFF])).step()
+    assert False, "unknown opcodes raise"
+except chip8.Error as e:
+    assert "unknown opcode" in str(e), str(e)
+try:
+    chip8.Machine().load_file("/nonexistent/rom.ch8")
+    assert False, "missing files raise"
+except OSError:
+    pass
+"#,
+        );
+    }
+}
@@ -0,0 +1,51 @@
+// behaviours that differ between chip-8 interpreters, the default matches
+// the interpreter as first written: wrapping sprites and the SCHIP shift
+// and load/store semantics with the original BNNN jump
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quirks {
+    // 8XY6/8XYE shift VY into VX instead of shifting VX in place
+    pub shift_uses_vy: bool,
+    // FX55/FX65 leave I pointing past the last register stored or loaded
+    pub load_store_increments_i: bool,
+    // BXNN jumps to XNN plus VX rather than NNN plus V0
+    pub jump_uses_vx: bool,
+    // 8XY1/8XY2/8XY3 clear VF
+    pub logic_resets_vf: bool,
+    // sprites are cut off at the screen edge instead of wrapping around
+    pub clip_sprites: bool,
+}
+
+impl Quirks {
+    // the original COSMAC VIP interpreter
+    pub fn chip8() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            logic_resets_vf: true,
+            clip_sprites: true,
+        }
+    }
+
+    // SUPER-CHIP 1.1 on the HP48
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+        }
+    }
+
+    // XO-CHIP as implemented by Octo
+    pub fn xo_chip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+        }
+    }
+}
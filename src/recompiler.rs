@@ -0,0 +1,712 @@
+// static recompilation of a rom to a standalone rust module. the rom's
+// control flow is followed from the program start to find every block entry,
+// each block becomes a native function and a dispatcher matches the program
+// counter to them. computed BNNN jumps land in the dispatcher, addresses it
+// has no block for run in an interpreter bundled into the module, and so does
+// everything once the rom stores over its own code
+
+use crate::database::sha1;
+use crate::disassembler::disassemble;
+use crate::error::Error;
+use crate::processor::{ADDRESS_MASK, FONT_SET, PROGRAM_START};
+use crate::quirks::Quirks;
+use std::collections::BTreeMap;
+
+// longest run of opcodes recompiled into one function
+const MAX_BLOCK: usize = 64;
+
+// opcodes from one entry address up to the first one that leaves the block,
+// plain jumps are followed
+#[derive(Debug, PartialEq)]
+struct Block {
+    // address and opcode of every instruction in the order they run
+    opcodes: Vec<(u16, u16)>,
+    // where the block continues when its last opcode doesn't decide that
+    next: Option<u16>,
+}
+
+// writes the module implementing `rom` for an interpreter with `quirks`
+pub fn recompile(rom: &[u8], quirks: Quirks) -> Result<String, Error> {
+    let mut memory = [0; 4096];
+    if rom.len() > memory.len() - PROGRAM_START {
+        return Err(Error::RomTooLarge { size: rom.len() });
+    }
+    memory[..FONT_SET.len()].copy_from_slice(&FONT_SET);
+    memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(rom);
+
+    let blocks = analyze(&memory, rom.len());
+    let mut code = [0u64; 64];
+    for (address, _) in blocks.values().flat_map(|block| &block.opcodes) {
+        for byte in 0..2 {
+            let address = ((address + byte) & ADDRESS_MASK) as usize;
+            code[address / 64] |= 1 << (address % 64);
+        }
+    }
+
+    let mut out = Vec::new();
+    out.push(format!("// recompiled by `chip_8 recompile` from the rom with sha1 {}, regenerate it", sha1(rom)));
+    out.push("// rather than editing by hand".to_string());
+    out.push(String::new());
+    out.push("use chip_8::display::Display;".to_string());
+    out.push("use chip_8::error::Error;".to_string());
+    out.push("use chip_8::keyboard::Keyboard;".to_string());
+    out.push(String::new());
+    out.push("// interpreter behaviour the rom was recompiled for".to_string());
+    out.push(format!("const SHIFT_USES_VY: bool = {};", quirks.shift_uses_vy));
+    out.push(format!("const LOAD_STORE_INCREMENTS_I: bool = {};", quirks.load_store_increments_i));
+    out.push(format!("const JUMP_USES_VX: bool = {};", quirks.jump_uses_vx));
+    out.push(format!("const LOGIC_RESETS_VF: bool = {};", quirks.logic_resets_vf));
+    out.push(format!("const CLIP_SPRITES: bool = {};", quirks.clip_sprites));
+    out.push(String::new());
+    out.push(format!("static FONT: [u8; {}] = [", FONT_SET.len()));
+    out.extend(FONT_SET.chunks(16).map(|chunk| format!("    {}", hex_list(chunk.iter().map(|byte| format!("{:#04X}", byte))))));
+    out.push("];".to_string());
+    out.push(String::new());
+    out.push(format!("static ROM: [u8; {}] = [", rom.len()));
+    out.extend(rom.chunks(16).map(|chunk| format!("    {}", hex_list(chunk.iter().map(|byte| format!("{:#04X}", byte))))));
+    out.push("];".to_string());
+    out.push(String::new());
+    out.push("// bit per memory byte holding a recompiled opcode".to_string());
+    out.push("static CODE: [u64; 64] = [".to_string());
+    out.extend(code.chunks(4).map(|chunk| format!("    {}", hex_list(chunk.iter().map(|word| format!("{:#018X}", word))))));
+    out.push("];".to_string());
+    out.push(String::new());
+    out.extend(MACHINE.lines().map(str::to_string));
+
+    out.push(String::new());
+    out.push("    // runs the block recompiled at the program counter when it fits in".to_string());
+    out.push("    // `remaining` instructions, otherwise interprets one, and returns how".to_string());
+    out.push("    // many instructions ran".to_string());
+    out.push("    fn dispatch(&mut self, remaining: usize) -> Result<usize, Error> {".to_string());
+    out.push("        if !self.native {".to_string());
+    out.push("            return self.interpret().map(|_| 1);".to_string());
+    out.push("        }".to_string());
+    out.push("        match self.program_counter {".to_string());
+    for (start, block) in &blocks {
+        out.push(format!("            {:#05X} if remaining >= {} => self.block_{:03x}(),", start, block.opcodes.len(), start));
+    }
+    out.push("            _ => self.interpret().map(|_| 1),".to_string());
+    out.push("        }".to_string());
+    out.push("    }".to_string());
+
+    for (start, block) in &blocks {
+        out.push(String::new());
+        out.push(format!("    fn block_{:03x}(&mut self) -> Result<usize, Error> {{", start));
+        for line in emit_block(block, quirks) {
+            out.push(if line.is_empty() { line } else { format!("        {}", line) });
+        }
+        out.push("    }".to_string());
+    }
+    out.push("}".to_string());
+
+    let mut source = out.join("\n");
+    source.push('\n');
+    Ok(source)
+}
+
+fn hex_list<I: Iterator<Item = String>>(values: I) -> String {
+    values.map(|value| value + ",").collect::<Vec<_>>().join(" ")
+}
+
+// blocks by entry address, found by following every path out of the program
+// start. BNNN could land anywhere past NNN so each even offset from it that
+// is inside the rom is treated as an entry too
+fn analyze(memory: &[u8; 4096], rom_length: usize) -> BTreeMap<u16, Block> {
+    let mut blocks = BTreeMap::new();
+    let mut pending = vec![PROGRAM_START as u16];
+    while let Some(start) = pending.pop() {
+        if blocks.contains_key(&start) {
+            continue;
+        }
+        let block = decode_block(memory, start);
+        let &(address, opcode) = block.opcodes.last().unwrap();
+        let next = (address + 2) & ADDRESS_MASK;
+        let skip = (address + 4) & ADDRESS_MASK;
+
+        if let Some(next) = block.next {
+            pending.push(next);
+        } else {
+            match kind(opcode) {
+                Kind::Call(target) => pending.extend([target, next]),
+                Kind::Skip => pending.extend([next, skip]),
+                Kind::WaitKey => pending.extend([address, next]),
+                Kind::Store => pending.push(next),
+                Kind::ComputedJump(base) => {
+                    let rom = PROGRAM_START as u16..(PROGRAM_START + rom_length) as u16;
+                    pending.extend((0..=0xFF).step_by(2).map(|offset| base + offset).filter(|target| rom.contains(target)));
+                },
+                _ => (),
+            }
+        }
+        blocks.insert(start, block);
+    }
+    blocks
+}
+
+fn decode_block(memory: &[u8; 4096], start: u16) -> Block {
+    let mut opcodes = Vec::new();
+    let mut address = start;
+    loop {
+        // jumps back into the block and long blocks continue in another one
+        if opcodes.len() == MAX_BLOCK || opcodes.iter().any(|(seen, _)| *seen == address) {
+            return Block { opcodes, next: Some(address) };
+        }
+        let opcode = (memory[address as usize] as u16) << 8 | memory[((address + 1) & ADDRESS_MASK) as usize] as u16;
+        opcodes.push((address, opcode));
+        match kind(opcode) {
+            Kind::Jump(target) => address = target,
+            Kind::Plain => address = (address + 2) & ADDRESS_MASK,
+            _ => return Block { opcodes, next: None },
+        }
+    }
+}
+
+// how an opcode affects control flow
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    // runs and falls through to the next opcode
+    Plain,
+    Jump(u16),
+    Call(u16),
+    Return,
+    // continues two or four bytes on
+    Skip,
+    // BNNN or BXNN, the value is the lowest address it reaches
+    ComputedJump(u16),
+    // stays on itself until a key is pressed
+    WaitKey,
+    // writes memory that might hold code
+    Store,
+    // raises an error when it runs
+    Unknown,
+}
+
+fn kind(opcode: u16) -> Kind {
+    let nnn = opcode & 0x0FFF;
+    match ((opcode & 0xF000) >> 12, (opcode & 0x00F0) >> 4, opcode & 0x000F) {
+        (0x0, _, _) if opcode == 0x00E0 => Kind::Plain,
+        (0x0, _, _) if opcode == 0x00EE => Kind::Return,
+        (0x1, _, _) => Kind::Jump(nnn),
+        (0x2, _, _) => Kind::Call(nnn),
+        (0x3, _, _) | (0x4, _, _) | (0x5, _, 0x0) | (0x9, _, 0x0) => Kind::Skip,
+        (0x6, _, _) | (0x7, _, _) | (0xA, _, _) | (0xC, _, _) | (0xD, _, _) => Kind::Plain,
+        (0x8, _, 0x0..=0x7) | (0x8, _, 0xE) => Kind::Plain,
+        (0xB, _, _) => Kind::ComputedJump(nnn),
+        (0xE, 0x9, 0xE) | (0xE, 0xA, 0x1) => Kind::Skip,
+        (0xF, 0x0, 0x7) | (0xF, 0x1, 0x5) | (0xF, 0x1, 0x8) | (0xF, 0x1, 0xE) | (0xF, 0x2, 0x9) | (0xF, 0x6, 0x5) => Kind::Plain,
+        (0xF, 0x0, 0xA) => Kind::WaitKey,
+        (0xF, 0x3, 0x3) | (0xF, 0x5, 0x5) => Kind::Store,
+        (_, _, _) => Kind::Unknown,
+    }
+}
+
+// body of the function running `block`, one comment with the disassembly
+// and the statements for each opcode
+fn emit_block(block: &Block, quirks: Quirks) -> Vec<String> {
+    let mut lines = Vec::new();
+    for &(address, opcode) in &block.opcodes {
+        lines.push(format!("// {:#05X} {}", address, disassemble(opcode)));
+        lines.extend(emit_opcode(address, opcode, quirks));
+    }
+    let (address, opcode) = *block.opcodes.last().unwrap();
+    if let Some(next) = block.next {
+        lines.push(format!("self.program_counter = {:#05X};", next));
+    }
+    if kind(opcode) == Kind::Unknown {
+        lines.push(format!("self.program_counter = {:#05X};", address));
+        lines.push(format!("Err(Error::UnknownOpcode {{ opcode: {:#06X}, pc: {:#05X} }})", opcode, address));
+    } else {
+        lines.push(format!("Ok({})", block.opcodes.len()));
+    }
+    lines
+}
+
+// statements running `opcode` at `address`, mirroring the interpreter with
+// the operands and quirks folded in
+fn emit_opcode(address: u16, opcode: u16, quirks: Quirks) -> Vec<String> {
+    let op_1 = (opcode & 0xF000) >> 12;
+    let op_2 = (opcode & 0x0F00) >> 8;
+    let op_3 = (opcode & 0x00F0) >> 4;
+    let op_4 = opcode & 0x000F;
+
+    let nnn = opcode & 0x0FFF;
+    let nn = opcode & 0x00FF;
+    let n = opcode & 0x000F;
+
+    let vx = format!("self.register[{:#X}]", op_2);
+    let vy = format!("self.register[{:#X}]", op_3);
+    let next = (address + 2) & ADDRESS_MASK;
+    let skip = (address + 4) & ADDRESS_MASK;
+
+    let skip_if = |condition: String| vec![format!("self.program_counter = if {} {{ {:#05X} }} else {{ {:#05X} }};", condition, skip, next)];
+    let jump = |target: u16| vec![format!("self.program_counter = {:#05X};", target)];
+    let shift = |source: &str| if quirks.shift_uses_vy { source.replace("{}", &vy) } else { source.replace("{}", &vx) };
+    let logic = |operator: &str| {
+        let mut lines = vec![
+            format!("let (vx, vy) = ({}, {});", vx, vy),
+            format!("{} = vx {} vy;", vx, operator),
+        ];
+        if quirks.logic_resets_vf {
+            lines.push("self.register[0xF] = 0;".to_string());
+        }
+        lines
+    };
+    let arithmetic = |operation: &str, flag: &str| vec![
+        format!("let (result, overflow) = {};", operation),
+        format!("self.register[0xF] = {} as u8;", flag),
+        format!("{} = result;", vx),
+    ];
+
+    match (op_1, op_2, op_3, op_4) {
+        (0, 0, 0xE, 0) => vec!["self.display.clear();".to_string()],
+        (0, 0, 0xE, 0xE) => vec![
+            "if self.stack_pointer == 0 {".to_string(),
+            format!("    self.program_counter = {:#05X};", address),
+            format!("    return Err(Error::StackUnderflow {{ pc: {:#05X} }});", address),
+            "}".to_string(),
+            "self.stack_pointer -= 1;".to_string(),
+            "self.program_counter = self.stack[self.stack_pointer as usize];".to_string(),
+        ],
+        // plain jumps are followed inside the block
+        (0x1, _, _, _) => Vec::new(),
+        (0x2, _, _, _) => vec![
+            "if self.stack_pointer as usize == self.stack.len() {".to_string(),
+            format!("    self.program_counter = {:#05X};", address),
+            format!("    return Err(Error::StackOverflow {{ pc: {:#05X} }});", address),
+            "}".to_string(),
+            format!("self.stack[self.stack_pointer as usize] = {:#05X};", next),
+            "self.stack_pointer += 1;".to_string(),
+            format!("self.program_counter = {:#05X};", nnn),
+        ],
+        (0x3, _, _, _) => skip_if(format!("{} == {:#04X}", vx, nn)),
+        (0x4, _, _, _) => skip_if(format!("{} != {:#04X}", vx, nn)),
+        (0x5, _, _, 0x0) if op_2 == op_3 => jump(skip),
+        (0x5, _, _, 0x0) => skip_if(format!("{} == {}", vx, vy)),
+        (0x6, _, _, _) => vec![format!("{} = {:#04X};", vx, nn)],
+        (0x7, _, _, _) => vec![format!("{} = {}.wrapping_add({:#04X});", vx, vx, nn)],
+        (0x8, _, _, 0x0) if op_2 == op_3 => Vec::new(),
+        (0x8, _, _, 0x0) => vec![format!("{} = {};", vx, vy)],
+        (0x8, _, _, 0x1) => logic("|"),
+        (0x8, _, _, 0x2) => logic("&"),
+        (0x8, _, _, 0x3) => logic("^"),
+        (0x8, _, _, 0x4) => arithmetic(&format!("{}.overflowing_add({})", vx, vy), "overflow"),
+        (0x8, _, _, 0x5) => arithmetic(&format!("{}.overflowing_sub({})", vx, vy), "!overflow"),
+        (0x8, _, _, 0x6) => vec![
+            shift("let source = {};"),
+            "self.register[0xF] = source & 0x1;".to_string(),
+            format!("{} = source >> 1;", vx),
+        ],
+        (0x8, _, _, 0x7) => arithmetic(&format!("{}.overflowing_sub({})", vy, vx), "!overflow"),
+        (0x8, _, _, 0xE) => vec![
+            shift("let source = {};"),
+            "self.register[0xF] = source >> 7;".to_string(),
+            format!("{} = source << 1;", vx),
+        ],
+        (0x9, _, _, 0x0) if op_2 == op_3 => jump(next),
+        (0x9, _, _, 0x0) => skip_if(format!("{} != {}", vx, vy)),
+        (0xA, _, _, _) => vec![format!("self.index_register = {:#05X};", nnn)],
+        (0xB, _, _, _) => {
+            let offset = if quirks.jump_uses_vx { &vx } else { "self.register[0x0]" };
+            vec![format!("self.program_counter = ({:#05X} + {} as u16) & 0xFFF;", nnn, offset)]
+        },
+        (0xC, _, _, _) => vec![format!("{} = {:#04X} & rand::random::<u8>();", vx, nn)],
+        (0xD, _, _, _) => vec![
+            format!("let mut sprite = [0; {}];", n),
+            "for (row, byte) in sprite.iter_mut().enumerate() {".to_string(),
+            "    *byte = self.memory[self.address(row)];".to_string(),
+            "}".to_string(),
+            format!(
+                "let collision = self.display.{}({}, {}, &sprite);",
+                if quirks.clip_sprites { "draw_clipped" } else { "draw" },
+                vx,
+                vy
+            ),
+            "self.register[0xF] = collision as u8;".to_string(),
+        ],
+        (0xE, _, 0x9, 0xE) => skip_if(format!("self.keyboard.pressed(({} & 0xF) as usize)", vx)),
+        (0xE, _, 0xA, 0x1) => skip_if(format!("!self.keyboard.pressed(({} & 0xF) as usize)", vx)),
+        (0xF, _, 0x0, 0x7) => vec![format!("{} = self.delay_timer;", vx)],
+        (0xF, _, 0x0, 0xA) => vec![
+            // stays on this opcode while no key is pressed
+            format!("self.program_counter = {:#05X};", address),
+            "for key in 0..16 {".to_string(),
+            "    if self.keyboard.pressed(key) {".to_string(),
+            format!("        {} = key as u8;", vx),
+            format!("        self.program_counter = {:#05X};", next),
+            "    }".to_string(),
+            "}".to_string(),
+        ],
+        (0xF, _, 0x1, 0x5) => vec![format!("self.delay_timer = {};", vx)],
+        (0xF, _, 0x1, 0x8) => vec![format!("self.sound_timer = {};", vx)],
+        (0xF, _, 0x1, 0xE) => vec![format!("self.index_register = (self.index_register + {} as u16) & 0xFFF;", vx)],
+        (0xF, _, 0x2, 0x9) => vec![format!("self.index_register = {} as u16 * 5;", vx)],
+        (0xF, _, 0x3, 0x3) => vec![
+            format!("let value = {};", vx),
+            "self.memory[self.address(0)] = value / 100;".to_string(),
+            "self.memory[self.address(1)] = (value / 10) % 10;".to_string(),
+            "self.memory[self.address(2)] = value % 10;".to_string(),
+            "self.stored(3);".to_string(),
+            format!("self.program_counter = {:#05X};", next),
+        ],
+        (0xF, _, 0x5, 0x5) => {
+            let mut lines: Vec<String> = (0..=op_2)
+                .map(|offset| format!("self.memory[self.address({})] = self.register[{:#X}];", offset, offset))
+                .collect();
+            lines.push(format!("self.stored({});", op_2 + 1));
+            if quirks.load_store_increments_i {
+                lines.push(format!("self.index_register = (self.index_register + {}) & 0xFFF;", op_2 + 1));
+            }
+            lines.push(format!("self.program_counter = {:#05X};", next));
+            lines
+        },
+        (0xF, _, 0x6, 0x5) => {
+            let mut lines: Vec<String> = (0..=op_2)
+                .map(|offset| format!("self.register[{:#X}] = self.memory[self.address({})];", offset, offset))
+                .collect();
+            if quirks.load_store_increments_i {
+                lines.push(format!("self.index_register = (self.index_register + {}) & 0xFFF;", op_2 + 1));
+            }
+            lines
+        },
+        // reported by the caller once the block reaches it
+        (_, _, _, _) => Vec::new(),
+    }
+}
+
+// the machine state and the code shared by every recompiled rom, the
+// dispatcher and the blocks are appended inside the impl
+const MACHINE: &str = r#"pub struct Machine {
+    // storage
+    memory: [u8; 4096],
+    register: [u8; 16],
+    stack: [u16; 16],
+
+    // counters
+    program_counter: u16,
+    index_register: u16,
+    stack_pointer: u8,
+
+    // timers
+    sound_timer: u8,
+    delay_timer: u8,
+
+    // cleared once the rom stores over its own code
+    native: bool,
+
+    // hardware
+    pub display: Display,
+    pub keyboard: Keyboard,
+}
+
+impl Default for Machine {
+    fn default() -> Machine {
+        Machine::new()
+    }
+}
+
+impl Machine {
+    pub fn new() -> Machine {
+        let mut memory = [0; 4096];
+        memory[..FONT.len()].copy_from_slice(&FONT);
+        memory[0x200..0x200 + ROM.len()].copy_from_slice(&ROM);
+        Machine {
+            memory,
+            register: [0; 16],
+            stack: [0; 16],
+            program_counter: 0x200,
+            index_register: 0,
+            stack_pointer: 0,
+            sound_timer: 0,
+            delay_timer: 0,
+            native: true,
+            display: Display::new(),
+            keyboard: Keyboard::new(),
+        }
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.register
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn stack_pointer(&self) -> u8 {
+        self.stack_pointer
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8; 4096] {
+        &self.memory
+    }
+
+    // runs a 60Hz frame, the timers tick once after `instructions` instructions
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Error> {
+        let mut remaining = instructions;
+        while remaining > 0 {
+            remaining -= self.dispatch(remaining)?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    // executes a single instruction without touching the timers
+    pub fn step(&mut self) -> Result<(), Error> {
+        self.interpret()
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    // memory index `offset` bytes past I, wrapping at the end of memory
+    fn address(&self, offset: usize) -> usize {
+        (self.index_register as usize + offset) & 0xFFF
+    }
+
+    // leaves the recompiled blocks for the interpreter when the `length`
+    // bytes just stored at I hold code
+    fn stored(&mut self, length: usize) {
+        for offset in 0..length {
+            let address = self.address(offset);
+            if (CODE[address / 64] >> (address % 64)) & 1 == 1 {
+                self.native = false;
+            }
+        }
+    }
+
+    // decodes and runs the opcode at the program counter, for addresses the
+    // recompiler found no block at and code the rom rewrote
+    fn interpret(&mut self) -> Result<(), Error> {
+        let pc = self.program_counter;
+        let opcode = (self.memory[pc as usize] as u16) << 8 | self.memory[((pc + 1) & 0xFFF) as usize] as u16;
+
+        let nnn = opcode & 0x0FFF;
+        let nn = (opcode & 0x00FF) as u8;
+        let n = (opcode & 0x000F) as usize;
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        let vx = self.register[x];
+        let vy = self.register[y];
+        let mut next = (pc + 2) & 0xFFF;
+
+        match (opcode >> 12, x, y, n) {
+            (0x0, 0x0, 0xE, 0x0) => self.display.clear(),
+            (0x0, 0x0, 0xE, 0xE) => {
+                if self.stack_pointer == 0 {
+                    return Err(Error::StackUnderflow { pc });
+                }
+                self.stack_pointer -= 1;
+                next = self.stack[self.stack_pointer as usize];
+            },
+            (0x1, _, _, _) => next = nnn,
+            (0x2, _, _, _) => {
+                if self.stack_pointer as usize == self.stack.len() {
+                    return Err(Error::StackOverflow { pc });
+                }
+                self.stack[self.stack_pointer as usize] = next;
+                self.stack_pointer += 1;
+                next = nnn;
+            },
+            (0x3, _, _, _) => next += if vx == nn { 2 } else { 0 },
+            (0x4, _, _, _) => next += if vx != nn { 2 } else { 0 },
+            (0x5, _, _, 0x0) => next += if vx == vy { 2 } else { 0 },
+            (0x6, _, _, _) => self.register[x] = nn,
+            (0x7, _, _, _) => self.register[x] = vx.wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.register[x] = vy,
+            (0x8, _, _, 0x1) | (0x8, _, _, 0x2) | (0x8, _, _, 0x3) => {
+                self.register[x] = match n {
+                    0x1 => vx | vy,
+                    0x2 => vx & vy,
+                    _ => vx ^ vy,
+                };
+                if LOGIC_RESETS_VF {
+                    self.register[0xF] = 0;
+                }
+            },
+            (0x8, _, _, 0x4) => {
+                let (result, overflow) = vx.overflowing_add(vy);
+                self.register[0xF] = overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0x5) => {
+                let (result, overflow) = vx.overflowing_sub(vy);
+                self.register[0xF] = !overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0x6) => {
+                let source = if SHIFT_USES_VY { vy } else { vx };
+                self.register[0xF] = source & 0x1;
+                self.register[x] = source >> 1;
+            },
+            (0x8, _, _, 0x7) => {
+                let (result, overflow) = vy.overflowing_sub(vx);
+                self.register[0xF] = !overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0xE) => {
+                let source = if SHIFT_USES_VY { vy } else { vx };
+                self.register[0xF] = source >> 7;
+                self.register[x] = source << 1;
+            },
+            (0x9, _, _, 0x0) => next += if vx != vy { 2 } else { 0 },
+            (0xA, _, _, _) => self.index_register = nnn,
+            (0xB, _, _, _) => {
+                let offset = if JUMP_USES_VX { vx } else { self.register[0] };
+                next = nnn + offset as u16;
+            },
+            (0xC, _, _, _) => self.register[x] = nn & rand::random::<u8>(),
+            (0xD, _, _, _) => {
+                let mut sprite = [0; 15];
+                for (row, byte) in sprite.iter_mut().enumerate().take(n) {
+                    *byte = self.memory[self.address(row)];
+                }
+                let collision = if CLIP_SPRITES {
+                    self.display.draw_clipped(vx, vy, &sprite[..n])
+                } else {
+                    self.display.draw(vx, vy, &sprite[..n])
+                };
+                self.register[0xF] = collision as u8;
+            },
+            (0xE, _, 0x9, 0xE) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 2 } else { 0 },
+            (0xE, _, 0xA, 0x1) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 0 } else { 2 },
+            (0xF, _, 0x0, 0x7) => self.register[x] = self.delay_timer,
+            (0xF, _, 0x0, 0xA) => {
+                // stay on this opcode while no key is pressed
+                next = pc;
+                for key in 0..16 {
+                    if self.keyboard.pressed(key) {
+                        self.register[x] = key as u8;
+                        next = pc + 2;
+                    }
+                }
+            },
+            (0xF, _, 0x1, 0x5) => self.delay_timer = vx,
+            (0xF, _, 0x1, 0x8) => self.sound_timer = vx,
+            (0xF, _, 0x1, 0xE) => self.index_register = (self.index_register + vx as u16) & 0xFFF,
+            (0xF, _, 0x2, 0x9) => self.index_register = vx as u16 * 5,
+            (0xF, _, 0x3, 0x3) => {
+                self.memory[self.address(0)] = vx / 100;
+                self.memory[self.address(1)] = (vx / 10) % 10;
+                self.memory[self.address(2)] = vx % 10;
+                self.stored(3);
+            },
+            (0xF, _, 0x5, 0x5) => {
+                for offset in 0..=x {
+                    self.memory[self.address(offset)] = self.register[offset];
+                }
+                self.stored(x + 1);
+                if LOAD_STORE_INCREMENTS_I {
+                    self.index_register = (self.index_register + x as u16 + 1) & 0xFFF;
+                }
+            },
+            (0xF, _, 0x6, 0x5) => {
+                for offset in 0..=x {
+                    self.register[offset] = self.memory[self.address(offset)];
+                }
+                if LOAD_STORE_INCREMENTS_I {
+                    self.index_register = (self.index_register + x as u16 + 1) & 0xFFF;
+                }
+            },
+            (_, _, _, _) => return Err(Error::UnknownOpcode { opcode, pc }),
+        }
+
+        // jumps and skips also wrap at the end of memory
+        self.program_counter = next & 0xFFF;
+        Ok(())
+    }"#;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(program: &[u16]) -> [u8; 4096] {
+        let mut memory = [0; 4096];
+        for (index, opcode) in program.iter().enumerate() {
+            memory[PROGRAM_START + index * 2] = (opcode >> 8) as u8;
+            memory[PROGRAM_START + index * 2 + 1] = *opcode as u8;
+        }
+        memory
+    }
+
+    #[test]
+    fn blocks_follow_jumps() {
+        let memory = memory(&[0x6001, 0x1206, 0x0000, 0x7001, 0x1202]);
+        let blocks = analyze(&memory, 10);
+        let addresses: Vec<u16> = blocks.keys().copied().collect();
+        assert_eq!(addresses, vec![0x200, 0x202], "the loop back to 0x202 starts its own block");
+        assert_eq!(
+            blocks[&0x200].opcodes,
+            vec![(0x200, 0x6001), (0x202, 0x1206), (0x206, 0x7001), (0x208, 0x1202)],
+            "the jumps are followed"
+        );
+        assert_eq!(blocks[&0x200].next, Some(0x202), "the block ends where it loops");
+        assert_eq!(blocks[&0x202].next, Some(0x202), "a block looping to itself continues at its start");
+    }
+
+    #[test]
+    fn calls_skips_and_key_waits_add_entries() {
+        // 200 call 20a, 202 skip, 204 and 206 both reached, 206 waits for a key
+        let memory = memory(&[0x220A, 0x3000, 0x6101, 0xF00A, 0x1208, 0x00EE]);
+        let blocks = analyze(&memory, 12);
+        let addresses: Vec<u16> = blocks.keys().copied().collect();
+        assert_eq!(
+            addresses,
+            vec![0x200, 0x202, 0x204, 0x206, 0x208, 0x20A],
+            "targets, return addresses and both sides of the skip are entries"
+        );
+        assert_eq!(blocks[&0x204].opcodes.len(), 2, "the key wait ends its block");
+        assert_eq!(blocks[&0x208].next, Some(0x208), "the final jump loops on itself");
+    }
+
+    #[test]
+    fn computed_jumps_enter_every_even_offset_in_the_rom() {
+        // jump table at 204 with two entries
+        let memory = memory(&[0x6002, 0xB204, 0x120A, 0x120A, 0x00E0, 0x120A]);
+        let blocks = analyze(&memory, 12);
+        assert!(blocks.contains_key(&0x204) && blocks.contains_key(&0x206), "table entries are blocks");
+        assert!(blocks.contains_key(&0x208) && blocks.contains_key(&0x20A), "later rom addresses are entries too");
+        assert!(!blocks.contains_key(&0x20C), "addresses past the rom are left to the interpreter");
+    }
+
+    #[test]
+    fn generated_module_layout() {
+        let source = recompile(&[0x60, 0x05, 0xF0, 0x55, 0x12, 0x04], Quirks::chip8()).unwrap();
+        assert!(source.starts_with("// recompiled by `chip_8 recompile`"), "the header explains where the module came from");
+        assert!(source.contains("const SHIFT_USES_VY: bool = true;"), "quirks are baked in");
+        assert!(source.contains("static ROM: [u8; 6] = [\n    0x60, 0x05, 0xF0, 0x55, 0x12, 0x04,\n];"), "the rom is embedded");
+        assert!(source.contains("            0x200 if remaining >= 2 => self.block_200(),"), "the dispatcher matches block entries");
+        assert!(source.contains("        // 0x202 LD [I], V0\n        self.memory[self.address(0)] = self.register[0x0];"), "opcodes carry their disassembly");
+        assert!(source.contains("self.index_register = (self.index_register + 1) & 0xFFF;"), "the store increments I");
+        assert!(source.ends_with("    }\n}\n"), "the impl is closed");
+    }
+
+    #[test]
+    fn unknown_opcodes_fail_at_their_address() {
+        let lines = emit_block(&decode_block(&memory(&[0x6001, 0x0123]), 0x200), Quirks::default());
+        assert_eq!(
+            &lines[lines.len() - 2..],
+            ["self.program_counter = 0x202;", "Err(Error::UnknownOpcode { opcode: 0x0123, pc: 0x202 })"],
+            "the error leaves the program counter on the opcode"
+        );
+    }
+
+    #[test]
+    fn oversized_roms_fail() {
+        assert!(matches!(recompile(&[0; 4000], Quirks::default()), Err(Error::RomTooLarge { size: 4000 })), "the rom must fit in memory");
+    }
+}
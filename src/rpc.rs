@@ -0,0 +1,630 @@
+// json-rpc 2.0 server so test automation and other tools can drive a
+// headless machine without linking the crate. each request and response is
+// one json value on a line, over stdin and stdout or a local tcp connection:
+//
+//   {"jsonrpc": "2.0", "id": 1, "method": "load", "params": {"path": "roms/pong"}}
+//   {"jsonrpc": "2.0", "id": 1, "result": {"title": "Pong", "instructions_per_frame": 10}}
+//
+// params are named. bytes go both ways as hex strings, framebuffer rows too
+// with the leftmost pixel in the top bit. the methods are
+//
+//   load {path, patch} or {rom}     starts a rom with the database's settings,
+//                                   a rom read from a path gets `patch` or the
+//                                   patch beside it
+//   reset                           starts the loaded rom again
+//   run {frames = 1}                runs 60Hz frames, stopping early at breakpoints
+//   step                            runs one instruction without ticking the timers
+//   press {key}, release {key}
+//   seed {seed}                     makes CXNN repeat
+//   framebuffer                     {width, height, rows}
+//   read_memory {address, length}   {data}
+//   write_memory {address, data}
+//   registers                       {v, i, pc, sp, stack, delay, sound}
+//   set_register {name, value}      name is v0 - vf, i or pc
+//   set_breakpoint {address}, clear_breakpoint {address}, breakpoints
+//   save_state                      {state}
+//   load_state {state}
+//   search_start                    {candidates}, a ram search from all of memory
+//   search_filter {filter, value}   {candidates}, filter is equal, changed,
+//                                   increased, decreased or value
+//   search_results                  [{address, value}]
+//   cheats                          [{kind, address, value, enabled, description}]
+//   add_cheat {kind, address, value, description = ""}
+//                                   {index}, kind is freeze or patch
+//   remove_cheat {index}, enable_cheat {index, enabled}
+//   save_cheats                     writes the rom's cheat file
+//
+// with a cheat directory set, roms start with the cheats saved for them
+
+use crate::cheats::{Cheat, Cheats, Filter, Kind, Search};
+use crate::database::{Database, RomInfo};
+use crate::display::{HEIGHT, WIDTH};
+use crate::error::Error;
+use crate::patch;
+use crate::processor::{Backend, Processor, Snapshot, ADDRESS_MASK};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+// codes from the json-rpc spec, then one for errors of the rom or machine
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const MACHINE_ERROR: i64 = -32000;
+
+// why a request failed, sent back as its error object
+#[derive(Debug)]
+struct Failure {
+    code: i64,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: i64, message: &str) -> Failure {
+        Failure { code, message: message.to_string() }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Failure {
+        Failure { code: MACHINE_ERROR, message: error.to_string() }
+    }
+}
+
+// named params of a request
+struct Params<'a>(&'a Map<String, Value>);
+
+impl Params<'_> {
+    fn optional_number(&self, name: &str, max: u64) -> Result<Option<u64>, Failure> {
+        match self.0.get(name) {
+            None => Ok(None),
+            Some(value) => match value.as_u64() {
+                Some(number) if number <= max => Ok(Some(number)),
+                _ => Err(Failure { code: INVALID_PARAMS, message: format!("{} must be a number up to {}", name, max) }),
+            },
+        }
+    }
+
+    fn number(&self, name: &str, max: u64) -> Result<u64, Failure> {
+        self.optional_number(name, max)?.ok_or_else(|| Failure { code: INVALID_PARAMS, message: format!("{} is missing", name) })
+    }
+
+    fn text(&self, name: &str) -> Result<&str, Failure> {
+        self.0.get(name).and_then(Value::as_str).ok_or_else(|| Failure { code: INVALID_PARAMS, message: format!("{} must be a string", name) })
+    }
+
+    fn flag(&self, name: &str) -> Result<bool, Failure> {
+        self.0.get(name).and_then(Value::as_bool).ok_or_else(|| Failure { code: INVALID_PARAMS, message: format!("{} must be true or false", name) })
+    }
+
+    fn bytes(&self, name: &str) -> Result<Vec<u8>, Failure> {
+        decode_hex(self.text(name)?).ok_or_else(|| Failure { code: INVALID_PARAMS, message: format!("{} must be hex bytes", name) })
+    }
+}
+
+pub struct Server {
+    database: Database,
+    backend: Backend,
+    cpu: Processor,
+    // the running rom, started again by a reset
+    rom: Option<Vec<u8>>,
+    instructions_per_frame: usize,
+    breakpoints: BTreeSet<u16>,
+    // instructions run since the timers last ticked, a frame stopped at a
+    // breakpoint carries on from here
+    progress: usize,
+    search: Option<Search>,
+    cheats: Cheats,
+    // where cheat files are loaded from and saved to
+    cheat_dir: Option<PathBuf>,
+}
+
+impl Server {
+    // a server with no rom loaded, roms are looked up in `database`
+    pub fn new(database: Database, backend: Backend) -> Server {
+        let mut cpu = Processor::new();
+        cpu.reset();
+        Server {
+            database,
+            backend,
+            cpu,
+            rom: None,
+            instructions_per_frame: RomInfo::default().instructions_per_frame,
+            breakpoints: BTreeSet::new(),
+            progress: 0,
+            search: None,
+            cheats: Cheats::new(),
+            cheat_dir: None,
+        }
+    }
+
+    pub fn set_cheat_dir(&mut self, dir: PathBuf) {
+        self.cheat_dir = Some(dir);
+    }
+
+    // starts `rom` from scratch with its saved cheats, the running one keeps
+    // going if this fails
+    pub fn load(&mut self, rom: Vec<u8>) -> Result<RomInfo, Error> {
+        let cheats = match &self.cheat_dir {
+            Some(dir) => Cheats::load(dir, &rom)?,
+            None => Cheats::new(),
+        };
+        let info = self.start(&rom)?;
+        self.rom = Some(rom);
+        self.cheats = cheats;
+        self.search = None;
+        Ok(info)
+    }
+
+    fn start(&mut self, rom: &[u8]) -> Result<RomInfo, Error> {
+        let info = self.database.lookup(rom);
+        let mut cpu = Processor::new();
+        cpu.reset();
+        cpu.set_quirks(info.quirks);
+        cpu.set_backend(self.backend);
+        cpu.load_program(rom)?;
+        self.cpu = cpu;
+        self.instructions_per_frame = info.instructions_per_frame;
+        self.progress = 0;
+        Ok(info)
+    }
+
+    // answers the requests from `input` until it ends
+    pub fn serve<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    // serves the clients of `listener` one after another, the machine
+    // carries over from one to the next
+    pub fn listen(&mut self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let input = BufReader::new(stream.try_clone()?);
+            // a client going away only ends its own connection
+            let _ = self.serve(input, stream);
+        }
+        Ok(())
+    }
+
+    // the response to a line holding a request or a batch of them, nothing
+    // when it only held notifications
+    pub fn handle(&mut self, line: &str) -> Option<Value> {
+        match serde_json::from_str(line) {
+            Ok(Value::Array(batch)) if !batch.is_empty() => {
+                let responses: Vec<Value> = batch.iter().filter_map(|request| self.answer(request)).collect();
+                if responses.is_empty() {
+                    None
+                } else {
+                    Some(Value::Array(responses))
+                }
+            },
+            Ok(request) => self.answer(&request),
+            Err(e) => Some(response(Value::Null, Err(Failure { code: PARSE_ERROR, message: e.to_string() }))),
+        }
+    }
+
+    fn answer(&mut self, request: &Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let no_params = Map::new();
+        let call = match (&request["jsonrpc"], request["method"].as_str(), request.get("params")) {
+            (Value::String(version), Some(method), None) if version == "2.0" => Ok((method, Params(&no_params))),
+            (Value::String(version), Some(method), Some(Value::Object(params))) if version == "2.0" => Ok((method, Params(params))),
+            (Value::String(version), Some(_), Some(_)) if version == "2.0" => Err(Failure::new(INVALID_PARAMS, "params must be named")),
+            _ => Err(Failure::new(INVALID_REQUEST, "not a json-rpc 2.0 request")),
+        };
+        let result = call.and_then(|(method, params)| self.call(method, &params));
+        match (id, result) {
+            (Some(id), result) => Some(response(id, result)),
+            // requests too broken to tell from notifications are still answered
+            (None, Err(failure)) if failure.code == INVALID_REQUEST => Some(response(Value::Null, Err(failure))),
+            // notifications get no response
+            (None, _) => None,
+        }
+    }
+
+    fn call(&mut self, method: &str, params: &Params) -> Result<Value, Failure> {
+        match method {
+            "load" => {
+                let rom = match params.0.get("path") {
+                    Some(_) => {
+                        let patch = match params.0.get("patch") {
+                            Some(_) => Some(Path::new(params.text("patch")?)),
+                            None => None,
+                        };
+                        patch::read_rom(Path::new(params.text("path")?), patch)?
+                    },
+                    None => params.bytes("rom")?,
+                };
+                let info = self.load(rom)?;
+                Ok(json!({ "title": info.title, "instructions_per_frame": info.instructions_per_frame }))
+            },
+            "reset" => match self.rom.clone() {
+                Some(rom) => {
+                    self.start(&rom)?;
+                    self.cheats.restart();
+                    Ok(Value::Null)
+                },
+                None => Err(Failure::new(MACHINE_ERROR, "no rom is loaded")),
+            },
+            "run" => {
+                let frames = params.optional_number("frames", u64::MAX)?.unwrap_or(1);
+                self.run(frames)
+            },
+            "step" => {
+                self.cpu.step()?;
+                Ok(json!({ "pc": self.cpu.program_counter() }))
+            },
+            "press" => {
+                self.cpu.keyboard.key_press(params.number("key", 0xF)? as u8);
+                Ok(Value::Null)
+            },
+            "release" => {
+                self.cpu.keyboard.key_release(params.number("key", 0xF)? as u8);
+                Ok(Value::Null)
+            },
+            "seed" => {
+                self.cpu.set_seed(params.number("seed", u64::MAX)?);
+                Ok(Value::Null)
+            },
+            "framebuffer" => Ok(json!({ "width": WIDTH, "height": HEIGHT, "rows": self.rows() })),
+            "read_memory" => {
+                let address = params.number("address", ADDRESS_MASK as u64)? as usize;
+                let length = params.number("length", self.cpu.memory().len() as u64)? as usize;
+                let memory = self.cpu.memory();
+                let data: Vec<u8> = (0..length).map(|offset| memory[(address + offset) & ADDRESS_MASK as usize]).collect();
+                Ok(json!({ "data": encode_hex(&data) }))
+            },
+            "write_memory" => {
+                let address = params.number("address", ADDRESS_MASK as u64)? as u16;
+                self.cpu.write_memory(address, &params.bytes("data")?);
+                Ok(Value::Null)
+            },
+            "registers" => Ok(json!({
+                "v": self.cpu.registers(),
+                "i": self.cpu.index_register(),
+                "pc": self.cpu.program_counter(),
+                "sp": self.cpu.stack_pointer(),
+                "stack": self.cpu.call_stack(),
+                "delay": self.cpu.delay_timer(),
+                "sound": self.cpu.sound_timer(),
+            })),
+            "set_register" => {
+                let name = params.text("name")?.to_ascii_lowercase();
+                match name.as_str() {
+                    "i" => self.cpu.set_index_register(params.number("value", ADDRESS_MASK as u64)? as u16),
+                    "pc" => self.cpu.set_program_counter(params.number("value", ADDRESS_MASK as u64)? as u16),
+                    _ => match name.strip_prefix('v').filter(|index| index.len() == 1).and_then(|index| usize::from_str_radix(index, 16).ok()) {
+                        Some(index) => self.cpu.set_register(index, params.number("value", 0xFF)? as u8),
+                        None => return Err(Failure { code: INVALID_PARAMS, message: format!("{} is not a register", name) }),
+                    },
+                }
+                Ok(Value::Null)
+            },
+            "set_breakpoint" => {
+                self.breakpoints.insert(params.number("address", ADDRESS_MASK as u64)? as u16);
+                Ok(Value::Null)
+            },
+            "clear_breakpoint" => {
+                self.breakpoints.remove(&(params.number("address", ADDRESS_MASK as u64)? as u16));
+                Ok(Value::Null)
+            },
+            "breakpoints" => Ok(json!(self.breakpoints)),
+            "save_state" => Ok(json!({ "state": encode_hex(&self.cpu.snapshot().to_bytes()) })),
+            "load_state" => {
+                let snapshot = Snapshot::from_bytes(&params.bytes("state")?)?;
+                self.cpu.restore(&snapshot);
+                self.progress = 0;
+                Ok(Value::Null)
+            },
+            "search_start" => {
+                let search = Search::new(&self.cpu);
+                let candidates = search.candidates().len();
+                self.search = Some(search);
+                Ok(json!({ "candidates": candidates }))
+            },
+            "search_filter" => {
+                let filter = match params.text("filter")? {
+                    "equal" => Filter::Equal,
+                    "changed" => Filter::Changed,
+                    "increased" => Filter::Increased,
+                    "decreased" => Filter::Decreased,
+                    "value" => Filter::Value(params.number("value", 0xFF)? as u8),
+                    other => return Err(Failure { code: INVALID_PARAMS, message: format!("{} is not a filter", other) }),
+                };
+                let search = self.search.as_mut().ok_or_else(|| Failure::new(MACHINE_ERROR, "no search is running"))?;
+                search.filter(&self.cpu, filter);
+                Ok(json!({ "candidates": search.candidates().len() }))
+            },
+            "search_results" => match &self.search {
+                Some(search) => Ok(search.candidates().iter().map(|(address, value)| json!({ "address": address, "value": value })).collect()),
+                None => Err(Failure::new(MACHINE_ERROR, "no search is running")),
+            },
+            "cheats" => Ok(self.cheats.cheats().iter().map(|cheat| json!({
+                "kind": match cheat.kind {
+                    Kind::Freeze => "freeze",
+                    Kind::Patch => "patch",
+                },
+                "address": cheat.address,
+                "value": cheat.value,
+                "enabled": cheat.enabled,
+                "description": cheat.description,
+            })).collect()),
+            "add_cheat" => {
+                let kind = match params.text("kind")? {
+                    "freeze" => Kind::Freeze,
+                    "patch" => Kind::Patch,
+                    other => return Err(Failure { code: INVALID_PARAMS, message: format!("{} is not a kind of cheat", other) }),
+                };
+                let address = params.number("address", ADDRESS_MASK as u64)? as u16;
+                let value = params.number("value", 0xFF)? as u8;
+                let description = match params.0.get("description") {
+                    Some(_) => params.text("description")?,
+                    None => "",
+                };
+                self.cheats.add(Cheat::new(kind, address, value, description));
+                Ok(json!({ "index": self.cheats.cheats().len() - 1 }))
+            },
+            "remove_cheat" => match self.cheats.remove(params.number("index", u64::MAX)? as usize) {
+                Some(_) => Ok(Value::Null),
+                None => Err(Failure::new(INVALID_PARAMS, "no cheat has that index")),
+            },
+            "enable_cheat" => {
+                let index = params.number("index", u64::MAX)? as usize;
+                if self.cheats.set_enabled(index, params.flag("enabled")?) {
+                    Ok(Value::Null)
+                } else {
+                    Err(Failure::new(INVALID_PARAMS, "no cheat has that index"))
+                }
+            },
+            "save_cheats" => match (&self.cheat_dir, &self.rom) {
+                (Some(dir), Some(rom)) => {
+                    self.cheats.save(dir, rom)?;
+                    Ok(Value::Null)
+                },
+                (None, _) => Err(Failure::new(MACHINE_ERROR, "no cheat directory is set")),
+                (_, None) => Err(Failure::new(MACHINE_ERROR, "no rom is loaded")),
+            },
+            _ => Err(Failure { code: METHOD_NOT_FOUND, message: format!("no method {}", method) }),
+        }
+    }
+
+    // runs `frames` frames, a run starting on a breakpoint goes past it. the
+    // result says how many frames finished and the breakpoint hit, if any
+    fn run(&mut self, frames: u64) -> Result<Value, Failure> {
+        let mut first = true;
+        for frame in 0..frames {
+            if self.progress == 0 {
+                self.cheats.apply(&mut self.cpu);
+            }
+            if self.breakpoints.is_empty() && self.progress == 0 {
+                self.cpu.run_frame(self.instructions_per_frame)?;
+                continue;
+            }
+            while self.progress < self.instructions_per_frame {
+                let pc = self.cpu.program_counter();
+                if !first && self.breakpoints.contains(&pc) {
+                    return Ok(json!({ "frames": frame, "breakpoint": pc }));
+                }
+                first = false;
+                self.cpu.step()?;
+                self.progress += 1;
+            }
+            self.cpu.tick_timers();
+            self.progress = 0;
+        }
+        Ok(json!({ "frames": frames, "breakpoint": null }))
+    }
+
+    fn rows(&self) -> Vec<String> {
+        (0..HEIGHT).map(|y| {
+            let mut row = [0u8; WIDTH / 8];
+            for x in (0..WIDTH).filter(|x| self.cpu.display.pixel(*x, y)) {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+            encode_hex(&row)
+        }).collect()
+    }
+}
+
+fn response(id: Value, result: Result<Value, Failure>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(failure) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": failure.code, "message": failure.message } }),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok()).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    const PONG: &[u8] = include_bytes!("../roms/pong");
+
+    fn server() -> Server {
+        let mut server = Server::new(Database::bundled(), Backend::default());
+        server.load(PONG.to_vec()).unwrap();
+        server
+    }
+
+    // the result of calling `method`, panicking on an error
+    fn call(server: &mut Server, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+        let response = server.handle(&request.to_string()).unwrap();
+        assert_eq!(response["id"], 7, "responses carry the request's id");
+        assert!(response.get("error").is_none(), "{} failed: {}", method, response);
+        response["result"].clone()
+    }
+
+    // the error code of calling `method`
+    fn error(server: &mut Server, method: &str, params: Value) -> i64 {
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+        server.handle(&request.to_string()).unwrap()["error"]["code"].as_i64().unwrap()
+    }
+
+    #[test]
+    fn roms_run_and_draw() {
+        let mut server = Server::new(Database::bundled(), Backend::default());
+        let loaded = call(&mut server, "load", json!({ "rom": encode_hex(PONG) }));
+        assert_eq!(loaded["instructions_per_frame"], Database::bundled().lookup(PONG).instructions_per_frame);
+
+        let blank = call(&mut server, "framebuffer", json!({}));
+        assert_eq!((blank["width"].as_u64(), blank["rows"].as_array().unwrap().len()), (Some(64), 32));
+        assert_eq!(call(&mut server, "run", json!({ "frames": 10 })), json!({ "frames": 10, "breakpoint": null }));
+        let drawn = call(&mut server, "framebuffer", json!({}));
+        assert!(drawn != blank, "pong draws paddles");
+
+        call(&mut server, "reset", json!({}));
+        assert_eq!(call(&mut server, "framebuffer", json!({})), blank, "a reset starts the rom again");
+        assert_eq!(call(&mut server, "registers", json!({}))["pc"], 0x200);
+    }
+
+    #[test]
+    fn memory_and_registers_are_read_and_written() {
+        let mut server = server();
+        call(&mut server, "write_memory", json!({ "address": 0x300, "data": "c0ffee" }));
+        assert_eq!(call(&mut server, "read_memory", json!({ "address": 0x300, "length": 3 })), json!({ "data": "c0ffee" }));
+        assert_eq!(call(&mut server, "read_memory", json!({ "address": 0xFFF, "length": 2 }))["data"].as_str().map(str::len), Some(4), "reads wrap at the end of memory");
+
+        call(&mut server, "set_register", json!({ "name": "vA", "value": 0x42 }));
+        call(&mut server, "set_register", json!({ "name": "i", "value": 0x300 }));
+        call(&mut server, "set_register", json!({ "name": "pc", "value": 0x202 }));
+        let registers = call(&mut server, "registers", json!({}));
+        assert_eq!((registers["v"][0xA].as_u64(), registers["i"].as_u64(), registers["pc"].as_u64()), (Some(0x42), Some(0x300), Some(0x202)));
+
+        assert_eq!(error(&mut server, "set_register", json!({ "name": "vg", "value": 1 })), INVALID_PARAMS);
+        assert_eq!(error(&mut server, "set_register", json!({ "name": "v0", "value": 256 })), INVALID_PARAMS);
+        assert_eq!(error(&mut server, "write_memory", json!({ "address": 0x300, "data": "xyz" })), INVALID_PARAMS);
+        assert_eq!(error(&mut server, "press", json!({ "key": 16 })), INVALID_PARAMS);
+    }
+
+    #[test]
+    fn breakpoints_stop_a_run() {
+        let mut server = server();
+        let target = 0x202;
+        call(&mut server, "set_breakpoint", json!({ "address": target }));
+        assert_eq!(call(&mut server, "breakpoints", json!({})), json!([target]));
+
+        let stopped = call(&mut server, "run", json!({ "frames": 5 }));
+        assert_eq!(stopped, json!({ "frames": 0, "breakpoint": target }));
+        assert_eq!(call(&mut server, "registers", json!({}))["pc"], target, "the processor stops on the breakpoint");
+
+        // the next run goes past it and finishes the frame it stopped in
+        call(&mut server, "clear_breakpoint", json!({ "address": target }));
+        assert_eq!(call(&mut server, "run", json!({})), json!({ "frames": 1, "breakpoint": null }));
+        assert_eq!(call(&mut server, "step", json!({}))["pc"].as_u64().map(|pc| pc > 0x200), Some(true));
+    }
+
+    #[test]
+    fn states_round_trip() {
+        let mut server = server();
+        call(&mut server, "seed", json!({ "seed": 5 }));
+        call(&mut server, "run", json!({ "frames": 30 }));
+        let state = call(&mut server, "save_state", json!({}))["state"].clone();
+        call(&mut server, "press", json!({ "key": 1 }));
+        call(&mut server, "run", json!({ "frames": 60 }));
+        let later = call(&mut server, "framebuffer", json!({}));
+
+        call(&mut server, "load_state", json!({ "state": state }));
+        call(&mut server, "run", json!({ "frames": 60 }));
+        assert_eq!(call(&mut server, "framebuffer", json!({})), later, "the game plays the same from the state");
+        assert_eq!(error(&mut server, "load_state", json!({ "state": "00" })), MACHINE_ERROR, "damaged states are refused");
+    }
+
+    #[test]
+    fn searches_find_what_cheats_freeze() {
+        let mut server = server();
+        let dir = std::env::temp_dir().join(format!("chip_8_rpc_cheats_{}", std::process::id()));
+        server.set_cheat_dir(dir.clone());
+        assert_eq!(error(&mut server, "search_filter", json!({ "filter": "equal" })), MACHINE_ERROR, "filters need a search");
+
+        assert_eq!(call(&mut server, "search_start", json!({})), json!({ "candidates": 4096 }));
+        call(&mut server, "write_memory", json!({ "address": 0x300, "data": "05" }));
+        call(&mut server, "search_filter", json!({ "filter": "increased" }));
+        let found = call(&mut server, "search_filter", json!({ "filter": "value", "value": 5 }));
+        assert_eq!(found, json!({ "candidates": 1 }));
+        assert_eq!(call(&mut server, "search_results", json!({})), json!([{ "address": 0x300, "value": 5 }]));
+        assert_eq!(error(&mut server, "search_filter", json!({ "filter": "bigger" })), INVALID_PARAMS);
+
+        let added = call(&mut server, "add_cheat", json!({ "kind": "freeze", "address": 0x300, "value": 9, "description": "lives" }));
+        assert_eq!(added, json!({ "index": 0 }));
+        call(&mut server, "write_memory", json!({ "address": 0x300, "data": "01" }));
+        call(&mut server, "run", json!({}));
+        assert_eq!(call(&mut server, "read_memory", json!({ "address": 0x300, "length": 1 })), json!({ "data": "09" }), "frozen bytes are written back each frame");
+
+        call(&mut server, "enable_cheat", json!({ "index": 0, "enabled": false }));
+        call(&mut server, "save_cheats", json!({}));
+        call(&mut server, "remove_cheat", json!({ "index": 0 }));
+        assert_eq!(error(&mut server, "remove_cheat", json!({ "index": 0 })), INVALID_PARAMS);
+        call(&mut server, "load", json!({ "rom": encode_hex(PONG) }));
+        let cheats = call(&mut server, "cheats", json!({}));
+        assert_eq!(cheats, json!([{ "kind": "freeze", "address": 0x300, "value": 9, "enabled": false, "description": "lives" }]), "roms start with their saved cheats");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn protocol_errors_follow_the_spec() {
+        let mut server = server();
+        assert_eq!(server.handle("{").unwrap()["error"]["code"], PARSE_ERROR);
+        assert_eq!(server.handle(r#"{"id": 1, "method": "run"}"#).unwrap()["error"]["code"], INVALID_REQUEST);
+        assert_eq!(server.handle("[]").unwrap()["error"]["code"], INVALID_REQUEST);
+        assert_eq!(server.handle(r#"{"jsonrpc": "2.0", "id": 1, "method": "run", "params": [1]}"#).unwrap()["error"]["code"], INVALID_PARAMS);
+        assert_eq!(error(&mut server, "fly", json!({})), METHOD_NOT_FOUND);
+        assert_eq!(error(&mut server, "load", json!({ "path": "roms/missing" })), MACHINE_ERROR);
+
+        assert!(server.handle(r#"{"jsonrpc": "2.0", "method": "press", "params": {"key": 1}}"#).is_none(), "notifications get no response");
+        let batch = server.handle(r#"[{"jsonrpc": "2.0", "id": 1, "method": "breakpoints"}, {"jsonrpc": "2.0", "method": "reset"}, {"jsonrpc": "2.0", "id": 2, "method": "fly"}]"#).unwrap();
+        let ids: Vec<&Value> = batch.as_array().unwrap().iter().map(|response| &response["id"]).collect();
+        assert_eq!(ids, [1, 2], "batches are answered in order without the notifications");
+    }
+
+    #[test]
+    fn clients_connect_over_tcp() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        // the processor stays on the thread it was made on
+        std::thread::spawn(move || Server::new(Database::bundled(), Backend::default()).listen(listener));
+
+        let request = |requests: &str| {
+            let mut client = TcpStream::connect(address).unwrap();
+            client.write_all(requests.as_bytes()).unwrap();
+            client.shutdown(std::net::Shutdown::Write).unwrap();
+            let lines: Vec<Value> = BufReader::new(client).lines().map(|line| serde_json::from_str(&line.unwrap()).unwrap()).collect();
+            lines
+        };
+        let responses = request(&format!(
+            "{}\n\n{}\n",
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "load", "params": { "rom": encode_hex(PONG) } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "run", "params": { "frames": 3 } }),
+        ));
+        assert_eq!(responses.len(), 2, "one response a line");
+        assert_eq!(responses[1]["result"]["frames"], 3);
+
+        let responses = request(&format!("{}\n", json!({ "jsonrpc": "2.0", "id": 3, "method": "registers" })));
+        assert!(responses[0]["result"]["pc"] != 0x200, "the machine carries over to the next client");
+    }
+}
@@ -0,0 +1,64 @@
+// the game screen kept in a texture, only pixels the display reports as
+// changed are uploaded again
+
+use chip_8::display::{self, Display, Region};
+use piston_window::texture::{CreateTexture, Format, UpdateTexture};
+use piston_window::*;
+
+pub struct Screen {
+    texture: G2dTexture,
+    pub texture_context: G2dTextureContext,
+    // background and foreground rgb
+    colors: [[u8; 3]; 2],
+    // set when the whole texture has to be uploaded again
+    stale: bool,
+}
+
+impl Screen {
+    pub fn new(window: &mut PistonWindow, colors: [[u8; 3]; 2]) -> Screen {
+        let mut texture_context = window.create_texture_context();
+        let blank = vec![0; display::WIDTH * display::HEIGHT * 4];
+        let settings = TextureSettings::new().filter(Filter::Nearest);
+        let texture = CreateTexture::create(
+            &mut texture_context, Format::Rgba8, &blank, [display::WIDTH as u32, display::HEIGHT as u32], &settings)
+            .expect("screen texture");
+        Screen { texture, texture_context, colors, stale: true }
+    }
+
+    pub fn set_colors(&mut self, colors: [[u8; 3]; 2]) {
+        self.colors = colors;
+        self.stale = true;
+    }
+
+    // uploads whatever changed on the display since the last sync
+    pub fn sync(&mut self, display: &mut Display) {
+        let region = if self.stale {
+            Some(Region { x: 0, y: 0, width: display::WIDTH, height: display::HEIGHT })
+        } else {
+            display.dirty_region()
+        };
+
+        if let Some(region) = region {
+            let mut pixels = Vec::with_capacity(region.width * region.height * 4);
+            for y in region.y..region.y + region.height {
+                for x in region.x..region.x + region.width {
+                    pixels.extend_from_slice(&self.colors[display.pixel(x, y) as usize]);
+                    pixels.push(0xFF);
+                }
+            }
+
+            let offset = [region.x as u32, region.y as u32];
+            let size = [region.width as u32, region.height as u32];
+            if let Err(e) = UpdateTexture::update(&mut self.texture, &mut self.texture_context, Format::Rgba8, &pixels, offset, size) {
+                eprintln!("could not update the screen: {:?}", e);
+            }
+        }
+
+        display.mark_clean();
+        self.stale = false;
+    }
+
+    pub fn draw(&self, scale: usize, context: &Context, graphics: &mut G2d) {
+        image(&self.texture, context.transform.scale(scale as f64, scale as f64), graphics);
+    }
+}
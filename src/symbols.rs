@@ -0,0 +1,196 @@
+// symbols an assembler wrote for a rom, read from text files of one symbol
+// per line with the address in hex first:
+//
+//   200 main          a label
+//   200 pong.8o:12    the source line an opcode came from
+//
+// relative paths are taken from the symbol file's own directory
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Location {
+    // index into `files`
+    file: usize,
+    line: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Symbols {
+    labels: HashMap<String, u16>,
+    // the first label given for each address
+    names: BTreeMap<u16, String>,
+    files: Vec<PathBuf>,
+    locations: BTreeMap<u16, Location>,
+}
+
+impl Symbols {
+    pub fn new() -> Symbols {
+        Symbols::default()
+    }
+
+    pub fn parse(text: &str, directory: &Path) -> Result<Symbols, Error> {
+        let mut symbols = Symbols::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            let error = |message: &str| Error::Symbols { line: index + 1, message: message.to_string() };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (address, symbol) = line.split_once(char::is_whitespace).ok_or_else(|| error("expected ADDRESS LABEL or ADDRESS FILE:LINE"))?;
+            let address = u16::from_str_radix(address.trim_start_matches("0x"), 16)
+                .ok()
+                .filter(|address| *address < 0x1000)
+                .ok_or_else(|| error("address is not in memory"))?;
+            let symbol = symbol.trim();
+
+            // paths may hold colons of their own, the line number is after the last
+            if let Some((file, number)) = symbol.rsplit_once(':') {
+                let number = number.parse().ok().filter(|number| *number > 0).ok_or_else(|| error("line is not a number"))?;
+                let path = directory.join(file);
+                let file = match symbols.files.iter().position(|known| *known == path) {
+                    Some(file) => file,
+                    None => {
+                        symbols.files.push(path);
+                        symbols.files.len() - 1
+                    },
+                };
+                symbols.locations.insert(address, Location { file, line: number });
+                continue;
+            }
+
+            if !symbol.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') || symbol.starts_with(|c: char| c.is_ascii_digit()) {
+                return Err(error("label is not a name"));
+            }
+            if symbols.labels.insert(symbol.to_string(), address).is_some() {
+                return Err(error("label is defined twice"));
+            }
+            symbols.names.entry(address).or_insert_with(|| symbol.to_string());
+        }
+        Ok(symbols)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Symbols, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|error| Error::File { path: path.to_path_buf(), error })?;
+        Symbols::parse(&text, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    // the label right at `address`
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    // `address` as an offset from the nearest label at or before it, `draw+4`
+    pub fn describe(&self, address: u16) -> Option<String> {
+        match self.names.range(..=address).next_back()? {
+            (start, name) if *start == address => Some(name.clone()),
+            (start, name) => Some(format!("{}+{}", name, address - start)),
+        }
+    }
+
+    // source file and 1 based line of the opcode at `address`
+    pub fn location(&self, address: u16) -> Option<(&Path, usize)> {
+        self.locations.get(&address).map(|location| (self.files[location.file].as_path(), location.line))
+    }
+
+    // the first address of `line` in `file`, or of the next line with code
+    // after it, along with the line it belongs to
+    pub fn address(&self, file: &Path, line: usize) -> Option<(u16, usize)> {
+        let file = self.files.iter().position(|known| known == file || file.ends_with(known))?;
+        self.locations
+            .iter()
+            .filter(|(_, location)| location.file == file && location.line >= line)
+            .min_by_key(|(address, location)| (location.line, **address))
+            .map(|(address, location)| (*address, location.line))
+    }
+
+    // `error` followed by where in the source it happened, when known
+    pub fn explain(&self, error: &Error) -> String {
+        let mut text = error.to_string();
+        if let Some(address) = error.pc() {
+            if let Some(name) = self.describe(address) {
+                text += &format!(" in {}", name);
+            }
+            if let Some((file, line)) = self.location(address) {
+                text += &format!(" at {}:{}", file.display(), line);
+            }
+        }
+        text
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYMBOLS: &str = "# pong\n200 main\n200 pong.8o:3\n202 pong.8o:3\n0x204 pong.8o:5\n\n2a0 font\n2a0 lib/font.8o:1\n";
+
+    #[test]
+    fn parse_symbols() {
+        let symbols = Symbols::parse(SYMBOLS, Path::new("/games")).unwrap();
+        assert_eq!(symbols.location(0x202), Some((Path::new("/games/pong.8o"), 3)), "addresses find their line");
+        assert_eq!(symbols.location(0x2A0), Some((Path::new("/games/lib/font.8o"), 1)), "paths are joined to the directory");
+        assert_eq!(symbols.location(0x206), None, "unmapped addresses have no line");
+        assert_eq!((symbols.label("font"), symbols.name(0x200)), (Some(0x2A0), Some("main")), "labels go both ways");
+    }
+
+    #[test]
+    fn addresses_are_described_by_labels() {
+        let symbols = Symbols::parse(SYMBOLS, Path::new("/games")).unwrap();
+        assert_eq!(symbols.describe(0x200).as_deref(), Some("main"), "a label names its address");
+        assert_eq!(symbols.describe(0x206).as_deref(), Some("main+6"), "later addresses are offsets from it");
+        assert_eq!(symbols.describe(0x1FE), None, "addresses before any label have no name");
+    }
+
+    #[test]
+    fn lines_resolve_to_addresses() {
+        let symbols = Symbols::parse(SYMBOLS, Path::new("/games")).unwrap();
+        let pong = Path::new("/games/pong.8o");
+        assert_eq!(symbols.address(pong, 3), Some((0x200, 3)), "a line starts at its lowest address");
+        assert_eq!(symbols.address(pong, 4), Some((0x204, 5)), "lines without code move to the next one");
+        assert_eq!(symbols.address(pong, 6), None, "nothing follows the last line");
+        assert_eq!(symbols.address(Path::new("/other/pong.8o"), 3), None, "other files do not match");
+    }
+
+    #[test]
+    fn errors_point_to_the_source() {
+        let symbols = Symbols::parse(SYMBOLS, Path::new("/games")).unwrap();
+        let error = Error::StackUnderflow { pc: 0x202 };
+        assert_eq!(symbols.explain(&error), "stack underflow: pc: 202 in main+2 at /games/pong.8o:3", "the line and label are added");
+        let error = Error::StackUnderflow { pc: 0x206 };
+        assert_eq!(symbols.explain(&error), "stack underflow: pc: 206 in main+6", "the label is added without a line");
+        assert_eq!(Symbols::new().explain(&error), "stack underflow: pc: 206", "errors stay as they are without symbols");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let missing = Path::new("/nonexistent/pong.sym");
+        assert!(matches!(Symbols::load(missing), Err(Error::File { path, .. }) if path == missing), "unreadable files are named");
+
+        let result = Symbols::parse("200 pong.8o:1\n200\n", Path::new(""));
+        assert!(matches!(result, Err(Error::Symbols { line: 2, .. })), "a missing symbol is reported");
+
+        let result = Symbols::parse("1000 pong.8o:1\n", Path::new(""));
+        assert!(matches!(result, Err(Error::Symbols { line: 1, .. })), "addresses past memory are reported");
+
+        let result = Symbols::parse("200 pong.8o:0\n", Path::new(""));
+        assert!(matches!(result, Err(Error::Symbols { line: 1, .. })), "lines count from 1");
+
+        let result = Symbols::parse("200 main\n202 main\n", Path::new(""));
+        assert!(matches!(result, Err(Error::Symbols { line: 2, .. })), "labels are unique");
+
+        let result = Symbols::parse("200 9lives\n", Path::new(""));
+        assert!(matches!(result, Err(Error::Symbols { line: 1, .. })), "labels are names");
+    }
+}
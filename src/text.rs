@@ -0,0 +1,69 @@
+// bitmap text rendering for the frontend panels
+
+use piston_window::*;
+
+// each glyph pixel is drawn as a GLYPH_SCALE square
+const GLYPH_SCALE: usize = 2;
+const CHAR_WIDTH: usize = 6 * GLYPH_SCALE;
+pub const LINE_HEIGHT: usize = 8 * GLYPH_SCALE;
+const MARGIN: usize = 10;
+
+const BACKGROUND: [f32; 4] = [0.1, 0.1, 0.15, 1.0];
+const TEXT: [f32; 4] = [0.8, 0.9, 0.8, 1.0];
+
+// fills `area` ([x, y, width, height]) and writes `lines` into it top down
+pub fn draw_panel<G: Graphics>(lines: &[String], area: [usize; 4], context: &Context, graphics: &mut G) {
+    let [left, top, width, height] = area;
+    Rectangle::new(BACKGROUND)
+        .draw([left as f64, top as f64, width as f64, height as f64], &context.draw_state, context.transform, graphics);
+
+    let pixel = Rectangle::new(TEXT);
+    for (line_number, line) in lines.iter().enumerate() {
+        let y = top + MARGIN + line_number * LINE_HEIGHT;
+        for (char_number, character) in line.chars().enumerate() {
+            let x = left + MARGIN + char_number * CHAR_WIDTH;
+            for (column, bits) in glyph(character).iter().enumerate() {
+                for row in 0..7 {
+                    if (bits >> row) & 1 == 1 {
+                        let dimensions = [
+                            (x + column * GLYPH_SCALE) as f64,
+                            (y + row * GLYPH_SCALE) as f64,
+                            GLYPH_SCALE as f64,
+                            GLYPH_SCALE as f64,
+                        ];
+                        pixel.draw(dimensions, &context.draw_state, context.transform, graphics);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn glyph(character: char) -> [u8; 5] {
+    let code = character.to_ascii_uppercase() as usize;
+    if (0x20..0x60).contains(&code) {
+        GLYPHS[code - 0x20]
+    } else {
+        GLYPHS[b'?' as usize - 0x20]
+    }
+}
+
+// 5x7 font for ascii 0x20 - 0x5F, one byte per column with the top row in bit 0
+static GLYPHS: [[u8; 5]; 64] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00], [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62], [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00], [0x00, 0x41, 0x22, 0x1C, 0x00], [0x08, 0x2A, 0x1C, 0x2A, 0x08], [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00], [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00], [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10], [0x27, 0x45, 0x45, 0x45, 0x39], [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E], [0x00, 0x36, 0x36, 0x00, 0x00], [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14], [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E], [0x7E, 0x11, 0x11, 0x11, 0x7E], [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41], [0x7F, 0x09, 0x09, 0x01, 0x01], [0x3E, 0x41, 0x41, 0x51, 0x32],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00], [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40], [0x7F, 0x02, 0x04, 0x02, 0x7F], [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E], [0x7F, 0x09, 0x19, 0x29, 0x46], [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F], [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x7F, 0x20, 0x18, 0x20, 0x7F],
+    [0x63, 0x14, 0x08, 0x14, 0x63], [0x03, 0x04, 0x78, 0x04, 0x03], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04], [0x40, 0x40, 0x40, 0x40, 0x40],
+];
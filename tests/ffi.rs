@@ -0,0 +1,13 @@
+// include/chip8.h is generated from src/ffi.rs, this keeps it in step
+
+fn header() -> String {
+    let config = cbindgen::Config::from_file("cbindgen.toml").unwrap();
+    let mut header = Vec::new();
+    cbindgen::Builder::new().with_config(config).with_src("src/ffi.rs").generate().unwrap().write(&mut header);
+    String::from_utf8(header).unwrap()
+}
+
+#[test]
+fn header_is_current() {
+    assert!(header() == include_str!("../include/chip8.h"), "include/chip8.h is regenerated");
+}
@@ -0,0 +1,238 @@
+// a small libretro frontend: loads the core from the shared library built
+// alongside these tests and drives it through its entry points the way
+// retroarch would
+#![cfg(feature = "libretro")]
+
+use std::os::raw::{c_char, c_uint, c_void};
+use std::sync::Mutex;
+
+use chip_8::database::Database;
+use chip_8::libretro::{GameInfo, SystemAvInfo, SystemInfo};
+use libloading::{Library, Symbol};
+
+const PONG: &[u8] = include_bytes!("../roms/pong");
+// sets the sound timer then loops
+const BUZZER: &[u8] = &[0x60, 0x10, 0xF0, 0x18, 0x12, 0x04];
+
+// what the core handed the frontend
+struct Frontend {
+    pixel_format: Option<c_uint>,
+    frames: Vec<(Vec<u32>, c_uint, c_uint, usize)>,
+    samples: Vec<i16>,
+    // keyboard keys held down, as retrok codes
+    held: Vec<c_uint>,
+    polled: usize,
+    // states saved from inside video_refresh
+    saved: usize,
+}
+
+static FRONTEND: Mutex<Frontend> = Mutex::new(Frontend { pixel_format: None, frames: Vec::new(), samples: Vec::new(), held: Vec::new(), polled: 0, saved: 0 });
+// the core has one global state, so one test drives it at a time
+static SERIAL: Mutex<()> = Mutex::new(());
+// retro_serialize, for frontends that save states from their callbacks
+static SERIALIZE: Mutex<Option<extern "C" fn(*mut c_void, usize) -> bool>> = Mutex::new(None);
+
+extern "C" fn environment(command: c_uint, data: *mut c_void) -> bool {
+    match command {
+        // SET_PIXEL_FORMAT
+        10 => {
+            FRONTEND.lock().unwrap().pixel_format = Some(unsafe { *(data as *const c_uint) });
+            true
+        },
+        _ => false,
+    }
+}
+
+extern "C" fn video_refresh(data: *const c_void, width: c_uint, height: c_uint, pitch: usize) {
+    let pixels = unsafe { std::slice::from_raw_parts(data as *const u32, pitch / 4 * height as usize) };
+    FRONTEND.lock().unwrap().frames.push((pixels.to_vec(), width, height, pitch));
+    if let Some(serialize) = *SERIALIZE.lock().unwrap() {
+        let mut state = vec![0u8; chip_8::processor::SNAPSHOT_SIZE];
+        if serialize(state.as_mut_ptr() as *mut c_void, state.len()) {
+            FRONTEND.lock().unwrap().saved += 1;
+        }
+    }
+}
+
+extern "C" fn audio_sample(_left: i16, _right: i16) {}
+
+extern "C" fn audio_sample_batch(data: *const i16, frames: usize) -> usize {
+    let samples = unsafe { std::slice::from_raw_parts(data, frames * 2) };
+    FRONTEND.lock().unwrap().samples.extend_from_slice(samples);
+    frames
+}
+
+extern "C" fn input_poll() {
+    FRONTEND.lock().unwrap().polled += 1;
+}
+
+extern "C" fn input_state(port: c_uint, device: c_uint, _index: c_uint, id: c_uint) -> i16 {
+    // port 0's RETRO_DEVICE_KEYBOARD
+    (port == 0 && device == 3 && FRONTEND.lock().unwrap().held.contains(&id)) as i16
+}
+
+struct Core {
+    library: Library,
+}
+
+impl Core {
+    // the core with every callback set and `rom` loaded
+    fn load(rom: &[u8]) -> Core {
+        let directory = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+        let library = unsafe { Library::new(directory.join(libloading::library_filename("chip_8"))) }.unwrap();
+        let core = Core { library };
+        *SERIALIZE.lock().unwrap() = None;
+        *FRONTEND.lock().unwrap() = Frontend { pixel_format: None, frames: Vec::new(), samples: Vec::new(), held: Vec::new(), polled: 0, saved: 0 };
+        unsafe {
+            core.symbol::<extern "C" fn(extern "C" fn(c_uint, *mut c_void) -> bool)>(b"retro_set_environment")(environment);
+            core.symbol::<extern "C" fn(extern "C" fn(*const c_void, c_uint, c_uint, usize))>(b"retro_set_video_refresh")(video_refresh);
+            core.symbol::<extern "C" fn(extern "C" fn(i16, i16))>(b"retro_set_audio_sample")(audio_sample);
+            core.symbol::<extern "C" fn(extern "C" fn(*const i16, usize) -> usize)>(b"retro_set_audio_sample_batch")(audio_sample_batch);
+            core.symbol::<extern "C" fn(extern "C" fn())>(b"retro_set_input_poll")(input_poll);
+            core.symbol::<extern "C" fn(extern "C" fn(c_uint, c_uint, c_uint, c_uint) -> i16)>(b"retro_set_input_state")(input_state);
+            core.symbol::<extern "C" fn()>(b"retro_init")();
+            let game = GameInfo { path: std::ptr::null(), data: rom.as_ptr() as *const c_void, size: rom.len(), meta: std::ptr::null() };
+            assert!(core.symbol::<extern "C" fn(*const GameInfo) -> bool>(b"retro_load_game")(&game), "the rom loads");
+        }
+        core
+    }
+
+    unsafe fn symbol<T>(&self, name: &[u8]) -> Symbol<'_, T> {
+        self.library.get(name).unwrap()
+    }
+
+    fn run(&self, frames: usize) {
+        for _ in 0..frames {
+            unsafe { self.symbol::<extern "C" fn()>(b"retro_run")() };
+        }
+    }
+
+    fn last_frame(&self) -> Vec<u32> {
+        FRONTEND.lock().unwrap().frames.last().unwrap().0.clone()
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        unsafe {
+            let mut state = vec![0; self.symbol::<extern "C" fn() -> usize>(b"retro_serialize_size")()];
+            assert!(self.symbol::<extern "C" fn(*mut c_void, usize) -> bool>(b"retro_serialize")(state.as_mut_ptr() as *mut c_void, state.len()), "the state is saved");
+            state
+        }
+    }
+
+    fn unserialize(&self, state: &[u8]) -> bool {
+        unsafe { self.symbol::<extern "C" fn(*const c_void, usize) -> bool>(b"retro_unserialize")(state.as_ptr() as *const c_void, state.len()) }
+    }
+}
+
+impl Drop for Core {
+    fn drop(&mut self) {
+        unsafe {
+            self.symbol::<extern "C" fn()>(b"retro_unload_game")();
+            self.symbol::<extern "C" fn()>(b"retro_deinit")();
+        }
+    }
+}
+
+#[test]
+fn core_describes_itself() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let core = Core::load(PONG);
+    unsafe {
+        assert_eq!(core.symbol::<extern "C" fn() -> c_uint>(b"retro_api_version")(), 1, "the core speaks libretro version 1");
+
+        let mut info: SystemInfo = std::mem::zeroed();
+        core.symbol::<extern "C" fn(*mut SystemInfo)>(b"retro_get_system_info")(&mut info);
+        let text = |text: *const c_char| std::ffi::CStr::from_ptr(text).to_str().unwrap();
+        assert_eq!((text(info.library_name), text(info.valid_extensions)), ("chip_8", "ch8|c8|chip8"));
+        assert!(!info.need_fullpath, "roms are loaded from memory");
+
+        let mut av: SystemAvInfo = std::mem::zeroed();
+        core.symbol::<extern "C" fn(*mut SystemAvInfo)>(b"retro_get_system_av_info")(&mut av);
+        assert_eq!((av.geometry.base_width, av.geometry.base_height, av.timing.fps), (64, 32, 60.0), "the display is 64x32 at 60Hz");
+    }
+    assert_eq!(FRONTEND.lock().unwrap().pixel_format, Some(1), "frames are xrgb8888");
+}
+
+#[test]
+fn frames_are_drawn() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let core = Core::load(PONG);
+    core.run(10);
+    let frontend = FRONTEND.lock().unwrap();
+    assert_eq!((frontend.frames.len(), frontend.polled), (10, 10), "each run polls input and shows a frame");
+    let (pixels, width, height, pitch) = &frontend.frames[9];
+    assert_eq!((*width, *height, *pitch), (64, 32, 64 * 4), "frames are the display's size");
+    let [background, foreground] = Database::bundled().lookup(PONG).colors.map(|[r, g, b]| u32::from_be_bytes([0, r, g, b]));
+    assert!(pixels.contains(&foreground) && pixels.contains(&background), "pong draws in its colors");
+}
+
+#[test]
+fn keys_are_read_through_the_keymap() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let core = Core::load(PONG);
+    // pong waits out its first frames before reading the keys
+    core.run(100);
+    let state = core.serialize();
+
+    core.run(20);
+    let still = core.last_frame();
+
+    // pong's left paddle moves up with hex key 1
+    assert!(core.unserialize(&state));
+    let up = Database::bundled().lookup(PONG).keymap[0x1] as c_uint;
+    FRONTEND.lock().unwrap().held.push(up);
+    core.run(20);
+    assert!(core.last_frame() != still, "holding the key moves the paddle");
+}
+
+#[test]
+fn the_buzzer_is_heard() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let core = Core::load(BUZZER);
+    core.run(1);
+    let samples = FRONTEND.lock().unwrap().samples.clone();
+    assert_eq!(samples.len(), 2 * 48000 / 60, "a frame of stereo samples goes out each run");
+    assert!(samples.iter().any(|sample| *sample > 0) && samples.iter().any(|sample| *sample < 0), "the buzzer is a square wave");
+
+    core.run(20);
+    let frontend = FRONTEND.lock().unwrap();
+    assert!(frontend.samples[frontend.samples.len() - 1600..].iter().all(|sample| *sample == 0), "it stops with the sound timer");
+}
+
+#[test]
+fn states_restore() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let core = Core::load(PONG);
+    core.run(30);
+    let state = core.serialize();
+    assert_eq!(state.len(), chip_8::processor::SNAPSHOT_SIZE, "states are the size the core gives");
+
+    core.run(60);
+    let later = core.last_frame();
+    assert!(core.unserialize(&state), "the state loads");
+    core.run(60);
+    assert!(core.last_frame() == later, "the game plays the same from the state");
+
+    assert!(!core.unserialize(&state[..100]), "short states are refused");
+    unsafe { core.symbol::<extern "C" fn()>(b"retro_reset")() };
+    core.run(1);
+}
+
+#[test]
+fn callbacks_may_call_back_into_the_core() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let core = Core::load(PONG);
+    *SERIALIZE.lock().unwrap() = Some(*unsafe { core.symbol::<extern "C" fn(*mut c_void, usize) -> bool>(b"retro_serialize") });
+    core.run(3);
+    *SERIALIZE.lock().unwrap() = None;
+    assert_eq!(FRONTEND.lock().unwrap().saved, 3, "states can be saved while a frame is shown");
+}
+
+#[test]
+fn failing_roms_halt() {
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    // 0xFFFF isn't an opcode
+    let core = Core::load(&[0xFF, 0xFF]);
+    core.run(3);
+    assert_eq!(FRONTEND.lock().unwrap().frames.len(), 3, "frames still go out after the rom fails");
+}
@@ -0,0 +1,784 @@
+// recompiled by `chip_8 recompile` from the rom with sha1 7286da1269032dea8317a8e8489f02f55896e1a1, regenerate it
+// rather than editing by hand
+
+use chip_8::display::Display;
+use chip_8::error::Error;
+use chip_8::keyboard::Keyboard;
+
+// interpreter behaviour the rom was recompiled for
+const SHIFT_USES_VY: bool = false;
+const LOAD_STORE_INCREMENTS_I: bool = false;
+const JUMP_USES_VX: bool = false;
+const LOGIC_RESETS_VF: bool = false;
+const CLIP_SPRITES: bool = false;
+
+static FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0,
+    0x10, 0xF0, 0x10, 0xF0, 0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, 0xF0, 0x80,
+    0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40, 0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0,
+    0x10, 0xF0, 0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0, 0xF0, 0x80, 0x80, 0x80,
+    0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+];
+
+static ROM: [u8; 64] = [
+    0x60, 0x04, 0xB2, 0x06, 0x00, 0x00, 0x13, 0x00, 0x13, 0x00, 0x22, 0x30, 0x71, 0x01, 0x31, 0x05,
+    0x12, 0x0A, 0x60, 0x65, 0x61, 0x77, 0xA2, 0x20, 0xF1, 0x55, 0x12, 0x20, 0x00, 0x00, 0x00, 0x00,
+    0x65, 0x01, 0x63, 0xFE, 0xA3, 0x00, 0xF3, 0x33, 0xF2, 0x65, 0xF4, 0x0A, 0x12, 0x2A, 0x00, 0x00,
+    0xF1, 0x29, 0x6A, 0x08, 0x8B, 0x10, 0x8B, 0xA4, 0xDA, 0xB5, 0x8C, 0x13, 0x8C, 0xB6, 0x00, 0xEE,
+];
+
+// bit per memory byte holding a recompiled opcode
+static CODE: [u64; 64] = [
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0xFFFFFFFFFFFFFFCF, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000003, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+];
+
+pub struct Machine {
+    // storage
+    memory: [u8; 4096],
+    register: [u8; 16],
+    stack: [u16; 16],
+
+    // counters
+    program_counter: u16,
+    index_register: u16,
+    stack_pointer: u8,
+
+    // timers
+    sound_timer: u8,
+    delay_timer: u8,
+
+    // cleared once the rom stores over its own code
+    native: bool,
+
+    // hardware
+    pub display: Display,
+    pub keyboard: Keyboard,
+}
+
+impl Default for Machine {
+    fn default() -> Machine {
+        Machine::new()
+    }
+}
+
+impl Machine {
+    pub fn new() -> Machine {
+        let mut memory = [0; 4096];
+        memory[..FONT.len()].copy_from_slice(&FONT);
+        memory[0x200..0x200 + ROM.len()].copy_from_slice(&ROM);
+        Machine {
+            memory,
+            register: [0; 16],
+            stack: [0; 16],
+            program_counter: 0x200,
+            index_register: 0,
+            stack_pointer: 0,
+            sound_timer: 0,
+            delay_timer: 0,
+            native: true,
+            display: Display::new(),
+            keyboard: Keyboard::new(),
+        }
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.register
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn stack_pointer(&self) -> u8 {
+        self.stack_pointer
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8; 4096] {
+        &self.memory
+    }
+
+    // runs a 60Hz frame, the timers tick once after `instructions` instructions
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Error> {
+        let mut remaining = instructions;
+        while remaining > 0 {
+            remaining -= self.dispatch(remaining)?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    // executes a single instruction without touching the timers
+    pub fn step(&mut self) -> Result<(), Error> {
+        self.interpret()
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    // memory index `offset` bytes past I, wrapping at the end of memory
+    fn address(&self, offset: usize) -> usize {
+        (self.index_register as usize + offset) & 0xFFF
+    }
+
+    // leaves the recompiled blocks for the interpreter when the `length`
+    // bytes just stored at I hold code
+    fn stored(&mut self, length: usize) {
+        for offset in 0..length {
+            let address = self.address(offset);
+            if (CODE[address / 64] >> (address % 64)) & 1 == 1 {
+                self.native = false;
+            }
+        }
+    }
+
+    // decodes and runs the opcode at the program counter, for addresses the
+    // recompiler found no block at and code the rom rewrote
+    fn interpret(&mut self) -> Result<(), Error> {
+        let pc = self.program_counter;
+        let opcode = (self.memory[pc as usize] as u16) << 8 | self.memory[((pc + 1) & 0xFFF) as usize] as u16;
+
+        let nnn = opcode & 0x0FFF;
+        let nn = (opcode & 0x00FF) as u8;
+        let n = (opcode & 0x000F) as usize;
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        let vx = self.register[x];
+        let vy = self.register[y];
+        let mut next = (pc + 2) & 0xFFF;
+
+        match (opcode >> 12, x, y, n) {
+            (0x0, 0x0, 0xE, 0x0) => self.display.clear(),
+            (0x0, 0x0, 0xE, 0xE) => {
+                if self.stack_pointer == 0 {
+                    return Err(Error::StackUnderflow { pc });
+                }
+                self.stack_pointer -= 1;
+                next = self.stack[self.stack_pointer as usize];
+            },
+            (0x1, _, _, _) => next = nnn,
+            (0x2, _, _, _) => {
+                if self.stack_pointer as usize == self.stack.len() {
+                    return Err(Error::StackOverflow { pc });
+                }
+                self.stack[self.stack_pointer as usize] = next;
+                self.stack_pointer += 1;
+                next = nnn;
+            },
+            (0x3, _, _, _) => next += if vx == nn { 2 } else { 0 },
+            (0x4, _, _, _) => next += if vx != nn { 2 } else { 0 },
+            (0x5, _, _, 0x0) => next += if vx == vy { 2 } else { 0 },
+            (0x6, _, _, _) => self.register[x] = nn,
+            (0x7, _, _, _) => self.register[x] = vx.wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.register[x] = vy,
+            (0x8, _, _, 0x1) | (0x8, _, _, 0x2) | (0x8, _, _, 0x3) => {
+                self.register[x] = match n {
+                    0x1 => vx | vy,
+                    0x2 => vx & vy,
+                    _ => vx ^ vy,
+                };
+                if LOGIC_RESETS_VF {
+                    self.register[0xF] = 0;
+                }
+            },
+            (0x8, _, _, 0x4) => {
+                let (result, overflow) = vx.overflowing_add(vy);
+                self.register[0xF] = overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0x5) => {
+                let (result, overflow) = vx.overflowing_sub(vy);
+                self.register[0xF] = !overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0x6) => {
+                let source = if SHIFT_USES_VY { vy } else { vx };
+                self.register[0xF] = source & 0x1;
+                self.register[x] = source >> 1;
+            },
+            (0x8, _, _, 0x7) => {
+                let (result, overflow) = vy.overflowing_sub(vx);
+                self.register[0xF] = !overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0xE) => {
+                let source = if SHIFT_USES_VY { vy } else { vx };
+                self.register[0xF] = source >> 7;
+                self.register[x] = source << 1;
+            },
+            (0x9, _, _, 0x0) => next += if vx != vy { 2 } else { 0 },
+            (0xA, _, _, _) => self.index_register = nnn,
+            (0xB, _, _, _) => {
+                let offset = if JUMP_USES_VX { vx } else { self.register[0] };
+                next = nnn + offset as u16;
+            },
+            (0xC, _, _, _) => self.register[x] = nn & rand::random::<u8>(),
+            (0xD, _, _, _) => {
+                let mut sprite = [0; 15];
+                for (row, byte) in sprite.iter_mut().enumerate().take(n) {
+                    *byte = self.memory[self.address(row)];
+                }
+                let collision = if CLIP_SPRITES {
+                    self.display.draw_clipped(vx, vy, &sprite[..n])
+                } else {
+                    self.display.draw(vx, vy, &sprite[..n])
+                };
+                self.register[0xF] = collision as u8;
+            },
+            (0xE, _, 0x9, 0xE) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 2 } else { 0 },
+            (0xE, _, 0xA, 0x1) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 0 } else { 2 },
+            (0xF, _, 0x0, 0x7) => self.register[x] = self.delay_timer,
+            (0xF, _, 0x0, 0xA) => {
+                // stay on this opcode while no key is pressed
+                next = pc;
+                for key in 0..16 {
+                    if self.keyboard.pressed(key) {
+                        self.register[x] = key as u8;
+                        next = pc + 2;
+                    }
+                }
+            },
+            (0xF, _, 0x1, 0x5) => self.delay_timer = vx,
+            (0xF, _, 0x1, 0x8) => self.sound_timer = vx,
+            (0xF, _, 0x1, 0xE) => self.index_register = (self.index_register + vx as u16) & 0xFFF,
+            (0xF, _, 0x2, 0x9) => self.index_register = vx as u16 * 5,
+            (0xF, _, 0x3, 0x3) => {
+                self.memory[self.address(0)] = vx / 100;
+                self.memory[self.address(1)] = (vx / 10) % 10;
+                self.memory[self.address(2)] = vx % 10;
+                self.stored(3);
+            },
+            (0xF, _, 0x5, 0x5) => {
+                for offset in 0..=x {
+                    self.memory[self.address(offset)] = self.register[offset];
+                }
+                self.stored(x + 1);
+                if LOAD_STORE_INCREMENTS_I {
+                    self.index_register = (self.index_register + x as u16 + 1) & 0xFFF;
+                }
+            },
+            (0xF, _, 0x6, 0x5) => {
+                for offset in 0..=x {
+                    self.register[offset] = self.memory[self.address(offset)];
+                }
+                if LOAD_STORE_INCREMENTS_I {
+                    self.index_register = (self.index_register + x as u16 + 1) & 0xFFF;
+                }
+            },
+            (_, _, _, _) => return Err(Error::UnknownOpcode { opcode, pc }),
+        }
+
+        // jumps and skips also wrap at the end of memory
+        self.program_counter = next & 0xFFF;
+        Ok(())
+    }
+
+    // runs the block recompiled at the program counter when it fits in
+    // `remaining` instructions, otherwise interprets one, and returns how
+    // many instructions ran
+    fn dispatch(&mut self, remaining: usize) -> Result<usize, Error> {
+        if !self.native {
+            return self.interpret().map(|_| 1);
+        }
+        match self.program_counter {
+            0x200 if remaining >= 2 => self.block_200(),
+            0x206 if remaining >= 2 => self.block_206(),
+            0x208 if remaining >= 2 => self.block_208(),
+            0x20A if remaining >= 1 => self.block_20a(),
+            0x20C if remaining >= 2 => self.block_20c(),
+            0x20E if remaining >= 1 => self.block_20e(),
+            0x210 if remaining >= 2 => self.block_210(),
+            0x212 if remaining >= 4 => self.block_212(),
+            0x214 if remaining >= 3 => self.block_214(),
+            0x216 if remaining >= 2 => self.block_216(),
+            0x218 if remaining >= 1 => self.block_218(),
+            0x21A if remaining >= 5 => self.block_21a(),
+            0x21C if remaining >= 1 => self.block_21c(),
+            0x21E if remaining >= 1 => self.block_21e(),
+            0x220 if remaining >= 4 => self.block_220(),
+            0x222 if remaining >= 3 => self.block_222(),
+            0x224 if remaining >= 2 => self.block_224(),
+            0x226 if remaining >= 1 => self.block_226(),
+            0x228 if remaining >= 2 => self.block_228(),
+            0x22A if remaining >= 1 => self.block_22a(),
+            0x22C if remaining >= 2 => self.block_22c(),
+            0x22E if remaining >= 1 => self.block_22e(),
+            0x230 if remaining >= 8 => self.block_230(),
+            0x232 if remaining >= 7 => self.block_232(),
+            0x234 if remaining >= 6 => self.block_234(),
+            0x236 if remaining >= 5 => self.block_236(),
+            0x238 if remaining >= 4 => self.block_238(),
+            0x23A if remaining >= 3 => self.block_23a(),
+            0x23C if remaining >= 2 => self.block_23c(),
+            0x23E if remaining >= 1 => self.block_23e(),
+            _ => self.interpret().map(|_| 1),
+        }
+    }
+
+    fn block_200(&mut self) -> Result<usize, Error> {
+        // 0x200 LD V0, 0x04
+        self.register[0x0] = 0x04;
+        // 0x202 JP V0, 0x206
+        self.program_counter = (0x206 + self.register[0x0] as u16) & 0xFFF;
+        Ok(2)
+    }
+
+    fn block_206(&mut self) -> Result<usize, Error> {
+        // 0x206 JP 0x300
+        // 0x300 DW 0x0000
+        self.program_counter = 0x300;
+        Err(Error::UnknownOpcode { opcode: 0x0000, pc: 0x300 })
+    }
+
+    fn block_208(&mut self) -> Result<usize, Error> {
+        // 0x208 JP 0x300
+        // 0x300 DW 0x0000
+        self.program_counter = 0x300;
+        Err(Error::UnknownOpcode { opcode: 0x0000, pc: 0x300 })
+    }
+
+    fn block_20a(&mut self) -> Result<usize, Error> {
+        // 0x20A CALL 0x230
+        if self.stack_pointer as usize == self.stack.len() {
+            self.program_counter = 0x20A;
+            return Err(Error::StackOverflow { pc: 0x20A });
+        }
+        self.stack[self.stack_pointer as usize] = 0x20C;
+        self.stack_pointer += 1;
+        self.program_counter = 0x230;
+        Ok(1)
+    }
+
+    fn block_20c(&mut self) -> Result<usize, Error> {
+        // 0x20C ADD V1, 0x01
+        self.register[0x1] = self.register[0x1].wrapping_add(0x01);
+        // 0x20E SE V1, 0x05
+        self.program_counter = if self.register[0x1] == 0x05 { 0x212 } else { 0x210 };
+        Ok(2)
+    }
+
+    fn block_20e(&mut self) -> Result<usize, Error> {
+        // 0x20E SE V1, 0x05
+        self.program_counter = if self.register[0x1] == 0x05 { 0x212 } else { 0x210 };
+        Ok(1)
+    }
+
+    fn block_210(&mut self) -> Result<usize, Error> {
+        // 0x210 JP 0x20A
+        // 0x20A CALL 0x230
+        if self.stack_pointer as usize == self.stack.len() {
+            self.program_counter = 0x20A;
+            return Err(Error::StackOverflow { pc: 0x20A });
+        }
+        self.stack[self.stack_pointer as usize] = 0x20C;
+        self.stack_pointer += 1;
+        self.program_counter = 0x230;
+        Ok(2)
+    }
+
+    fn block_212(&mut self) -> Result<usize, Error> {
+        // 0x212 LD V0, 0x65
+        self.register[0x0] = 0x65;
+        // 0x214 LD V1, 0x77
+        self.register[0x1] = 0x77;
+        // 0x216 LD I, 0x220
+        self.index_register = 0x220;
+        // 0x218 LD [I], V1
+        self.memory[self.address(0)] = self.register[0x0];
+        self.memory[self.address(1)] = self.register[0x1];
+        self.stored(2);
+        self.program_counter = 0x21A;
+        Ok(4)
+    }
+
+    fn block_214(&mut self) -> Result<usize, Error> {
+        // 0x214 LD V1, 0x77
+        self.register[0x1] = 0x77;
+        // 0x216 LD I, 0x220
+        self.index_register = 0x220;
+        // 0x218 LD [I], V1
+        self.memory[self.address(0)] = self.register[0x0];
+        self.memory[self.address(1)] = self.register[0x1];
+        self.stored(2);
+        self.program_counter = 0x21A;
+        Ok(3)
+    }
+
+    fn block_216(&mut self) -> Result<usize, Error> {
+        // 0x216 LD I, 0x220
+        self.index_register = 0x220;
+        // 0x218 LD [I], V1
+        self.memory[self.address(0)] = self.register[0x0];
+        self.memory[self.address(1)] = self.register[0x1];
+        self.stored(2);
+        self.program_counter = 0x21A;
+        Ok(2)
+    }
+
+    fn block_218(&mut self) -> Result<usize, Error> {
+        // 0x218 LD [I], V1
+        self.memory[self.address(0)] = self.register[0x0];
+        self.memory[self.address(1)] = self.register[0x1];
+        self.stored(2);
+        self.program_counter = 0x21A;
+        Ok(1)
+    }
+
+    fn block_21a(&mut self) -> Result<usize, Error> {
+        // 0x21A JP 0x220
+        // 0x220 LD V5, 0x01
+        self.register[0x5] = 0x01;
+        // 0x222 LD V3, 0xFE
+        self.register[0x3] = 0xFE;
+        // 0x224 LD I, 0x300
+        self.index_register = 0x300;
+        // 0x226 LD B, V3
+        let value = self.register[0x3];
+        self.memory[self.address(0)] = value / 100;
+        self.memory[self.address(1)] = (value / 10) % 10;
+        self.memory[self.address(2)] = value % 10;
+        self.stored(3);
+        self.program_counter = 0x228;
+        Ok(5)
+    }
+
+    fn block_21c(&mut self) -> Result<usize, Error> {
+        // 0x21C DW 0x0000
+        self.program_counter = 0x21C;
+        Err(Error::UnknownOpcode { opcode: 0x0000, pc: 0x21C })
+    }
+
+    fn block_21e(&mut self) -> Result<usize, Error> {
+        // 0x21E DW 0x0000
+        self.program_counter = 0x21E;
+        Err(Error::UnknownOpcode { opcode: 0x0000, pc: 0x21E })
+    }
+
+    fn block_220(&mut self) -> Result<usize, Error> {
+        // 0x220 LD V5, 0x01
+        self.register[0x5] = 0x01;
+        // 0x222 LD V3, 0xFE
+        self.register[0x3] = 0xFE;
+        // 0x224 LD I, 0x300
+        self.index_register = 0x300;
+        // 0x226 LD B, V3
+        let value = self.register[0x3];
+        self.memory[self.address(0)] = value / 100;
+        self.memory[self.address(1)] = (value / 10) % 10;
+        self.memory[self.address(2)] = value % 10;
+        self.stored(3);
+        self.program_counter = 0x228;
+        Ok(4)
+    }
+
+    fn block_222(&mut self) -> Result<usize, Error> {
+        // 0x222 LD V3, 0xFE
+        self.register[0x3] = 0xFE;
+        // 0x224 LD I, 0x300
+        self.index_register = 0x300;
+        // 0x226 LD B, V3
+        let value = self.register[0x3];
+        self.memory[self.address(0)] = value / 100;
+        self.memory[self.address(1)] = (value / 10) % 10;
+        self.memory[self.address(2)] = value % 10;
+        self.stored(3);
+        self.program_counter = 0x228;
+        Ok(3)
+    }
+
+    fn block_224(&mut self) -> Result<usize, Error> {
+        // 0x224 LD I, 0x300
+        self.index_register = 0x300;
+        // 0x226 LD B, V3
+        let value = self.register[0x3];
+        self.memory[self.address(0)] = value / 100;
+        self.memory[self.address(1)] = (value / 10) % 10;
+        self.memory[self.address(2)] = value % 10;
+        self.stored(3);
+        self.program_counter = 0x228;
+        Ok(2)
+    }
+
+    fn block_226(&mut self) -> Result<usize, Error> {
+        // 0x226 LD B, V3
+        let value = self.register[0x3];
+        self.memory[self.address(0)] = value / 100;
+        self.memory[self.address(1)] = (value / 10) % 10;
+        self.memory[self.address(2)] = value % 10;
+        self.stored(3);
+        self.program_counter = 0x228;
+        Ok(1)
+    }
+
+    fn block_228(&mut self) -> Result<usize, Error> {
+        // 0x228 LD V2, [I]
+        self.register[0x0] = self.memory[self.address(0)];
+        self.register[0x1] = self.memory[self.address(1)];
+        self.register[0x2] = self.memory[self.address(2)];
+        // 0x22A LD V4, K
+        self.program_counter = 0x22A;
+        for key in 0..16 {
+            if self.keyboard.pressed(key) {
+                self.register[0x4] = key as u8;
+                self.program_counter = 0x22C;
+            }
+        }
+        Ok(2)
+    }
+
+    fn block_22a(&mut self) -> Result<usize, Error> {
+        // 0x22A LD V4, K
+        self.program_counter = 0x22A;
+        for key in 0..16 {
+            if self.keyboard.pressed(key) {
+                self.register[0x4] = key as u8;
+                self.program_counter = 0x22C;
+            }
+        }
+        Ok(1)
+    }
+
+    fn block_22c(&mut self) -> Result<usize, Error> {
+        // 0x22C JP 0x22A
+        // 0x22A LD V4, K
+        self.program_counter = 0x22A;
+        for key in 0..16 {
+            if self.keyboard.pressed(key) {
+                self.register[0x4] = key as u8;
+                self.program_counter = 0x22C;
+            }
+        }
+        Ok(2)
+    }
+
+    fn block_22e(&mut self) -> Result<usize, Error> {
+        // 0x22E DW 0x0000
+        self.program_counter = 0x22E;
+        Err(Error::UnknownOpcode { opcode: 0x0000, pc: 0x22E })
+    }
+
+    fn block_230(&mut self) -> Result<usize, Error> {
+        // 0x230 LD F, V1
+        self.index_register = self.register[0x1] as u16 * 5;
+        // 0x232 LD VA, 0x08
+        self.register[0xA] = 0x08;
+        // 0x234 LD VB, V1
+        self.register[0xB] = self.register[0x1];
+        // 0x236 ADD VB, VA
+        let (result, overflow) = self.register[0xB].overflowing_add(self.register[0xA]);
+        self.register[0xF] = overflow as u8;
+        self.register[0xB] = result;
+        // 0x238 DRW VA, VB, 5
+        let mut sprite = [0; 5];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x23A XOR VC, V1
+        let (vx, vy) = (self.register[0xC], self.register[0x1]);
+        self.register[0xC] = vx ^ vy;
+        // 0x23C SHR VC
+        let source = self.register[0xC];
+        self.register[0xF] = source & 0x1;
+        self.register[0xC] = source >> 1;
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(8)
+    }
+
+    fn block_232(&mut self) -> Result<usize, Error> {
+        // 0x232 LD VA, 0x08
+        self.register[0xA] = 0x08;
+        // 0x234 LD VB, V1
+        self.register[0xB] = self.register[0x1];
+        // 0x236 ADD VB, VA
+        let (result, overflow) = self.register[0xB].overflowing_add(self.register[0xA]);
+        self.register[0xF] = overflow as u8;
+        self.register[0xB] = result;
+        // 0x238 DRW VA, VB, 5
+        let mut sprite = [0; 5];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x23A XOR VC, V1
+        let (vx, vy) = (self.register[0xC], self.register[0x1]);
+        self.register[0xC] = vx ^ vy;
+        // 0x23C SHR VC
+        let source = self.register[0xC];
+        self.register[0xF] = source & 0x1;
+        self.register[0xC] = source >> 1;
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(7)
+    }
+
+    fn block_234(&mut self) -> Result<usize, Error> {
+        // 0x234 LD VB, V1
+        self.register[0xB] = self.register[0x1];
+        // 0x236 ADD VB, VA
+        let (result, overflow) = self.register[0xB].overflowing_add(self.register[0xA]);
+        self.register[0xF] = overflow as u8;
+        self.register[0xB] = result;
+        // 0x238 DRW VA, VB, 5
+        let mut sprite = [0; 5];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x23A XOR VC, V1
+        let (vx, vy) = (self.register[0xC], self.register[0x1]);
+        self.register[0xC] = vx ^ vy;
+        // 0x23C SHR VC
+        let source = self.register[0xC];
+        self.register[0xF] = source & 0x1;
+        self.register[0xC] = source >> 1;
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(6)
+    }
+
+    fn block_236(&mut self) -> Result<usize, Error> {
+        // 0x236 ADD VB, VA
+        let (result, overflow) = self.register[0xB].overflowing_add(self.register[0xA]);
+        self.register[0xF] = overflow as u8;
+        self.register[0xB] = result;
+        // 0x238 DRW VA, VB, 5
+        let mut sprite = [0; 5];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x23A XOR VC, V1
+        let (vx, vy) = (self.register[0xC], self.register[0x1]);
+        self.register[0xC] = vx ^ vy;
+        // 0x23C SHR VC
+        let source = self.register[0xC];
+        self.register[0xF] = source & 0x1;
+        self.register[0xC] = source >> 1;
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(5)
+    }
+
+    fn block_238(&mut self) -> Result<usize, Error> {
+        // 0x238 DRW VA, VB, 5
+        let mut sprite = [0; 5];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x23A XOR VC, V1
+        let (vx, vy) = (self.register[0xC], self.register[0x1]);
+        self.register[0xC] = vx ^ vy;
+        // 0x23C SHR VC
+        let source = self.register[0xC];
+        self.register[0xF] = source & 0x1;
+        self.register[0xC] = source >> 1;
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(4)
+    }
+
+    fn block_23a(&mut self) -> Result<usize, Error> {
+        // 0x23A XOR VC, V1
+        let (vx, vy) = (self.register[0xC], self.register[0x1]);
+        self.register[0xC] = vx ^ vy;
+        // 0x23C SHR VC
+        let source = self.register[0xC];
+        self.register[0xF] = source & 0x1;
+        self.register[0xC] = source >> 1;
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(3)
+    }
+
+    fn block_23c(&mut self) -> Result<usize, Error> {
+        // 0x23C SHR VC
+        let source = self.register[0xC];
+        self.register[0xF] = source & 0x1;
+        self.register[0xC] = source >> 1;
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(2)
+    }
+
+    fn block_23e(&mut self) -> Result<usize, Error> {
+        // 0x23E RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x23E;
+            return Err(Error::StackUnderflow { pc: 0x23E });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(1)
+    }
+}
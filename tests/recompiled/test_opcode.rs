@@ -0,0 +1,1807 @@
+// recompiled by `chip_8 recompile` from the rom with sha1 f1cfcffe1937ed6dd6eeed1a7f85dfc777bda700, regenerate it
+// rather than editing by hand
+
+use chip_8::display::Display;
+use chip_8::error::Error;
+use chip_8::keyboard::Keyboard;
+
+// interpreter behaviour the rom was recompiled for
+const SHIFT_USES_VY: bool = false;
+const LOAD_STORE_INCREMENTS_I: bool = false;
+const JUMP_USES_VX: bool = false;
+const LOGIC_RESETS_VF: bool = false;
+const CLIP_SPRITES: bool = false;
+
+static FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0,
+    0x10, 0xF0, 0x10, 0xF0, 0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, 0xF0, 0x80,
+    0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40, 0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0,
+    0x10, 0xF0, 0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0, 0xF0, 0x80, 0x80, 0x80,
+    0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+];
+
+static ROM: [u8; 478] = [
+    0x12, 0x4E, 0xEA, 0xAC, 0xAA, 0xEA, 0xCE, 0xAA, 0xAA, 0xAE, 0xE0, 0xA0, 0xA0, 0xE0, 0xC0, 0x40,
+    0x40, 0xE0, 0xE0, 0x20, 0xC0, 0xE0, 0xE0, 0x60, 0x20, 0xE0, 0xA0, 0xE0, 0x20, 0x20, 0x60, 0x40,
+    0x20, 0x40, 0xE0, 0x80, 0xE0, 0xE0, 0xE0, 0x20, 0x20, 0x20, 0xE0, 0xE0, 0xA0, 0xE0, 0xE0, 0xE0,
+    0x20, 0xE0, 0x40, 0xA0, 0xE0, 0xA0, 0xE0, 0xC0, 0x80, 0xE0, 0xE0, 0x80, 0xC0, 0x80, 0xA0, 0x40,
+    0xA0, 0xA0, 0xA2, 0x02, 0xDA, 0xB4, 0x00, 0xEE, 0xA2, 0x02, 0xDA, 0xB4, 0x13, 0xDC, 0x68, 0x01,
+    0x69, 0x05, 0x6A, 0x0A, 0x6B, 0x01, 0x65, 0x2A, 0x66, 0x2B, 0xA2, 0x16, 0xD8, 0xB4, 0xA2, 0x3E,
+    0xD9, 0xB4, 0xA2, 0x02, 0x36, 0x2B, 0xA2, 0x06, 0xDA, 0xB4, 0x6B, 0x06, 0xA2, 0x1A, 0xD8, 0xB4,
+    0xA2, 0x3E, 0xD9, 0xB4, 0xA2, 0x06, 0x45, 0x2A, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x0B, 0xA2, 0x1E,
+    0xD8, 0xB4, 0xA2, 0x3E, 0xD9, 0xB4, 0xA2, 0x06, 0x55, 0x60, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x10,
+    0xA2, 0x26, 0xD8, 0xB4, 0xA2, 0x3E, 0xD9, 0xB4, 0xA2, 0x06, 0x76, 0xFF, 0x46, 0x2A, 0xA2, 0x02,
+    0xDA, 0xB4, 0x6B, 0x15, 0xA2, 0x2E, 0xD8, 0xB4, 0xA2, 0x3E, 0xD9, 0xB4, 0xA2, 0x06, 0x95, 0x60,
+    0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x1A, 0xA2, 0x32, 0xD8, 0xB4, 0xA2, 0x3E, 0xD9, 0xB4, 0x22, 0x42,
+    0x68, 0x17, 0x69, 0x1B, 0x6A, 0x20, 0x6B, 0x01, 0xA2, 0x0A, 0xD8, 0xB4, 0xA2, 0x36, 0xD9, 0xB4,
+    0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x06, 0xA2, 0x2A, 0xD8, 0xB4, 0xA2, 0x0A, 0xD9, 0xB4, 0xA2, 0x06,
+    0x87, 0x50, 0x47, 0x2A, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x0B, 0xA2, 0x2A, 0xD8, 0xB4, 0xA2, 0x0E,
+    0xD9, 0xB4, 0xA2, 0x06, 0x67, 0x2A, 0x87, 0xB1, 0x47, 0x2B, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x10,
+    0xA2, 0x2A, 0xD8, 0xB4, 0xA2, 0x12, 0xD9, 0xB4, 0xA2, 0x06, 0x66, 0x78, 0x67, 0x1F, 0x87, 0x62,
+    0x47, 0x18, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x15, 0xA2, 0x2A, 0xD8, 0xB4, 0xA2, 0x16, 0xD9, 0xB4,
+    0xA2, 0x06, 0x66, 0x78, 0x67, 0x1F, 0x87, 0x63, 0x47, 0x67, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x1A,
+    0xA2, 0x2A, 0xD8, 0xB4, 0xA2, 0x1A, 0xD9, 0xB4, 0xA2, 0x06, 0x66, 0x8C, 0x67, 0x8C, 0x87, 0x64,
+    0x47, 0x18, 0xA2, 0x02, 0xDA, 0xB4, 0x68, 0x2C, 0x69, 0x30, 0x6A, 0x34, 0x6B, 0x01, 0xA2, 0x2A,
+    0xD8, 0xB4, 0xA2, 0x1E, 0xD9, 0xB4, 0xA2, 0x06, 0x66, 0x8C, 0x67, 0x78, 0x87, 0x65, 0x47, 0xEC,
+    0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x06, 0xA2, 0x2A, 0xD8, 0xB4, 0xA2, 0x22, 0xD9, 0xB4, 0xA2, 0x06,
+    0x66, 0xE0, 0x86, 0x6E, 0x46, 0xC0, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x0B, 0xA2, 0x2A, 0xD8, 0xB4,
+    0xA2, 0x36, 0xD9, 0xB4, 0xA2, 0x06, 0x66, 0x0F, 0x86, 0x66, 0x46, 0x07, 0xA2, 0x02, 0xDA, 0xB4,
+    0x6B, 0x10, 0xA2, 0x3A, 0xD8, 0xB4, 0xA2, 0x1E, 0xD9, 0xB4, 0xA3, 0xE8, 0x60, 0x00, 0x61, 0x30,
+    0xF1, 0x55, 0xA3, 0xE9, 0xF0, 0x65, 0xA2, 0x06, 0x40, 0x30, 0xA2, 0x02, 0xDA, 0xB4, 0x6B, 0x15,
+    0xA2, 0x3A, 0xD8, 0xB4, 0xA2, 0x16, 0xD9, 0xB4, 0xA3, 0xE8, 0x66, 0x89, 0xF6, 0x33, 0xF2, 0x65,
+    0xA2, 0x02, 0x30, 0x01, 0xA2, 0x06, 0x31, 0x03, 0xA2, 0x06, 0x32, 0x07, 0xA2, 0x06, 0xDA, 0xB4,
+    0x6B, 0x1A, 0xA2, 0x0E, 0xD8, 0xB4, 0xA2, 0x3E, 0xD9, 0xB4, 0x12, 0x48, 0x13, 0xDC,
+];
+
+// bit per memory byte holding a recompiled opcode
+static CODE: [u64; 64] = [
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000003, 0xFFFFFFFFFFFFFFFC, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF, 0x000000003FFFFFFF,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+];
+
+pub struct Machine {
+    // storage
+    memory: [u8; 4096],
+    register: [u8; 16],
+    stack: [u16; 16],
+
+    // counters
+    program_counter: u16,
+    index_register: u16,
+    stack_pointer: u8,
+
+    // timers
+    sound_timer: u8,
+    delay_timer: u8,
+
+    // cleared once the rom stores over its own code
+    native: bool,
+
+    // hardware
+    pub display: Display,
+    pub keyboard: Keyboard,
+}
+
+impl Default for Machine {
+    fn default() -> Machine {
+        Machine::new()
+    }
+}
+
+impl Machine {
+    pub fn new() -> Machine {
+        let mut memory = [0; 4096];
+        memory[..FONT.len()].copy_from_slice(&FONT);
+        memory[0x200..0x200 + ROM.len()].copy_from_slice(&ROM);
+        Machine {
+            memory,
+            register: [0; 16],
+            stack: [0; 16],
+            program_counter: 0x200,
+            index_register: 0,
+            stack_pointer: 0,
+            sound_timer: 0,
+            delay_timer: 0,
+            native: true,
+            display: Display::new(),
+            keyboard: Keyboard::new(),
+        }
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.register
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn stack_pointer(&self) -> u8 {
+        self.stack_pointer
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn memory(&self) -> &[u8; 4096] {
+        &self.memory
+    }
+
+    // runs a 60Hz frame, the timers tick once after `instructions` instructions
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Error> {
+        let mut remaining = instructions;
+        while remaining > 0 {
+            remaining -= self.dispatch(remaining)?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    // executes a single instruction without touching the timers
+    pub fn step(&mut self) -> Result<(), Error> {
+        self.interpret()
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    // memory index `offset` bytes past I, wrapping at the end of memory
+    fn address(&self, offset: usize) -> usize {
+        (self.index_register as usize + offset) & 0xFFF
+    }
+
+    // leaves the recompiled blocks for the interpreter when the `length`
+    // bytes just stored at I hold code
+    fn stored(&mut self, length: usize) {
+        for offset in 0..length {
+            let address = self.address(offset);
+            if (CODE[address / 64] >> (address % 64)) & 1 == 1 {
+                self.native = false;
+            }
+        }
+    }
+
+    // decodes and runs the opcode at the program counter, for addresses the
+    // recompiler found no block at and code the rom rewrote
+    fn interpret(&mut self) -> Result<(), Error> {
+        let pc = self.program_counter;
+        let opcode = (self.memory[pc as usize] as u16) << 8 | self.memory[((pc + 1) & 0xFFF) as usize] as u16;
+
+        let nnn = opcode & 0x0FFF;
+        let nn = (opcode & 0x00FF) as u8;
+        let n = (opcode & 0x000F) as usize;
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        let vx = self.register[x];
+        let vy = self.register[y];
+        let mut next = (pc + 2) & 0xFFF;
+
+        match (opcode >> 12, x, y, n) {
+            (0x0, 0x0, 0xE, 0x0) => self.display.clear(),
+            (0x0, 0x0, 0xE, 0xE) => {
+                if self.stack_pointer == 0 {
+                    return Err(Error::StackUnderflow { pc });
+                }
+                self.stack_pointer -= 1;
+                next = self.stack[self.stack_pointer as usize];
+            },
+            (0x1, _, _, _) => next = nnn,
+            (0x2, _, _, _) => {
+                if self.stack_pointer as usize == self.stack.len() {
+                    return Err(Error::StackOverflow { pc });
+                }
+                self.stack[self.stack_pointer as usize] = next;
+                self.stack_pointer += 1;
+                next = nnn;
+            },
+            (0x3, _, _, _) => next += if vx == nn { 2 } else { 0 },
+            (0x4, _, _, _) => next += if vx != nn { 2 } else { 0 },
+            (0x5, _, _, 0x0) => next += if vx == vy { 2 } else { 0 },
+            (0x6, _, _, _) => self.register[x] = nn,
+            (0x7, _, _, _) => self.register[x] = vx.wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.register[x] = vy,
+            (0x8, _, _, 0x1) | (0x8, _, _, 0x2) | (0x8, _, _, 0x3) => {
+                self.register[x] = match n {
+                    0x1 => vx | vy,
+                    0x2 => vx & vy,
+                    _ => vx ^ vy,
+                };
+                if LOGIC_RESETS_VF {
+                    self.register[0xF] = 0;
+                }
+            },
+            (0x8, _, _, 0x4) => {
+                let (result, overflow) = vx.overflowing_add(vy);
+                self.register[0xF] = overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0x5) => {
+                let (result, overflow) = vx.overflowing_sub(vy);
+                self.register[0xF] = !overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0x6) => {
+                let source = if SHIFT_USES_VY { vy } else { vx };
+                self.register[0xF] = source & 0x1;
+                self.register[x] = source >> 1;
+            },
+            (0x8, _, _, 0x7) => {
+                let (result, overflow) = vy.overflowing_sub(vx);
+                self.register[0xF] = !overflow as u8;
+                self.register[x] = result;
+            },
+            (0x8, _, _, 0xE) => {
+                let source = if SHIFT_USES_VY { vy } else { vx };
+                self.register[0xF] = source >> 7;
+                self.register[x] = source << 1;
+            },
+            (0x9, _, _, 0x0) => next += if vx != vy { 2 } else { 0 },
+            (0xA, _, _, _) => self.index_register = nnn,
+            (0xB, _, _, _) => {
+                let offset = if JUMP_USES_VX { vx } else { self.register[0] };
+                next = nnn + offset as u16;
+            },
+            (0xC, _, _, _) => self.register[x] = nn & rand::random::<u8>(),
+            (0xD, _, _, _) => {
+                let mut sprite = [0; 15];
+                for (row, byte) in sprite.iter_mut().enumerate().take(n) {
+                    *byte = self.memory[self.address(row)];
+                }
+                let collision = if CLIP_SPRITES {
+                    self.display.draw_clipped(vx, vy, &sprite[..n])
+                } else {
+                    self.display.draw(vx, vy, &sprite[..n])
+                };
+                self.register[0xF] = collision as u8;
+            },
+            (0xE, _, 0x9, 0xE) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 2 } else { 0 },
+            (0xE, _, 0xA, 0x1) => next += if self.keyboard.pressed((vx & 0xF) as usize) { 0 } else { 2 },
+            (0xF, _, 0x0, 0x7) => self.register[x] = self.delay_timer,
+            (0xF, _, 0x0, 0xA) => {
+                // stay on this opcode while no key is pressed
+                next = pc;
+                for key in 0..16 {
+                    if self.keyboard.pressed(key) {
+                        self.register[x] = key as u8;
+                        next = pc + 2;
+                    }
+                }
+            },
+            (0xF, _, 0x1, 0x5) => self.delay_timer = vx,
+            (0xF, _, 0x1, 0x8) => self.sound_timer = vx,
+            (0xF, _, 0x1, 0xE) => self.index_register = (self.index_register + vx as u16) & 0xFFF,
+            (0xF, _, 0x2, 0x9) => self.index_register = vx as u16 * 5,
+            (0xF, _, 0x3, 0x3) => {
+                self.memory[self.address(0)] = vx / 100;
+                self.memory[self.address(1)] = (vx / 10) % 10;
+                self.memory[self.address(2)] = vx % 10;
+                self.stored(3);
+            },
+            (0xF, _, 0x5, 0x5) => {
+                for offset in 0..=x {
+                    self.memory[self.address(offset)] = self.register[offset];
+                }
+                self.stored(x + 1);
+                if LOAD_STORE_INCREMENTS_I {
+                    self.index_register = (self.index_register + x as u16 + 1) & 0xFFF;
+                }
+            },
+            (0xF, _, 0x6, 0x5) => {
+                for offset in 0..=x {
+                    self.register[offset] = self.memory[self.address(offset)];
+                }
+                if LOAD_STORE_INCREMENTS_I {
+                    self.index_register = (self.index_register + x as u16 + 1) & 0xFFF;
+                }
+            },
+            (_, _, _, _) => return Err(Error::UnknownOpcode { opcode, pc }),
+        }
+
+        // jumps and skips also wrap at the end of memory
+        self.program_counter = next & 0xFFF;
+        Ok(())
+    }
+
+    // runs the block recompiled at the program counter when it fits in
+    // `remaining` instructions, otherwise interprets one, and returns how
+    // many instructions ran
+    fn dispatch(&mut self, remaining: usize) -> Result<usize, Error> {
+        if !self.native {
+            return self.interpret().map(|_| 1);
+        }
+        match self.program_counter {
+            0x200 if remaining >= 13 => self.block_200(),
+            0x242 if remaining >= 3 => self.block_242(),
+            0x266 if remaining >= 9 => self.block_266(),
+            0x268 if remaining >= 8 => self.block_268(),
+            0x278 if remaining >= 9 => self.block_278(),
+            0x27A if remaining >= 8 => self.block_27a(),
+            0x28A if remaining >= 10 => self.block_28a(),
+            0x28C if remaining >= 9 => self.block_28c(),
+            0x29E if remaining >= 9 => self.block_29e(),
+            0x2A0 if remaining >= 8 => self.block_2a0(),
+            0x2B0 if remaining >= 8 => self.block_2b0(),
+            0x2B2 if remaining >= 7 => self.block_2b2(),
+            0x2C0 if remaining >= 18 => self.block_2c0(),
+            0x2E4 if remaining >= 11 => self.block_2e4(),
+            0x2E6 if remaining >= 10 => self.block_2e6(),
+            0x2FA if remaining >= 12 => self.block_2fa(),
+            0x2FC if remaining >= 11 => self.block_2fc(),
+            0x312 if remaining >= 12 => self.block_312(),
+            0x314 if remaining >= 11 => self.block_314(),
+            0x32A if remaining >= 12 => self.block_32a(),
+            0x32C if remaining >= 11 => self.block_32c(),
+            0x342 if remaining >= 15 => self.block_342(),
+            0x344 if remaining >= 14 => self.block_344(),
+            0x360 if remaining >= 11 => self.block_360(),
+            0x362 if remaining >= 10 => self.block_362(),
+            0x376 if remaining >= 11 => self.block_376(),
+            0x378 if remaining >= 10 => self.block_378(),
+            0x38C if remaining >= 11 => self.block_38c(),
+            0x38E if remaining >= 10 => self.block_38e(),
+            0x3A2 if remaining >= 4 => self.block_3a2(),
+            0x3AA if remaining >= 10 => self.block_3aa(),
+            0x3AC if remaining >= 9 => self.block_3ac(),
+            0x3BE if remaining >= 3 => self.block_3be(),
+            0x3C4 if remaining >= 2 => self.block_3c4(),
+            0x3C6 if remaining >= 1 => self.block_3c6(),
+            0x3C8 if remaining >= 2 => self.block_3c8(),
+            0x3CA if remaining >= 1 => self.block_3ca(),
+            0x3CC if remaining >= 12 => self.block_3cc(),
+            0x3CE if remaining >= 11 => self.block_3ce(),
+            0x3DC if remaining >= 1 => self.block_3dc(),
+            _ => self.interpret().map(|_| 1),
+        }
+    }
+
+    fn block_200(&mut self) -> Result<usize, Error> {
+        // 0x200 JP 0x24E
+        // 0x24E LD V8, 0x01
+        self.register[0x8] = 0x01;
+        // 0x250 LD V9, 0x05
+        self.register[0x9] = 0x05;
+        // 0x252 LD VA, 0x0A
+        self.register[0xA] = 0x0A;
+        // 0x254 LD VB, 0x01
+        self.register[0xB] = 0x01;
+        // 0x256 LD V5, 0x2A
+        self.register[0x5] = 0x2A;
+        // 0x258 LD V6, 0x2B
+        self.register[0x6] = 0x2B;
+        // 0x25A LD I, 0x216
+        self.index_register = 0x216;
+        // 0x25C DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x25E LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x260 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x262 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x264 SE V6, 0x2B
+        self.program_counter = if self.register[0x6] == 0x2B { 0x268 } else { 0x266 };
+        Ok(13)
+    }
+
+    fn block_242(&mut self) -> Result<usize, Error> {
+        // 0x242 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x244 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x246 RET
+        if self.stack_pointer == 0 {
+            self.program_counter = 0x246;
+            return Err(Error::StackUnderflow { pc: 0x246 });
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(3)
+    }
+
+    fn block_266(&mut self) -> Result<usize, Error> {
+        // 0x266 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x268 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x26A LD VB, 0x06
+        self.register[0xB] = 0x06;
+        // 0x26C LD I, 0x21A
+        self.index_register = 0x21A;
+        // 0x26E DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x270 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x272 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x274 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x276 SNE V5, 0x2A
+        self.program_counter = if self.register[0x5] != 0x2A { 0x27A } else { 0x278 };
+        Ok(9)
+    }
+
+    fn block_268(&mut self) -> Result<usize, Error> {
+        // 0x268 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x26A LD VB, 0x06
+        self.register[0xB] = 0x06;
+        // 0x26C LD I, 0x21A
+        self.index_register = 0x21A;
+        // 0x26E DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x270 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x272 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x274 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x276 SNE V5, 0x2A
+        self.program_counter = if self.register[0x5] != 0x2A { 0x27A } else { 0x278 };
+        Ok(8)
+    }
+
+    fn block_278(&mut self) -> Result<usize, Error> {
+        // 0x278 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x27A DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x27C LD VB, 0x0B
+        self.register[0xB] = 0x0B;
+        // 0x27E LD I, 0x21E
+        self.index_register = 0x21E;
+        // 0x280 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x282 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x284 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x286 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x288 SE V5, V6
+        self.program_counter = if self.register[0x5] == self.register[0x6] { 0x28C } else { 0x28A };
+        Ok(9)
+    }
+
+    fn block_27a(&mut self) -> Result<usize, Error> {
+        // 0x27A DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x27C LD VB, 0x0B
+        self.register[0xB] = 0x0B;
+        // 0x27E LD I, 0x21E
+        self.index_register = 0x21E;
+        // 0x280 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x282 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x284 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x286 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x288 SE V5, V6
+        self.program_counter = if self.register[0x5] == self.register[0x6] { 0x28C } else { 0x28A };
+        Ok(8)
+    }
+
+    fn block_28a(&mut self) -> Result<usize, Error> {
+        // 0x28A LD I, 0x202
+        self.index_register = 0x202;
+        // 0x28C DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x28E LD VB, 0x10
+        self.register[0xB] = 0x10;
+        // 0x290 LD I, 0x226
+        self.index_register = 0x226;
+        // 0x292 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x294 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x296 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x298 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x29A ADD V6, 0xFF
+        self.register[0x6] = self.register[0x6].wrapping_add(0xFF);
+        // 0x29C SNE V6, 0x2A
+        self.program_counter = if self.register[0x6] != 0x2A { 0x2A0 } else { 0x29E };
+        Ok(10)
+    }
+
+    fn block_28c(&mut self) -> Result<usize, Error> {
+        // 0x28C DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x28E LD VB, 0x10
+        self.register[0xB] = 0x10;
+        // 0x290 LD I, 0x226
+        self.index_register = 0x226;
+        // 0x292 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x294 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x296 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x298 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x29A ADD V6, 0xFF
+        self.register[0x6] = self.register[0x6].wrapping_add(0xFF);
+        // 0x29C SNE V6, 0x2A
+        self.program_counter = if self.register[0x6] != 0x2A { 0x2A0 } else { 0x29E };
+        Ok(9)
+    }
+
+    fn block_29e(&mut self) -> Result<usize, Error> {
+        // 0x29E LD I, 0x202
+        self.index_register = 0x202;
+        // 0x2A0 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2A2 LD VB, 0x15
+        self.register[0xB] = 0x15;
+        // 0x2A4 LD I, 0x22E
+        self.index_register = 0x22E;
+        // 0x2A6 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2A8 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x2AA DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2AC LD I, 0x206
+        self.index_register = 0x206;
+        // 0x2AE SNE V5, V6
+        self.program_counter = if self.register[0x5] != self.register[0x6] { 0x2B2 } else { 0x2B0 };
+        Ok(9)
+    }
+
+    fn block_2a0(&mut self) -> Result<usize, Error> {
+        // 0x2A0 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2A2 LD VB, 0x15
+        self.register[0xB] = 0x15;
+        // 0x2A4 LD I, 0x22E
+        self.index_register = 0x22E;
+        // 0x2A6 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2A8 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x2AA DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2AC LD I, 0x206
+        self.index_register = 0x206;
+        // 0x2AE SNE V5, V6
+        self.program_counter = if self.register[0x5] != self.register[0x6] { 0x2B2 } else { 0x2B0 };
+        Ok(8)
+    }
+
+    fn block_2b0(&mut self) -> Result<usize, Error> {
+        // 0x2B0 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x2B2 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2B4 LD VB, 0x1A
+        self.register[0xB] = 0x1A;
+        // 0x2B6 LD I, 0x232
+        self.index_register = 0x232;
+        // 0x2B8 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2BA LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x2BC DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2BE CALL 0x242
+        if self.stack_pointer as usize == self.stack.len() {
+            self.program_counter = 0x2BE;
+            return Err(Error::StackOverflow { pc: 0x2BE });
+        }
+        self.stack[self.stack_pointer as usize] = 0x2C0;
+        self.stack_pointer += 1;
+        self.program_counter = 0x242;
+        Ok(8)
+    }
+
+    fn block_2b2(&mut self) -> Result<usize, Error> {
+        // 0x2B2 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2B4 LD VB, 0x1A
+        self.register[0xB] = 0x1A;
+        // 0x2B6 LD I, 0x232
+        self.index_register = 0x232;
+        // 0x2B8 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2BA LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x2BC DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2BE CALL 0x242
+        if self.stack_pointer as usize == self.stack.len() {
+            self.program_counter = 0x2BE;
+            return Err(Error::StackOverflow { pc: 0x2BE });
+        }
+        self.stack[self.stack_pointer as usize] = 0x2C0;
+        self.stack_pointer += 1;
+        self.program_counter = 0x242;
+        Ok(7)
+    }
+
+    fn block_2c0(&mut self) -> Result<usize, Error> {
+        // 0x2C0 LD V8, 0x17
+        self.register[0x8] = 0x17;
+        // 0x2C2 LD V9, 0x1B
+        self.register[0x9] = 0x1B;
+        // 0x2C4 LD VA, 0x20
+        self.register[0xA] = 0x20;
+        // 0x2C6 LD VB, 0x01
+        self.register[0xB] = 0x01;
+        // 0x2C8 LD I, 0x20A
+        self.index_register = 0x20A;
+        // 0x2CA DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2CC LD I, 0x236
+        self.index_register = 0x236;
+        // 0x2CE DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2D0 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x2D2 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2D4 LD VB, 0x06
+        self.register[0xB] = 0x06;
+        // 0x2D6 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x2D8 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2DA LD I, 0x20A
+        self.index_register = 0x20A;
+        // 0x2DC DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2DE LD I, 0x206
+        self.index_register = 0x206;
+        // 0x2E0 LD V7, V5
+        self.register[0x7] = self.register[0x5];
+        // 0x2E2 SNE V7, 0x2A
+        self.program_counter = if self.register[0x7] != 0x2A { 0x2E6 } else { 0x2E4 };
+        Ok(18)
+    }
+
+    fn block_2e4(&mut self) -> Result<usize, Error> {
+        // 0x2E4 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x2E6 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2E8 LD VB, 0x0B
+        self.register[0xB] = 0x0B;
+        // 0x2EA LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x2EC DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2EE LD I, 0x20E
+        self.index_register = 0x20E;
+        // 0x2F0 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2F2 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x2F4 LD V7, 0x2A
+        self.register[0x7] = 0x2A;
+        // 0x2F6 OR V7, VB
+        let (vx, vy) = (self.register[0x7], self.register[0xB]);
+        self.register[0x7] = vx | vy;
+        // 0x2F8 SNE V7, 0x2B
+        self.program_counter = if self.register[0x7] != 0x2B { 0x2FC } else { 0x2FA };
+        Ok(11)
+    }
+
+    fn block_2e6(&mut self) -> Result<usize, Error> {
+        // 0x2E6 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2E8 LD VB, 0x0B
+        self.register[0xB] = 0x0B;
+        // 0x2EA LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x2EC DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2EE LD I, 0x20E
+        self.index_register = 0x20E;
+        // 0x2F0 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2F2 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x2F4 LD V7, 0x2A
+        self.register[0x7] = 0x2A;
+        // 0x2F6 OR V7, VB
+        let (vx, vy) = (self.register[0x7], self.register[0xB]);
+        self.register[0x7] = vx | vy;
+        // 0x2F8 SNE V7, 0x2B
+        self.program_counter = if self.register[0x7] != 0x2B { 0x2FC } else { 0x2FA };
+        Ok(10)
+    }
+
+    fn block_2fa(&mut self) -> Result<usize, Error> {
+        // 0x2FA LD I, 0x202
+        self.index_register = 0x202;
+        // 0x2FC DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2FE LD VB, 0x10
+        self.register[0xB] = 0x10;
+        // 0x300 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x302 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x304 LD I, 0x212
+        self.index_register = 0x212;
+        // 0x306 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x308 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x30A LD V6, 0x78
+        self.register[0x6] = 0x78;
+        // 0x30C LD V7, 0x1F
+        self.register[0x7] = 0x1F;
+        // 0x30E AND V7, V6
+        let (vx, vy) = (self.register[0x7], self.register[0x6]);
+        self.register[0x7] = vx & vy;
+        // 0x310 SNE V7, 0x18
+        self.program_counter = if self.register[0x7] != 0x18 { 0x314 } else { 0x312 };
+        Ok(12)
+    }
+
+    fn block_2fc(&mut self) -> Result<usize, Error> {
+        // 0x2FC DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x2FE LD VB, 0x10
+        self.register[0xB] = 0x10;
+        // 0x300 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x302 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x304 LD I, 0x212
+        self.index_register = 0x212;
+        // 0x306 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x308 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x30A LD V6, 0x78
+        self.register[0x6] = 0x78;
+        // 0x30C LD V7, 0x1F
+        self.register[0x7] = 0x1F;
+        // 0x30E AND V7, V6
+        let (vx, vy) = (self.register[0x7], self.register[0x6]);
+        self.register[0x7] = vx & vy;
+        // 0x310 SNE V7, 0x18
+        self.program_counter = if self.register[0x7] != 0x18 { 0x314 } else { 0x312 };
+        Ok(11)
+    }
+
+    fn block_312(&mut self) -> Result<usize, Error> {
+        // 0x312 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x314 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x316 LD VB, 0x15
+        self.register[0xB] = 0x15;
+        // 0x318 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x31A DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x31C LD I, 0x216
+        self.index_register = 0x216;
+        // 0x31E DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x320 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x322 LD V6, 0x78
+        self.register[0x6] = 0x78;
+        // 0x324 LD V7, 0x1F
+        self.register[0x7] = 0x1F;
+        // 0x326 XOR V7, V6
+        let (vx, vy) = (self.register[0x7], self.register[0x6]);
+        self.register[0x7] = vx ^ vy;
+        // 0x328 SNE V7, 0x67
+        self.program_counter = if self.register[0x7] != 0x67 { 0x32C } else { 0x32A };
+        Ok(12)
+    }
+
+    fn block_314(&mut self) -> Result<usize, Error> {
+        // 0x314 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x316 LD VB, 0x15
+        self.register[0xB] = 0x15;
+        // 0x318 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x31A DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x31C LD I, 0x216
+        self.index_register = 0x216;
+        // 0x31E DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x320 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x322 LD V6, 0x78
+        self.register[0x6] = 0x78;
+        // 0x324 LD V7, 0x1F
+        self.register[0x7] = 0x1F;
+        // 0x326 XOR V7, V6
+        let (vx, vy) = (self.register[0x7], self.register[0x6]);
+        self.register[0x7] = vx ^ vy;
+        // 0x328 SNE V7, 0x67
+        self.program_counter = if self.register[0x7] != 0x67 { 0x32C } else { 0x32A };
+        Ok(11)
+    }
+
+    fn block_32a(&mut self) -> Result<usize, Error> {
+        // 0x32A LD I, 0x202
+        self.index_register = 0x202;
+        // 0x32C DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x32E LD VB, 0x1A
+        self.register[0xB] = 0x1A;
+        // 0x330 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x332 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x334 LD I, 0x21A
+        self.index_register = 0x21A;
+        // 0x336 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x338 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x33A LD V6, 0x8C
+        self.register[0x6] = 0x8C;
+        // 0x33C LD V7, 0x8C
+        self.register[0x7] = 0x8C;
+        // 0x33E ADD V7, V6
+        let (result, overflow) = self.register[0x7].overflowing_add(self.register[0x6]);
+        self.register[0xF] = overflow as u8;
+        self.register[0x7] = result;
+        // 0x340 SNE V7, 0x18
+        self.program_counter = if self.register[0x7] != 0x18 { 0x344 } else { 0x342 };
+        Ok(12)
+    }
+
+    fn block_32c(&mut self) -> Result<usize, Error> {
+        // 0x32C DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x32E LD VB, 0x1A
+        self.register[0xB] = 0x1A;
+        // 0x330 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x332 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x334 LD I, 0x21A
+        self.index_register = 0x21A;
+        // 0x336 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x338 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x33A LD V6, 0x8C
+        self.register[0x6] = 0x8C;
+        // 0x33C LD V7, 0x8C
+        self.register[0x7] = 0x8C;
+        // 0x33E ADD V7, V6
+        let (result, overflow) = self.register[0x7].overflowing_add(self.register[0x6]);
+        self.register[0xF] = overflow as u8;
+        self.register[0x7] = result;
+        // 0x340 SNE V7, 0x18
+        self.program_counter = if self.register[0x7] != 0x18 { 0x344 } else { 0x342 };
+        Ok(11)
+    }
+
+    fn block_342(&mut self) -> Result<usize, Error> {
+        // 0x342 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x344 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x346 LD V8, 0x2C
+        self.register[0x8] = 0x2C;
+        // 0x348 LD V9, 0x30
+        self.register[0x9] = 0x30;
+        // 0x34A LD VA, 0x34
+        self.register[0xA] = 0x34;
+        // 0x34C LD VB, 0x01
+        self.register[0xB] = 0x01;
+        // 0x34E LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x350 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x352 LD I, 0x21E
+        self.index_register = 0x21E;
+        // 0x354 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x356 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x358 LD V6, 0x8C
+        self.register[0x6] = 0x8C;
+        // 0x35A LD V7, 0x78
+        self.register[0x7] = 0x78;
+        // 0x35C SUB V7, V6
+        let (result, overflow) = self.register[0x7].overflowing_sub(self.register[0x6]);
+        self.register[0xF] = !overflow as u8;
+        self.register[0x7] = result;
+        // 0x35E SNE V7, 0xEC
+        self.program_counter = if self.register[0x7] != 0xEC { 0x362 } else { 0x360 };
+        Ok(15)
+    }
+
+    fn block_344(&mut self) -> Result<usize, Error> {
+        // 0x344 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x346 LD V8, 0x2C
+        self.register[0x8] = 0x2C;
+        // 0x348 LD V9, 0x30
+        self.register[0x9] = 0x30;
+        // 0x34A LD VA, 0x34
+        self.register[0xA] = 0x34;
+        // 0x34C LD VB, 0x01
+        self.register[0xB] = 0x01;
+        // 0x34E LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x350 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x352 LD I, 0x21E
+        self.index_register = 0x21E;
+        // 0x354 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x356 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x358 LD V6, 0x8C
+        self.register[0x6] = 0x8C;
+        // 0x35A LD V7, 0x78
+        self.register[0x7] = 0x78;
+        // 0x35C SUB V7, V6
+        let (result, overflow) = self.register[0x7].overflowing_sub(self.register[0x6]);
+        self.register[0xF] = !overflow as u8;
+        self.register[0x7] = result;
+        // 0x35E SNE V7, 0xEC
+        self.program_counter = if self.register[0x7] != 0xEC { 0x362 } else { 0x360 };
+        Ok(14)
+    }
+
+    fn block_360(&mut self) -> Result<usize, Error> {
+        // 0x360 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x362 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x364 LD VB, 0x06
+        self.register[0xB] = 0x06;
+        // 0x366 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x368 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x36A LD I, 0x222
+        self.index_register = 0x222;
+        // 0x36C DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x36E LD I, 0x206
+        self.index_register = 0x206;
+        // 0x370 LD V6, 0xE0
+        self.register[0x6] = 0xE0;
+        // 0x372 SHL V6
+        let source = self.register[0x6];
+        self.register[0xF] = source >> 7;
+        self.register[0x6] = source << 1;
+        // 0x374 SNE V6, 0xC0
+        self.program_counter = if self.register[0x6] != 0xC0 { 0x378 } else { 0x376 };
+        Ok(11)
+    }
+
+    fn block_362(&mut self) -> Result<usize, Error> {
+        // 0x362 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x364 LD VB, 0x06
+        self.register[0xB] = 0x06;
+        // 0x366 LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x368 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x36A LD I, 0x222
+        self.index_register = 0x222;
+        // 0x36C DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x36E LD I, 0x206
+        self.index_register = 0x206;
+        // 0x370 LD V6, 0xE0
+        self.register[0x6] = 0xE0;
+        // 0x372 SHL V6
+        let source = self.register[0x6];
+        self.register[0xF] = source >> 7;
+        self.register[0x6] = source << 1;
+        // 0x374 SNE V6, 0xC0
+        self.program_counter = if self.register[0x6] != 0xC0 { 0x378 } else { 0x376 };
+        Ok(10)
+    }
+
+    fn block_376(&mut self) -> Result<usize, Error> {
+        // 0x376 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x378 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x37A LD VB, 0x0B
+        self.register[0xB] = 0x0B;
+        // 0x37C LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x37E DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x380 LD I, 0x236
+        self.index_register = 0x236;
+        // 0x382 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x384 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x386 LD V6, 0x0F
+        self.register[0x6] = 0x0F;
+        // 0x388 SHR V6
+        let source = self.register[0x6];
+        self.register[0xF] = source & 0x1;
+        self.register[0x6] = source >> 1;
+        // 0x38A SNE V6, 0x07
+        self.program_counter = if self.register[0x6] != 0x07 { 0x38E } else { 0x38C };
+        Ok(11)
+    }
+
+    fn block_378(&mut self) -> Result<usize, Error> {
+        // 0x378 DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x37A LD VB, 0x0B
+        self.register[0xB] = 0x0B;
+        // 0x37C LD I, 0x22A
+        self.index_register = 0x22A;
+        // 0x37E DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x380 LD I, 0x236
+        self.index_register = 0x236;
+        // 0x382 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x384 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x386 LD V6, 0x0F
+        self.register[0x6] = 0x0F;
+        // 0x388 SHR V6
+        let source = self.register[0x6];
+        self.register[0xF] = source & 0x1;
+        self.register[0x6] = source >> 1;
+        // 0x38A SNE V6, 0x07
+        self.program_counter = if self.register[0x6] != 0x07 { 0x38E } else { 0x38C };
+        Ok(10)
+    }
+
+    fn block_38c(&mut self) -> Result<usize, Error> {
+        // 0x38C LD I, 0x202
+        self.index_register = 0x202;
+        // 0x38E DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x390 LD VB, 0x10
+        self.register[0xB] = 0x10;
+        // 0x392 LD I, 0x23A
+        self.index_register = 0x23A;
+        // 0x394 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x396 LD I, 0x21E
+        self.index_register = 0x21E;
+        // 0x398 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x39A LD I, 0x3E8
+        self.index_register = 0x3E8;
+        // 0x39C LD V0, 0x00
+        self.register[0x0] = 0x00;
+        // 0x39E LD V1, 0x30
+        self.register[0x1] = 0x30;
+        // 0x3A0 LD [I], V1
+        self.memory[self.address(0)] = self.register[0x0];
+        self.memory[self.address(1)] = self.register[0x1];
+        self.stored(2);
+        self.program_counter = 0x3A2;
+        Ok(11)
+    }
+
+    fn block_38e(&mut self) -> Result<usize, Error> {
+        // 0x38E DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x390 LD VB, 0x10
+        self.register[0xB] = 0x10;
+        // 0x392 LD I, 0x23A
+        self.index_register = 0x23A;
+        // 0x394 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x396 LD I, 0x21E
+        self.index_register = 0x21E;
+        // 0x398 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x39A LD I, 0x3E8
+        self.index_register = 0x3E8;
+        // 0x39C LD V0, 0x00
+        self.register[0x0] = 0x00;
+        // 0x39E LD V1, 0x30
+        self.register[0x1] = 0x30;
+        // 0x3A0 LD [I], V1
+        self.memory[self.address(0)] = self.register[0x0];
+        self.memory[self.address(1)] = self.register[0x1];
+        self.stored(2);
+        self.program_counter = 0x3A2;
+        Ok(10)
+    }
+
+    fn block_3a2(&mut self) -> Result<usize, Error> {
+        // 0x3A2 LD I, 0x3E9
+        self.index_register = 0x3E9;
+        // 0x3A4 LD V0, [I]
+        self.register[0x0] = self.memory[self.address(0)];
+        // 0x3A6 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x3A8 SNE V0, 0x30
+        self.program_counter = if self.register[0x0] != 0x30 { 0x3AC } else { 0x3AA };
+        Ok(4)
+    }
+
+    fn block_3aa(&mut self) -> Result<usize, Error> {
+        // 0x3AA LD I, 0x202
+        self.index_register = 0x202;
+        // 0x3AC DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3AE LD VB, 0x15
+        self.register[0xB] = 0x15;
+        // 0x3B0 LD I, 0x23A
+        self.index_register = 0x23A;
+        // 0x3B2 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3B4 LD I, 0x216
+        self.index_register = 0x216;
+        // 0x3B6 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3B8 LD I, 0x3E8
+        self.index_register = 0x3E8;
+        // 0x3BA LD V6, 0x89
+        self.register[0x6] = 0x89;
+        // 0x3BC LD B, V6
+        let value = self.register[0x6];
+        self.memory[self.address(0)] = value / 100;
+        self.memory[self.address(1)] = (value / 10) % 10;
+        self.memory[self.address(2)] = value % 10;
+        self.stored(3);
+        self.program_counter = 0x3BE;
+        Ok(10)
+    }
+
+    fn block_3ac(&mut self) -> Result<usize, Error> {
+        // 0x3AC DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3AE LD VB, 0x15
+        self.register[0xB] = 0x15;
+        // 0x3B0 LD I, 0x23A
+        self.index_register = 0x23A;
+        // 0x3B2 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3B4 LD I, 0x216
+        self.index_register = 0x216;
+        // 0x3B6 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3B8 LD I, 0x3E8
+        self.index_register = 0x3E8;
+        // 0x3BA LD V6, 0x89
+        self.register[0x6] = 0x89;
+        // 0x3BC LD B, V6
+        let value = self.register[0x6];
+        self.memory[self.address(0)] = value / 100;
+        self.memory[self.address(1)] = (value / 10) % 10;
+        self.memory[self.address(2)] = value % 10;
+        self.stored(3);
+        self.program_counter = 0x3BE;
+        Ok(9)
+    }
+
+    fn block_3be(&mut self) -> Result<usize, Error> {
+        // 0x3BE LD V2, [I]
+        self.register[0x0] = self.memory[self.address(0)];
+        self.register[0x1] = self.memory[self.address(1)];
+        self.register[0x2] = self.memory[self.address(2)];
+        // 0x3C0 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x3C2 SE V0, 0x01
+        self.program_counter = if self.register[0x0] == 0x01 { 0x3C6 } else { 0x3C4 };
+        Ok(3)
+    }
+
+    fn block_3c4(&mut self) -> Result<usize, Error> {
+        // 0x3C4 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x3C6 SE V1, 0x03
+        self.program_counter = if self.register[0x1] == 0x03 { 0x3CA } else { 0x3C8 };
+        Ok(2)
+    }
+
+    fn block_3c6(&mut self) -> Result<usize, Error> {
+        // 0x3C6 SE V1, 0x03
+        self.program_counter = if self.register[0x1] == 0x03 { 0x3CA } else { 0x3C8 };
+        Ok(1)
+    }
+
+    fn block_3c8(&mut self) -> Result<usize, Error> {
+        // 0x3C8 LD I, 0x206
+        self.index_register = 0x206;
+        // 0x3CA SE V2, 0x07
+        self.program_counter = if self.register[0x2] == 0x07 { 0x3CE } else { 0x3CC };
+        Ok(2)
+    }
+
+    fn block_3ca(&mut self) -> Result<usize, Error> {
+        // 0x3CA SE V2, 0x07
+        self.program_counter = if self.register[0x2] == 0x07 { 0x3CE } else { 0x3CC };
+        Ok(1)
+    }
+
+    fn block_3cc(&mut self) -> Result<usize, Error> {
+        // 0x3CC LD I, 0x206
+        self.index_register = 0x206;
+        // 0x3CE DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3D0 LD VB, 0x1A
+        self.register[0xB] = 0x1A;
+        // 0x3D2 LD I, 0x20E
+        self.index_register = 0x20E;
+        // 0x3D4 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3D6 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x3D8 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3DA JP 0x248
+        // 0x248 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x24A DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x24C JP 0x3DC
+        // 0x3DC JP 0x3DC
+        self.program_counter = 0x3DC;
+        Ok(12)
+    }
+
+    fn block_3ce(&mut self) -> Result<usize, Error> {
+        // 0x3CE DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3D0 LD VB, 0x1A
+        self.register[0xB] = 0x1A;
+        // 0x3D2 LD I, 0x20E
+        self.index_register = 0x20E;
+        // 0x3D4 DRW V8, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x8], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3D6 LD I, 0x23E
+        self.index_register = 0x23E;
+        // 0x3D8 DRW V9, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0x9], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x3DA JP 0x248
+        // 0x248 LD I, 0x202
+        self.index_register = 0x202;
+        // 0x24A DRW VA, VB, 4
+        let mut sprite = [0; 4];
+        for (row, byte) in sprite.iter_mut().enumerate() {
+            *byte = self.memory[self.address(row)];
+        }
+        let collision = self.display.draw(self.register[0xA], self.register[0xB], &sprite);
+        self.register[0xF] = collision as u8;
+        // 0x24C JP 0x3DC
+        // 0x3DC JP 0x3DC
+        self.program_counter = 0x3DC;
+        Ok(11)
+    }
+
+    fn block_3dc(&mut self) -> Result<usize, Error> {
+        // 0x3DC JP 0x3DC
+        self.program_counter = 0x3DC;
+        Ok(1)
+    }
+}
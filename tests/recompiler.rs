@@ -0,0 +1,100 @@
+// the modules under tests/recompiled were written by `chip_8 recompile`,
+// these tests keep them in step with the recompiler and run them in lockstep
+// with the interpreter
+
+use chip_8::database::Database;
+use chip_8::processor::Processor;
+use chip_8::quirks::Quirks;
+use chip_8::recompiler::recompile;
+
+#[allow(dead_code)]
+#[path = "recompiled/program.rs"]
+mod program;
+#[allow(dead_code)]
+#[path = "recompiled/test_opcode.rs"]
+mod test_opcode;
+
+const TEST_OPCODE: &[u8] = include_bytes!("../roms/test_opcode.ch8");
+
+// jumps through a BNNN table into a subroutine drawing digits five times,
+// stores over its next opcode, stores the digits of 254 and loads them, then
+// waits for keys
+const PROGRAM: &[u8] = &[
+    0x60, 0x04, 0xB2, 0x06, 0x00, 0x00, 0x13, 0x00, 0x13, 0x00, 0x22, 0x30, 0x71, 0x01, 0x31, 0x05,
+    0x12, 0x0A, 0x60, 0x65, 0x61, 0x77, 0xA2, 0x20, 0xF1, 0x55, 0x12, 0x20, 0x00, 0x00, 0x00, 0x00,
+    0x65, 0x01, 0x63, 0xFE, 0xA3, 0x00, 0xF3, 0x33, 0xF2, 0x65, 0xF4, 0x0A, 0x12, 0x2A, 0x00, 0x00,
+    0xF1, 0x29, 0x6A, 0x08, 0x8B, 0x10, 0x8B, 0xA4, 0xDA, 0xB5, 0x8C, 0x13, 0x8C, 0xB6, 0x00, 0xEE,
+];
+
+fn interpreter(rom: &[u8], quirks: Quirks) -> Processor {
+    let mut cpu = Processor::new();
+    cpu.reset();
+    cpu.set_quirks(quirks);
+    cpu.load_program(rom).unwrap();
+    cpu
+}
+
+// runs frames of `instructions(frame)` instructions on both and compares
+// the whole machine after each
+macro_rules! assert_lockstep {
+    ($machine:expr, $cpu:expr, $frames:expr, $instructions:expr) => {
+        let (mut machine, mut cpu) = ($machine, $cpu);
+        let instructions: fn(usize) -> usize = $instructions;
+        for frame in 0..$frames {
+            let instructions = instructions(frame);
+            assert_eq!(machine.run_frame(instructions).is_ok(), cpu.run_frame(instructions).is_ok(), "frame {} fails on both", frame);
+            assert_eq!(machine.registers(), cpu.registers(), "registers after frame {}", frame);
+            assert_eq!(machine.program_counter(), cpu.program_counter(), "program counter after frame {}", frame);
+            assert_eq!(machine.index_register(), cpu.index_register(), "I after frame {}", frame);
+            assert_eq!(machine.stack_pointer(), cpu.stack_pointer(), "stack pointer after frame {}", frame);
+            assert_eq!(machine.delay_timer(), cpu.delay_timer(), "delay timer after frame {}", frame);
+            assert_eq!(machine.sound_timer(), cpu.sound_timer(), "sound timer after frame {}", frame);
+            assert!(machine.memory()[..] == cpu.memory()[..], "memory after frame {}", frame);
+            assert_eq!(machine.display.rows(), cpu.display.rows(), "display after frame {}", frame);
+        }
+    };
+}
+
+#[test]
+fn recompiled_modules_are_current() {
+    let quirks = Database::bundled().lookup(TEST_OPCODE).quirks;
+    assert!(recompile(TEST_OPCODE, quirks).unwrap() == include_str!("recompiled/test_opcode.rs"), "test_opcode.rs is regenerated");
+    assert!(recompile(PROGRAM, Quirks::default()).unwrap() == include_str!("recompiled/program.rs"), "program.rs is regenerated");
+}
+
+#[test]
+fn recompiled_test_opcode_matches_interpreter() {
+    let quirks = Database::bundled().lookup(TEST_OPCODE).quirks;
+    // whole frames run nearly every opcode in a recompiled block, short ones
+    // split blocks between the blocks and the bundled interpreter
+    assert_lockstep!(test_opcode::Machine::new(), interpreter(TEST_OPCODE, quirks), 100, |_| 1000);
+    assert_lockstep!(test_opcode::Machine::new(), interpreter(TEST_OPCODE, quirks), 3000, |frame| 1 + frame % 23);
+}
+
+#[test]
+fn recompiled_program_matches_interpreter() {
+    assert_lockstep!(program::Machine::new(), interpreter(PROGRAM, Quirks::default()), 10, |_| 1000);
+    assert_lockstep!(program::Machine::new(), interpreter(PROGRAM, Quirks::default()), 200, |frame| 1 + frame % 23);
+
+    let mut machine = program::Machine::new();
+    for _ in 0..10 {
+        machine.run_frame(10).unwrap();
+    }
+    assert_eq!(machine.registers()[0x5], 0x77, "the opcode stored over the code runs");
+    assert_eq!(&machine.registers()[..3], [2, 5, 4], "the digits of 254 are loaded back");
+}
+
+#[test]
+fn recompiled_key_waits_match_interpreter() {
+    let (mut machine, mut cpu) = (program::Machine::new(), interpreter(PROGRAM, Quirks::default()));
+    machine.keyboard.key_press(0xF);
+    cpu.keyboard.key_press(0xF);
+    assert_lockstep!(machine, cpu, 40, |frame| 1 + frame % 7);
+
+    let mut machine = program::Machine::new();
+    machine.keyboard.key_press(0xF);
+    for _ in 0..20 {
+        machine.run_frame(10).unwrap();
+    }
+    assert_eq!(machine.registers()[0x4], 0xF, "key F ends the wait");
+}